@@ -0,0 +1,69 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use snowplow_tracker::{Emitter, Error, PayloadBuilder, StructuredEvent, Subject, Tracker};
+
+// An emitter that finalises and discards payloads, so only building the payload is measured
+struct DiscardingEmitter;
+
+impl Emitter for DiscardingEmitter {
+    fn add(&mut self, payload: PayloadBuilder) -> Result<(), Error> {
+        payload.finalise_payload().map(drop)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn collector_url(&self) -> &str {
+        ""
+    }
+}
+
+fn structured_event() -> StructuredEvent {
+    StructuredEvent::builder()
+        .category("shop")
+        .action("add-to-basket")
+        .label("shoes")
+        .property("size")
+        .value(42.5)
+        .subject(
+            Subject::builder()
+                .user_id("user_1")
+                .user_agent(
+                    "Mozilla/5.0 (X11; Linux x86_64; rv:109.0) Gecko/20100101 Firefox/115.0",
+                )
+                .ip_address("192.168.0.1")
+                .timezone("Europe/London")
+                .language("en-gb")
+                .build()
+                .unwrap(),
+        )
+        .build()
+        .unwrap()
+}
+
+fn track_structured_event(c: &mut Criterion) {
+    let mut tracker = Tracker::new("ns", "app_id", DiscardingEmitter, None);
+    let event = structured_event();
+
+    c.bench_function("track structured event", |b| {
+        b.iter(|| tracker.track(event.clone(), None).unwrap())
+    });
+}
+
+criterion_group!(benches, track_structured_event);
+criterion_main!(benches);
@@ -0,0 +1,117 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use derive_builder::Builder;
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::payload::SelfDescribingJson;
+
+/// The schema of the application context entity
+pub(crate) const APPLICATION_CONTEXT_SCHEMA: &str =
+    "iglu:com.snowplowanalytics.mobile/application/jsonschema/1-0-0";
+
+/// A context entity describing the version and build of the app tracking events
+///
+/// Attach it to every event with [Tracker::set_application_context](crate::Tracker::set_application_context).
+///
+/// ## Example
+/// ```
+/// use snowplow_tracker::ApplicationContext;
+///
+/// let application_context = ApplicationContext::builder()
+///     .version("2.1.0")
+///     .build_name("2104")
+///     .build()
+///     .unwrap();
+///
+/// // The version is required
+/// assert!(ApplicationContext::builder().version("").build_name("2104").build().is_err());
+/// ```
+#[derive(Serialize, Builder, Clone, Debug)]
+#[builder(setter(into))]
+#[builder(build_fn(validate = "Self::validate", error = "Error"))]
+pub struct ApplicationContext {
+    /// The version of the app, e.g. `2.1.0`.
+    pub version: String,
+
+    /// The build of the app, e.g. a build number. Set with `build_name` on the builder.
+    #[builder(setter(name = "build_name"))]
+    pub build: String,
+}
+
+impl ApplicationContextBuilder {
+    fn validate(&self) -> Result<(), Error> {
+        match &self.version {
+            Some(version) if version.is_empty() => Err(Error::BuilderError(
+                "Application version can't be empty".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl ApplicationContext {
+    pub fn builder() -> ApplicationContextBuilder {
+        ApplicationContextBuilder::default()
+    }
+
+    pub(crate) fn to_self_describing_json(&self) -> SelfDescribingJson {
+        // Serializing a struct of strings can't fail
+        SelfDescribingJson::new(
+            APPLICATION_CONTEXT_SCHEMA,
+            serde_json::to_value(self).unwrap_or_default(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn serializes_version_and_build() {
+        let application_context = ApplicationContext::builder()
+            .version("2.1.0")
+            .build_name("2104")
+            .build()
+            .unwrap();
+
+        let entity = application_context.to_self_describing_json();
+
+        assert_eq!(entity.schema, APPLICATION_CONTEXT_SCHEMA);
+        assert_eq!(entity.data, json!({ "version": "2.1.0", "build": "2104" }));
+    }
+
+    #[test]
+    fn empty_version_is_an_error() {
+        let result = ApplicationContext::builder()
+            .version("")
+            .build_name("2104")
+            .build();
+
+        assert!(matches!(result, Err(Error::BuilderError(_))));
+    }
+
+    #[test]
+    fn missing_fields_are_an_error() {
+        assert!(ApplicationContext::builder()
+            .version("2.1.0")
+            .build()
+            .is_err());
+        assert!(ApplicationContext::builder()
+            .build_name("2104")
+            .build()
+            .is_err());
+    }
+}
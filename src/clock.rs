@@ -0,0 +1,90 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+
+// `std::time::Instant` isn't available in browsers, where `web_time` uses `performance.now()` instead
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+pub(crate) use web_time::Instant;
+
+/// A source of the current time, used for the timestamps of events
+///
+/// The tracker and emitters use a [SystemClock] by default. Use a [FixedClock] for deterministic timestamps in tests.
+pub trait Clock: Send + Sync {
+    /// The current time
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// A [Clock] returning the system time
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A [Clock] returning a fixed time, which only changes when set or advanced
+///
+/// Clones share the time, so a clone can be kept to move the time of a clock passed to a [Tracker](crate::Tracker).
+///
+/// ## Example
+/// ```
+/// use chrono::{Duration, TimeZone, Utc};
+/// use snowplow_tracker::{Clock, FixedClock};
+///
+/// let clock = FixedClock::new(Utc.timestamp_millis_opt(1_000).unwrap());
+/// clock.advance(Duration::milliseconds(500));
+///
+/// assert_eq!(clock.now().timestamp_millis(), 1_500);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FixedClock {
+    now: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl FixedClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    /// Sets the time returned by the clock
+    pub fn set(&self, now: DateTime<Utc>) {
+        match self.now.lock() {
+            Ok(mut time) => *time = now,
+            Err(e) => log::error!("Failed to lock fixed clock: {e}"),
+        }
+    }
+
+    /// Moves the time returned by the clock forward
+    pub fn advance(&self, duration: Duration) {
+        match self.now.lock() {
+            Ok(mut time) => *time += duration,
+            Err(e) => log::error!("Failed to lock fixed clock: {e}"),
+        }
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        match self.now.lock() {
+            Ok(time) => *time,
+            Err(e) => *e.into_inner(),
+        }
+    }
+}
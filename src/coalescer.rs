@@ -0,0 +1,191 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::error::Error;
+use crate::payload::{PayloadBuilder, SelfDescribingJson};
+
+struct PendingEvent {
+    key: String,
+    event_id: Uuid,
+    payload: PayloadBuilder,
+    count: u64,
+    first_seen: DateTime<Utc>,
+}
+
+/// Suppresses consecutive identical events tracked within a time window.
+///
+/// The first event of a run is held back until a different event is tracked, an identical event is
+/// tracked after the window, or the coalescer is drained. It is then emitted once, with a context
+/// entity of `count_schema` holding the number of identical events it represents, if more than one.
+pub(crate) struct EventCoalescer {
+    window: Duration,
+    count_schema: String,
+    pending: Option<PendingEvent>,
+}
+
+impl EventCoalescer {
+    pub(crate) fn new(window: Duration, count_schema: &str) -> Self {
+        Self {
+            window,
+            count_schema: count_schema.to_string(),
+            pending: None,
+        }
+    }
+
+    /// Offers a payload tracked at `now` to the coalescer
+    ///
+    /// Returns the event ID the payload is represented by, along with a previously held payload
+    /// that is now ready to be emitted, if any.
+    pub(crate) fn push(
+        &mut self,
+        payload: PayloadBuilder,
+        now: DateTime<Utc>,
+    ) -> Result<(Uuid, Option<PayloadBuilder>), Error> {
+        let key = payload.content_key()?;
+
+        if let Some(pending) = self.pending.as_mut() {
+            // A clock moved backwards counts as no time elapsed
+            let elapsed = (now - pending.first_seen).to_std().unwrap_or_default();
+            if pending.key == key && elapsed <= self.window {
+                pending.count += 1;
+                return Ok((pending.event_id, None));
+            }
+        }
+
+        let event_id = match payload.eid {
+            Some(eid) => eid,
+            None => return Err(Error::BuilderError("Event ID not set".to_string())),
+        };
+
+        let ready = self.pending.replace(PendingEvent {
+            key,
+            event_id,
+            payload,
+            count: 1,
+            first_seen: now,
+        });
+
+        Ok((event_id, ready.map(|pending| self.finalise(pending))))
+    }
+
+    /// Removes the held payload, if any, so it can be emitted
+    pub(crate) fn drain(&mut self) -> Option<PayloadBuilder> {
+        let pending = self.pending.take()?;
+        Some(self.finalise(pending))
+    }
+
+    fn finalise(&self, pending: PendingEvent) -> PayloadBuilder {
+        let mut payload = pending.payload;
+        if pending.count > 1 {
+            payload.add_context(SelfDescribingJson::new(
+                &self.count_schema,
+                json!({ "count": pending.count }),
+            ));
+        }
+        payload
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use crate::payload::Payload;
+    use crate::StructuredEvent;
+
+    use super::*;
+
+    const COUNT_SCHEMA: &str = "iglu:com.acme/coalesced_events/jsonschema/1-0-0";
+
+    fn payload(action: &str) -> PayloadBuilder {
+        let event = StructuredEvent::builder()
+            .category("shop")
+            .action(action)
+            .build()
+            .unwrap();
+        Payload::builder()
+            .eid(Uuid::new_v4())
+            .structured_event(event)
+    }
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + seconds, 0).unwrap()
+    }
+
+    fn count(payload: &PayloadBuilder) -> Option<serde_json::Value> {
+        let context = payload.co.clone().flatten()?;
+        let count = context.data.iter().find(|c| c.schema == COUNT_SCHEMA)?;
+        Some(count.data["count"].clone())
+    }
+
+    #[test]
+    fn coalesces_identical_payloads_within_window() {
+        let mut coalescer = EventCoalescer::new(Duration::from_secs(60), COUNT_SCHEMA);
+
+        let (first_id, ready) = coalescer.push(payload("click"), at(0)).unwrap();
+        assert!(ready.is_none());
+        for seconds in 1..5 {
+            let (event_id, ready) = coalescer.push(payload("click"), at(seconds)).unwrap();
+            assert_eq!(event_id, first_id);
+            assert!(ready.is_none());
+        }
+
+        let held = coalescer.drain().unwrap();
+        assert_eq!(held.eid, Some(first_id));
+        assert_eq!(count(&held), Some(serde_json::json!(5)));
+        assert!(coalescer.drain().is_none());
+    }
+
+    #[test]
+    fn different_payload_releases_held_payload_with_count() {
+        let mut coalescer = EventCoalescer::new(Duration::from_secs(60), COUNT_SCHEMA);
+
+        let (first_id, _) = coalescer.push(payload("click"), at(0)).unwrap();
+        coalescer.push(payload("click"), at(1)).unwrap();
+        let (second_id, ready) = coalescer.push(payload("view"), at(2)).unwrap();
+
+        let ready = ready.unwrap();
+        assert_ne!(second_id, first_id);
+        assert_eq!(ready.eid, Some(first_id));
+        assert_eq!(count(&ready), Some(serde_json::json!(2)));
+    }
+
+    #[test]
+    fn identical_payload_after_window_releases_held_payload() {
+        let mut coalescer = EventCoalescer::new(Duration::from_secs(10), COUNT_SCHEMA);
+
+        let (first_id, _) = coalescer.push(payload("click"), at(0)).unwrap();
+        let (second_id, ready) = coalescer.push(payload("click"), at(11)).unwrap();
+
+        // A payload representing a single event has no count
+        let ready = ready.unwrap();
+        assert_ne!(second_id, first_id);
+        assert_eq!(ready.eid, Some(first_id));
+        assert!(ready.co.flatten().is_none());
+    }
+
+    #[test]
+    fn clock_moved_backwards_stays_within_window() {
+        let mut coalescer = EventCoalescer::new(Duration::from_secs(10), COUNT_SCHEMA);
+
+        let (first_id, _) = coalescer.push(payload("click"), at(100)).unwrap();
+        let (event_id, ready) = coalescer.push(payload("click"), at(0)).unwrap();
+
+        assert_eq!(event_id, first_id);
+        assert!(ready.is_none());
+    }
+}
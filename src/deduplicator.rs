@@ -0,0 +1,46 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use crate::clock::Instant;
+
+/// Remembers the keys of recently tracked events, so duplicates within a time window can be skipped.
+pub(crate) struct EventDeduplicator {
+    window: Duration,
+    seen: HashMap<String, (Uuid, Instant)>,
+}
+
+impl EventDeduplicator {
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            window,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Returns the ID of the event tracked with the same key within the window, if any
+    pub(crate) fn duplicate_of(&mut self, key: &str) -> Option<Uuid> {
+        let window = self.window;
+        self.seen
+            .retain(|_, (_, tracked_at)| tracked_at.elapsed() <= window);
+
+        self.seen.get(key).map(|(event_id, _)| *event_id)
+    }
+
+    /// Records that an event was tracked with the given key
+    pub(crate) fn record(&mut self, key: String, event_id: Uuid) {
+        self.seen.insert(key, (event_id, Instant::now()));
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use derive_builder::Builder;
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::payload::SelfDescribingJson;
+
+/// The schema of the desktop context entity
+pub(crate) const DESKTOP_CONTEXT_SCHEMA: &str =
+    "iglu:com.snowplowanalytics.snowplow/desktop_context/jsonschema/1-0-0";
+
+/// A context entity describing the operating system and device of a desktop app
+///
+/// Attach it to every event with [Tracker::set_desktop_context](crate::Tracker::set_desktop_context).
+///
+/// ## Example
+/// ```
+/// use snowplow_tracker::DesktopContext;
+///
+/// let desktop_context = DesktopContext::builder()
+///     .os_type("Linux")
+///     .os_version("6.1")
+///     .os_is_64_bit(true)
+///     .device_processor_count(8_u32)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Serialize, Builder, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[builder(setter(into, strip_option))]
+#[builder(build_fn(error = "Error"))]
+pub struct DesktopContext {
+    /// The type of operating system, e.g. `Windows`, `Linux` or `macOS`.
+    pub os_type: String,
+
+    /// The version of the operating system.
+    pub os_version: String,
+
+    /// The service pack of the operating system, if any.
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os_service_pack: Option<String>,
+
+    /// Whether the operating system is 64-bit.
+    #[builder(default)]
+    #[serde(rename = "osIs64Bit")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os_is_64_bit: Option<bool>,
+
+    /// The manufacturer of the device.
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_manufacturer: Option<String>,
+
+    /// The model of the device.
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_model: Option<String>,
+
+    /// The number of processors of the device.
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_processor_count: Option<u32>,
+}
+
+impl DesktopContext {
+    pub fn builder() -> DesktopContextBuilder {
+        DesktopContextBuilder::default()
+    }
+
+    pub(crate) fn to_self_describing_json(&self) -> SelfDescribingJson {
+        // Serializing a struct of strings and numbers can't fail
+        SelfDescribingJson::new(
+            DESKTOP_CONTEXT_SCHEMA,
+            serde_json::to_value(self).unwrap_or_default(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn serializes_all_fields() {
+        let desktop_context = DesktopContext::builder()
+            .os_type("Windows")
+            .os_version("10.0")
+            .os_service_pack("SP1")
+            .os_is_64_bit(true)
+            .device_manufacturer("Acme")
+            .device_model("Laptop 3000")
+            .device_processor_count(4_u32)
+            .build()
+            .unwrap();
+
+        let entity = desktop_context.to_self_describing_json();
+
+        assert_eq!(entity.schema, DESKTOP_CONTEXT_SCHEMA);
+        assert_eq!(
+            entity.data,
+            json!({
+                "osType": "Windows",
+                "osVersion": "10.0",
+                "osServicePack": "SP1",
+                "osIs64Bit": true,
+                "deviceManufacturer": "Acme",
+                "deviceModel": "Laptop 3000",
+                "deviceProcessorCount": 4,
+            })
+        );
+    }
+
+    #[test]
+    fn unset_fields_are_omitted() {
+        let desktop_context = DesktopContext::builder()
+            .os_type("Linux")
+            .os_version("6.1")
+            .device_processor_count(8_u32)
+            .build()
+            .unwrap();
+
+        let entity = desktop_context.to_self_describing_json();
+
+        assert_eq!(
+            entity.data,
+            json!({ "osType": "Linux", "osVersion": "6.1", "deviceProcessorCount": 8 })
+        );
+    }
+
+    #[test]
+    fn os_type_and_version_are_required() {
+        assert!(DesktopContext::builder().os_type("Linux").build().is_err());
+    }
+}
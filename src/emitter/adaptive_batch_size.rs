@@ -0,0 +1,101 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::Error;
+
+/// A batch size that adapts to backpressure, set with `BatchEmitterBuilder::adaptive_batch_size`
+///
+/// Starting at `min`, the size doubles each time a batch is taken while events keep backing up,
+/// up to `max`, so fewer requests are made during bursts. It halves back towards `min` each time
+/// a batch is taken without a backlog. Clones share the current size.
+#[derive(Debug, Clone)]
+pub(crate) struct AdaptiveBatchSize {
+    min: usize,
+    max: usize,
+    current: Arc<AtomicUsize>,
+}
+
+impl AdaptiveBatchSize {
+    pub(crate) fn new(min: usize, max: usize) -> Result<Self, Error> {
+        if min == 0 || min > max {
+            return Err(Error::BuilderError(format!(
+                "Invalid adaptive batch size bounds: {min}..={max}, expected 0 < min <= max"
+            )));
+        }
+
+        Ok(Self {
+            min,
+            max,
+            current: Arc::new(AtomicUsize::new(min)),
+        })
+    }
+
+    /// The number of events in the next batch
+    pub(crate) fn current(&self) -> usize {
+        self.current.load(Ordering::SeqCst)
+    }
+
+    /// The largest number of events in a batch
+    pub(crate) fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Grows the batch size if events are backing up, and shrinks it otherwise
+    pub(crate) fn adapt(&self, backlogged: bool) {
+        let (min, max) = (self.min, self.max);
+        // The closure always returns `Some`, so this can't fail
+        let _ = self
+            .current
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| {
+                Some(match backlogged {
+                    true => current.saturating_mul(2).min(max),
+                    false => (current / 2).max(min),
+                })
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_while_backlogged_and_shrinks_when_drained() {
+        let batch_size = AdaptiveBatchSize::new(5, 50).unwrap();
+        assert_eq!(batch_size.current(), 5);
+
+        let grown: Vec<usize> = (0..5)
+            .map(|_| {
+                batch_size.adapt(true);
+                batch_size.current()
+            })
+            .collect();
+        assert_eq!(grown, vec![10, 20, 40, 50, 50]);
+
+        let shrunk: Vec<usize> = (0..5)
+            .map(|_| {
+                batch_size.adapt(false);
+                batch_size.current()
+            })
+            .collect();
+        assert_eq!(shrunk, vec![25, 12, 6, 5, 5]);
+    }
+
+    #[test]
+    fn invalid_bounds_are_an_error() {
+        assert!(AdaptiveBatchSize::new(0, 10).is_err());
+        assert!(AdaptiveBatchSize::new(20, 10).is_err());
+        assert!(AdaptiveBatchSize::new(10, 10).is_ok());
+    }
+}
@@ -9,18 +9,36 @@
 // "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
 
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
-use crate::emitter::Emitter;
+use async_trait::async_trait;
+use tokio::sync::Semaphore;
+
+use crate::clock::{Clock, SystemClock};
+use crate::emitter::{DeadLetter, Emitter, EmitterStats, EventFilter};
 use crate::error::Error;
 use crate::event_batch::EventBatch;
 use crate::event_store::DEFAULT_EVENT_STORE_CAPACITY;
 use crate::event_store::{EventStore, InMemoryEventStore};
-use crate::http_client::ReqwestClient;
-use crate::payload::PayloadBuilder;
-use crate::HttpClient;
+use crate::http_client::{
+    BeforeSend, CookieJar, HttpResponse, RequestParts, RequestSigner, ReqwestClient,
+    DEFAULT_USER_AGENT,
+};
+use crate::payload::{Payload, PayloadBuilder};
+use crate::{HttpClient, Subject};
 
-use super::RetryPolicy;
+use super::adaptive_batch_size::AdaptiveBatchSize;
+use super::collector_failover::CollectorFailover;
+use super::collector_url::{normalize_collector_url, normalize_collector_url_or_keep};
+use super::rate_limiter::RateLimiter;
+use super::{
+    BufferOption, CollectorVendor, DeliveryReport, EmitFormat, FlushPolicy, HttpMethod, Jitter,
+    OnSuccess, ProtocolVersion, RetryPolicy, Sink,
+};
 
 /// An implementation of the [Emitter] trait that sends batched events to the Snowplow Collector.
 pub struct BatchEmitter {
@@ -34,13 +52,34 @@ pub struct BatchEmitter {
     executor_handle: Option<std::thread::JoinHandle<()>>,
     /// The transmitter to send an [EmitterMessage] to the [Emitter] thread
     tx: tokio::sync::mpsc::Sender<EmitterMessage>,
+    /// Settings and state shared with the thread sending events
+    send_context: SendContext,
+    /// Whether the namespace of the tracker using this emitter is sent in the `X-SP-Namespace` header
+    send_namespace_header: bool,
+    /// Decides which events are kept, if set
+    filter: Option<EventFilter>,
+    /// The path of the collector's health endpoint, used by [Emitter::health_check]
+    health_path: String,
 }
 
+// A user-supplied function returning whether the device is able to reach the collector
+type ConnectivityCheck = Arc<dyn Fn() -> bool + Send + Sync>;
+
+// A user-supplied function returning the key of an event, so that batches only hold events with the same key
+type PartitionKey = Arc<dyn Fn(&Payload) -> String + Send + Sync>;
+
 /// Possible messages to send to the Emitter, sent via the [Emitter] transmitter
 #[derive(Debug)]
 pub enum EmitterMessage {
     /// Sends a batch of events
     Send(EventBatch),
+    /// Adds an event to the [EventStore], sending a batch if there are enough events
+    Add(Box<PayloadBuilder>),
+    /// Sends all events currently in the [EventStore]
+    Flush,
+    /// Sends all events currently in the [EventStore] once in-flight batches are sent,
+    /// notifying the sender when the events have been sent, with the response of the collector if it rejected a batch
+    FlushNow(std::sync::mpsc::Sender<Result<(), Error>>),
     /// Shuts down the [Emitter]
     /// This will also attempt to send all events currently in the [EventStore]
     Close,
@@ -49,18 +88,90 @@ pub enum EmitterMessage {
 /// A builder for the [BatchEmitter] struct
 pub struct BatchEmitterBuilder {
     collector_url: Option<String>,
+    fallback_collector_urls: Vec<String>,
+    failover_after: usize,
     event_store: Arc<Mutex<dyn EventStore + Send + Sync>>,
     http_client: Option<Box<dyn HttpClient + Send + Sync>>,
+    sink: Option<Arc<dyn Sink + Send + Sync>>,
     retry_policy: RetryPolicy,
+    connectivity_check: Option<ConnectivityCheck>,
+    send_namespace_header: bool,
+    server_anonymisation: bool,
+    get_fallback_status_codes: Vec<u16>,
+    get_fallback_to_post: bool,
+    buffer_option: Option<BufferOption>,
+    before_send: Option<BeforeSend>,
+    max_concurrent_requests: usize,
+    max_requests_per_second: Option<u32>,
+    preserve_order: bool,
+    flush_interval: Option<Duration>,
+    flush_policy: FlushPolicy,
+    max_event_age: Option<Duration>,
+    byte_limit: Option<usize>,
+    partition_key: Option<PartitionKey>,
+    method: HttpMethod,
+    headers: HashMap<String, String>,
+    timeout: Option<Duration>,
+    proxy: Option<String>,
+    proxy_basic_auth: Option<(String, String)>,
+    compress: bool,
+    signer: Option<RequestSigner>,
+    user_agent: String,
+    health_path: String,
+    clock: Arc<dyn Clock>,
+    retry_jitter: Jitter,
+    no_retry_status_codes: HashSet<u16>,
+    adaptive_batch_size: Option<(usize, usize)>,
+    emit_format: EmitFormat,
+    vendor: Option<CollectorVendor>,
+    protocol_version: Option<ProtocolVersion>,
+    cookie_jar: Option<CookieJar>,
+    on_success: Option<OnSuccess>,
 }
 
 impl BatchEmitterBuilder {
     pub fn default() -> Self {
         Self {
             collector_url: None,
+            fallback_collector_urls: Vec::new(),
+            failover_after: DEFAULT_FAILOVER_AFTER,
             event_store: Arc::new(Mutex::new(InMemoryEventStore::default())),
             http_client: None,
+            sink: None,
             retry_policy: RetryPolicy::MaxRetries(10),
+            connectivity_check: None,
+            send_namespace_header: false,
+            server_anonymisation: false,
+            get_fallback_status_codes: Vec::new(),
+            get_fallback_to_post: false,
+            buffer_option: None,
+            before_send: None,
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+            max_requests_per_second: None,
+            preserve_order: false,
+            flush_interval: None,
+            flush_policy: FlushPolicy::default(),
+            max_event_age: None,
+            byte_limit: None,
+            partition_key: None,
+            method: HttpMethod::default(),
+            headers: HashMap::new(),
+            timeout: None,
+            proxy: None,
+            proxy_basic_auth: None,
+            compress: false,
+            signer: None,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            health_path: DEFAULT_HEALTH_PATH.to_string(),
+            clock: Arc::new(SystemClock),
+            retry_jitter: Jitter::default(),
+            no_retry_status_codes: HashSet::from(DEFAULT_NO_RETRY_STATUS_CODES),
+            adaptive_batch_size: None,
+            emit_format: EmitFormat::default(),
+            vendor: None,
+            protocol_version: None,
+            cookie_jar: None,
+            on_success: None,
         }
     }
 
@@ -70,6 +181,25 @@ impl BatchEmitterBuilder {
         self
     }
 
+    /// Add a collector URL that events are sent to if the collector fails, tried in the order they are added
+    ///
+    /// Once [BatchEmitterBuilder::failover_after] requests in a row have failed, without a response
+    /// or with a status code that is retried, the next collector becomes active. After the last
+    /// fallback, the emitter goes back to the primary collector. Failover needs a [HttpClient] that
+    /// supports changing its collector URL, such as the default [ReqwestClient].
+    pub fn fallback_collector_url(mut self, collector_url: &str) -> Self {
+        self.fallback_collector_urls.push(collector_url.to_string());
+        self
+    }
+
+    /// Set the number of failed requests in a row after which the emitter fails over to the next collector
+    ///
+    /// Defaults to 3. Building the emitter fails if this is zero.
+    pub fn failover_after(mut self, failures: usize) -> Self {
+        self.failover_after = failures;
+        self
+    }
+
     /// Set the [EventStore] implementation  
     pub fn event_store(mut self, event_store: impl EventStore + Send + Sync + 'static) -> Self {
         self.event_store = Arc::new(Mutex::new(event_store));
@@ -82,450 +212,4070 @@ impl BatchEmitterBuilder {
         self
     }
 
+    /// Set a [Sink] that batches of events are sent to instead of the collector, e.g. a message queue
+    ///
+    /// The emitter still buffers events and retries batches the sink fails to send, but settings of
+    /// requests to the collector, such as the HTTP method and headers, don't apply. The collector URL
+    /// isn't required when a sink is set.
+    pub fn sink(mut self, sink: impl Sink + Send + Sync + 'static) -> Self {
+        self.sink = Some(Arc::new(sink));
+        self
+    }
+
     /// Set the retry policy
     pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
         self.retry_policy = retry_policy;
         self
     }
 
-    /// Build the [BatchEmitter]
-    pub fn build(self) -> Result<BatchEmitter, Error> {
-        match self.collector_url {
-            Some(collector_url) => {
-                let event_store_capacity = match self.event_store.lock() {
-                    Ok(event_store) => event_store.capacity(),
-                    Err(e) => {
-                        return Err(Error::EventStoreError(
-                            format!("Failed to lock event store: {}", e).to_string(),
-                        ))
-                    }
-                };
-
-                Ok(BatchEmitter::create_emitter(
-                    &collector_url,
-                    event_store_capacity,
-                    self.event_store,
-                    self.http_client
-                        .unwrap_or(ReqwestClient::new(&collector_url)),
-                    self.retry_policy,
-                ))
-            }
-            None => Err(Error::EmitterError("Collector URL is required".to_string())),
-        }
+    /// Set how the delay before retrying a batch is randomized, by default [Jitter::Equal]
+    pub fn retry_jitter(mut self, retry_jitter: Jitter) -> Self {
+        self.retry_jitter = retry_jitter;
+        self
     }
-}
 
-// HTTP status codes that should not be retried
-const DONT_RETRY_STATUS_CODES: [u16; 5] = [400, 401, 403, 410, 422];
+    /// Set the response status codes that are treated as permanent failures, by default `400, 401, 403, 410, 422`
+    ///
+    /// Batches receiving one of these are dropped without retrying. Any other unsuccessful
+    /// response, or a failure to send, is retried according to the [RetryPolicy].
+    pub fn no_retry_status_codes(mut self, status_codes: HashSet<u16>) -> Self {
+        self.no_retry_status_codes = status_codes;
+        self
+    }
 
-/// The batch sent to the Snowplow Collector and the response code
-pub struct SentBatchResponse {
-    pub batch: EventBatch,
-    pub code: u16,
-}
+    /// Set a check of whether the device is online
+    ///
+    /// While the check returns `false`, events are kept in the event store rather than sent,
+    /// as if the emitter were paused.
+    pub fn connectivity_check(
+        mut self,
+        connectivity_check: impl Fn() -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.connectivity_check = Some(Arc::new(connectivity_check));
+        self
+    }
 
-impl BatchEmitter {
-    pub fn builder() -> BatchEmitterBuilder {
-        BatchEmitterBuilder::default()
+    /// Set whether the namespace of the tracker using this emitter is sent in the `X-SP-Namespace` header
+    ///
+    /// This lets collectors route or observe events by namespace without parsing the request body.
+    pub fn send_namespace_header(mut self, send_namespace_header: bool) -> Self {
+        self.send_namespace_header = send_namespace_header;
+        self
     }
 
-    fn create_emitter(
-        collector_url: &str,
-        event_store_capacity: usize,
-        event_store: Arc<Mutex<dyn EventStore + Send + Sync>>,
-        http_client: Box<dyn HttpClient + Send + Sync>,
-        retry_policy: RetryPolicy,
-    ) -> BatchEmitter {
-        let (tx, rx) = tokio::sync::mpsc::channel(event_store_capacity);
-        let mut emitter = BatchEmitter {
-            collector_url: collector_url.to_string(),
-            http_client,
-            event_store,
-            executor_handle: None,
-            tx,
-        };
+    /// Set the number of events buffered before they are sent
+    ///
+    /// By default, [BufferOption::Single] when sending via GET and [BufferOption::SmallBatch] via POST.
+    /// Batches never exceed the batch size of the event store. See also [Emitter::with_buffer_option].
+    pub fn buffer_option(mut self, buffer_option: BufferOption) -> Self {
+        self.buffer_option = Some(buffer_option);
+        self
+    }
 
-        // Clone http client to be used in the spawned thread
-        let client = emitter.http_client.clone();
-        let store = emitter.event_store.clone();
+    /// Set whether the collector should anonymise events, by sending the `SP-Anonymous` header
+    ///
+    /// The collector then doesn't record the IP address or set the network user ID of events.
+    pub fn server_anonymisation(mut self, server_anonymisation: bool) -> Self {
+        self.server_anonymisation = server_anonymisation;
+        self
+    }
 
-        // Spawn the tokio runtime in a separate thread
-        emitter.executor_handle = Some(std::thread::spawn(move || {
-            BatchEmitter::start_tokio(client, rx, store, retry_policy);
-        }));
+    /// Set the status codes of POST responses that trigger re-sending the events via GET
+    ///
+    /// Useful in restrictive networks where POST requests are blocked, e.g. with `[403, 405]`.
+    /// Each event in the batch is then sent in its own GET request. If any of these fail,
+    /// the whole batch is retried, so events may be sent more than once.
+    pub fn get_fallback_status_codes(mut self, status_codes: &[u16]) -> Self {
+        self.get_fallback_status_codes = status_codes.to_vec();
+        self
+    }
 
-        emitter
+    /// Set whether events too large to be sent via GET are sent via POST instead, by default `false`
+    ///
+    /// With [HttpMethod::Get], the query string of each request is limited to 2048 bytes, as longer
+    /// URLs are often truncated or rejected. By default, adding an event exceeding this limit fails
+    /// with [Error::PayloadTooLarge]. If enabled, the event is accepted and sent on its own via POST.
+    pub fn get_fallback_to_post(mut self, get_fallback_to_post: bool) -> Self {
+        self.get_fallback_to_post = get_fallback_to_post;
+        self
     }
 
-    /// Create a new [BatchEmitter] with an [InMemoryEventStore]
-    pub fn new(collector_url: &str) -> BatchEmitter {
-        BatchEmitter::create_emitter(
-            collector_url,
-            DEFAULT_EVENT_STORE_CAPACITY,
-            Arc::new(Mutex::new(InMemoryEventStore::default())),
-            ReqwestClient::new(collector_url),
-            RetryPolicy::MaxRetries(10),
-        )
+    /// Set a hook called with the method, URL, headers, and body of every request before it is sent
+    ///
+    /// The hook runs on every batch, including retries and GET fallback requests, and can change
+    /// any part of the request, e.g. to add tracing headers. It is only supported by HTTP clients
+    /// implementing [HttpClient::set_before_send], such as the default [ReqwestClient].
+    pub fn before_send(
+        mut self,
+        before_send: impl Fn(&mut RequestParts) + Send + Sync + 'static,
+    ) -> Self {
+        self.before_send = Some(Arc::new(before_send));
+        self
     }
 
-    // Static Methods
+    /// Set a callback called with the [DeliveryReport] of every batch sent successfully
+    ///
+    /// The report counts the events the collector accepted and rejected, if its response body says so,
+    /// e.g. `{"accepted": 8, "rejected": 2}`. Otherwise, all events of a successful response count as accepted.
+    /// The callback runs on the emitter thread, so it should return quickly.
+    pub fn on_success(
+        mut self,
+        on_success: impl Fn(&DeliveryReport) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_success = Some(Arc::new(on_success));
+        self
+    }
 
-    fn is_successful_response(code: u16) -> bool {
-        code >= 200 && code < 300
+    /// Set the maximum number of requests to the collector in flight at once, by default 2
+    ///
+    /// Further batches wait until a request completes, so bursts of events don't overwhelm the collector.
+    /// Building the emitter fails if the limit is 0.
+    pub fn max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = max_concurrent_requests;
+        self
     }
 
-    // True if the code is outside 200-299 and not in DONT_RETRY_STATUS_CODES
-    fn should_retry(code: u16) -> bool {
-        match Self::is_successful_response(code) {
-            true => false,
-            false => !DONT_RETRY_STATUS_CODES.contains(&code),
-        }
+    /// Cap the number of requests to the collector sent per second, e.g. to respect its rate limit
+    ///
+    /// Requests are let through by a token bucket holding up to one second's worth of requests,
+    /// so a burst of up to `requests_per_second` is sent right away. Once the limit is hit,
+    /// sending waits rather than failing. Events sent via GET count as one request each.
+    /// Building the emitter fails if this is zero.
+    pub fn max_requests_per_second(mut self, requests_per_second: u32) -> Self {
+        self.max_requests_per_second = Some(requests_per_second);
+        self
     }
 
-    fn retry_batch(
-        mut batch: EventBatch,
-        retry_tx: tokio::sync::mpsc::UnboundedSender<EmitterMessage>,
-    ) {
-        batch.update_for_retry();
+    /// Set whether batches are sent strictly in order, by default `false`
+    ///
+    /// When enabled, batches are sent one at a time, and a failed batch is retried before any later
+    /// batch is sent, so events reach the collector in the order they were added. This trades throughput
+    /// for ordering: a batch waiting to be retried holds back all later batches.
+    /// [Emitter::flush_now] then also waits for the retries of earlier batches.
+    pub fn preserve_order(mut self, preserve_order: bool) -> Self {
+        self.preserve_order = preserve_order;
+        self
+    }
 
-        let batch_id = batch.id;
-        match retry_tx.send(EmitterMessage::Send(batch)) {
-            Ok(_) => log::debug!("Batch {batch_id} re-queued"),
-            Err(e) => {
-                log::warn!("Failed to re-queue batch {batch_id}: {e}")
-            }
-        }
+    /// Let the number of events in each batch adapt to backpressure, between `min` and `max`, instead of the [BufferOption]
+    ///
+    /// Starting at `min`, the batch size doubles each time a batch is sent while events are backing up,
+    /// i.e. all requests allowed by [BatchEmitterBuilder::max_concurrent_requests] are in flight or
+    /// another batch is already waiting, up to `max`. This sends bursts in fewer, larger requests.
+    /// The batch size halves back towards `min` each time a batch is sent without a backlog.
+    /// Building the emitter fails unless `0 < min <= max`, or if `max` is greater than the batch
+    /// size of the [EventStore], which is the largest batch it can return.
+    pub fn adaptive_batch_size(mut self, min: usize, max: usize) -> Self {
+        self.adaptive_batch_size = Some((min, max));
+        self
     }
 
-    fn run_cleanup(
-        store: Arc<Mutex<dyn EventStore + Send + Sync>>,
-        batch: EventBatch,
-    ) -> Result<(), Error> {
-        let mut store_guard = match store.lock() {
-            Ok(guard) => guard,
-            Err(e) => {
-                return Err(Error::EmitterError(format!(
-                    "Failed to acquire event store lock: {e}"
-                )))
-            }
-        };
+    /// Set an interval at which all events in the event store are sent, regardless of the [BufferOption]
+    ///
+    /// This makes sure events are sent in a timely manner when there are too few to fill a batch.
+    /// Periodic flushing stops when the emitter is closed. Building the emitter fails if the interval is zero.
+    pub fn flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.flush_interval = Some(flush_interval);
+        self
+    }
 
-        match store_guard.cleanup_after_send_attempt(batch.id) {
-            Ok(_) => log::debug!("Cleanup run for batch: {}", batch.id),
-            Err(e) => return Err(Error::EmitterError(format!("Failed to cleanup: {e}"))),
-        };
+    /// Set when buffered events are sent: once enough events or bytes are buffered, or after an interval, whichever comes first
+    ///
+    /// The number of events takes priority over the [BufferOption], and the interval over
+    /// [BatchEmitterBuilder::flush_interval]. Building the emitter fails if the number of events
+    /// or bytes, or the interval, is zero.
+    pub fn flush_policy(mut self, flush_policy: FlushPolicy) -> Self {
+        self.flush_policy = flush_policy;
+        self
+    }
 
-        Ok(())
+    /// Discard buffered events older than `max_event_age` instead of sending them, e.g. after the device was offline for days
+    ///
+    /// The age of an event is measured from its created timestamp (`dtm`), when it is taken from the event store to be sent.
+    /// Discarded events are passed to the [Emitter::on_dead_letter] callback and counted as dropped.
+    pub fn max_event_age(mut self, max_event_age: Duration) -> Self {
+        self.max_event_age = Some(max_event_age);
+        self
     }
 
-    async fn batch_send_task(
-        mut batch: EventBatch,
-        client: Box<dyn HttpClient + Send + Sync>,
-        retry_tx: tokio::sync::mpsc::UnboundedSender<EmitterMessage>,
-        store: Arc<Mutex<dyn EventStore + Send + Sync>>,
-        retry_policy: RetryPolicy,
-    ) {
-        if let Some(delay) = batch.delay {
-            log::debug!("Delaying batch {} for {:?}", batch.id, delay);
-            tokio::time::sleep(delay).await;
+    /// Send events with different keys in separate batches, e.g. when trackers with different app IDs share the emitter
+    ///
+    /// Each batch taken from the event store is split so all its events return the same key, which
+    /// keeps batches homogeneous when downstream routing depends on a field such as the app ID:
+    ///
+    /// ```
+    /// use snowplow_tracker::{BatchEmitter, Emitter};
+    ///
+    /// let mut emitter = BatchEmitter::builder()
+    ///     .collector_url("https://collector.example.com")
+    ///     .partition_batches_by(|payload| payload.app_id().to_string())
+    ///     .build()
+    ///     .unwrap();
+    /// emitter.close().unwrap();
+    /// ```
+    pub fn partition_batches_by(
+        mut self,
+        partition_key: impl Fn(&Payload) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.partition_key = Some(Arc::new(partition_key));
+        self
+    }
 
-            if let Err(e) = batch.update_event_stm() {
-                // If the update fails, we just re-send the batch as-is
-                // Not ideal, but it's better than losing events
-                log::warn!(
-                    "Failed to update stm of events in batch {} for retry: {e}",
-                    batch.id
-                )
-            };
-        };
+    /// Set the maximum size in bytes of a request to the collector
+    ///
+    /// Collectors reject requests that are too large, often over 1MB. Batches exceeding the limit
+    /// are split across multiple requests, while adding an event too large to be sent within the
+    /// limit on its own fails with [Error::PayloadTooLarge].
+    pub fn byte_limit(mut self, byte_limit: usize) -> Self {
+        self.byte_limit = Some(byte_limit);
+        self
+    }
 
-        let batch_length = batch.events.len();
-        match Self::send_batch(batch, client).await {
-            Ok(resp) => {
-                // We got a response from the collector, but need to check if
-                // it was successful
+    /// Set the HTTP method events are sent with, by default [HttpMethod::Post]
+    ///
+    /// With [HttpMethod::Get], each event is sent in its own request, so the [BufferOption]
+    /// is [BufferOption::Single] unless set otherwise.
+    pub fn method(mut self, method: HttpMethod) -> Self {
+        self.method = method;
+        self
+    }
 
-                match (
-                    Self::should_retry(resp.code),
-                    resp.batch.has_retry(retry_policy),
-                ) {
-                    // An unsuccessful response with retry attempts remaining
-                    (true, true) => Self::retry_batch(resp.batch, retry_tx),
+    /// Add a header sent with every request to the collector
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(name.to_string(), value.to_string());
+        self
+    }
 
-                    // An unsuccessful response with no retry attempts remaining
-                    (true, false) => {
-                        log::warn!("Batch {} failed to send, no retry available", resp.batch.id);
-                        match Self::run_cleanup(store, resp.batch) {
-                            Ok(_) => (),
-                            Err(e) => log::error!("{e}"),
-                        }
-                    }
+    /// Set the timeout of requests to the collector, by default there is none
+    ///
+    /// This only applies to the default [ReqwestClient], and is ignored if an [HttpClient] is set.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
 
-                    // A successful response
-                    (false, _) => {
-                        log::info!("Sent batch {} of {batch_length} events", resp.batch.id);
-                        match Self::run_cleanup(store, resp.batch) {
-                            Ok(_) => (),
-                            Err(e) => log::error!("{e}"),
-                        }
-                    }
-                }
-            }
+    /// Send requests to the collector through an HTTP or HTTPS proxy, such as `http://proxy.example.com:3128`
+    ///
+    /// By default, the proxy is detected from the `HTTP_PROXY` and `HTTPS_PROXY` environment variables, if set.
+    /// Building the emitter fails if the proxy URL is invalid. This only applies to the default
+    /// [ReqwestClient], and is ignored if an [HttpClient] is set.
+    pub fn proxy(mut self, proxy_url: &str) -> Self {
+        self.proxy = Some(proxy_url.to_string());
+        self
+    }
 
-            // The request to the collector failed - no response
-            Err(failed_batch) => {
-                if failed_batch.has_retry(retry_policy) {
-                    Self::retry_batch(failed_batch, retry_tx)
-                } else {
-                    log::warn!(
-                        "Batch {} failed to send, no retry available",
-                        failed_batch.id
-                    );
-                    match Self::run_cleanup(store, failed_batch) {
-                        Ok(_) => (),
-                        Err(e) => log::error!("{e}"),
-                    }
-                }
-            }
-        }
+    /// Set the username and password sent to the proxy set with [BatchEmitterBuilder::proxy], using basic auth
+    pub fn proxy_basic_auth(mut self, username: &str, password: &str) -> Self {
+        self.proxy_basic_auth = Some((username.to_string(), password.to_string()));
+        self
     }
 
-    // Sends an EventBatch to the collector
-    async fn send_batch(
-        batch: EventBatch,
-        http_client: Box<dyn HttpClient + Send + Sync>,
-    ) -> Result<SentBatchResponse, EventBatch> {
-        match http_client.post(batch.as_payload()).await {
-            Ok(code) => {
-                log::debug!("Batch {} sent with status code {}", batch.id, code);
-                Ok(SentBatchResponse { batch, code })
-            }
-            Err(e) => {
-                log::warn!("Failed to send batch {}: {e}, re-queueing...", batch.id);
-                Err(batch)
-            }
-        }
+    /// Set whether the body of POST requests is compressed with gzip, by default `false`
+    ///
+    /// The collector must accept the `Content-Encoding: gzip` header. Compression is only supported
+    /// by HTTP clients implementing [HttpClient::set_compression], such as the default [ReqwestClient].
+    pub fn compress(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
     }
 
-    // Starts a tokio runtime and runs the emitter loop
-    fn start_tokio(
-        http_client: Box<dyn HttpClient + Send + Sync>,
-        mut rx: tokio::sync::mpsc::Receiver<EmitterMessage>,
-        event_store: Arc<Mutex<dyn EventStore + Send + Sync>>,
-        retry_policy: RetryPolicy,
-    ) {
-        // Create a new runtime to handle the async tasks
-        // Unwrap here as if the runtime fails to start, there is nothing we can do
-        let rt = tokio::runtime::Builder::new_multi_thread()
-            .enable_all()
-            .build()
-            .unwrap();
+    /// Sign the body of each request with an HMAC-SHA256 over `secret`, sent as the lowercase hex `header` header
+    ///
+    /// The signature covers the bytes actually sent, i.e. after compression. Signing is only supported
+    /// by HTTP clients implementing [HttpClient::set_signer], such as the default [ReqwestClient].
+    pub fn sign_requests(mut self, header: &str, secret: &[u8]) -> Self {
+        self.signer = Some(RequestSigner::new(header, secret));
+        self
+    }
 
-        // The main emitter loop
-        // This continuously loops and checks for new batches to send
-        rt.block_on(async {
-            // The currently running tokio tasks
-            let mut tokio_tasks: Vec<_> = Vec::new();
-            let (retry_tx, mut retry_rx) = tokio::sync::mpsc::unbounded_channel();
+    /// Set the shape of the body of POST requests, by default [EmitFormat::Tp2Envelope] as expected by a collector
+    ///
+    /// Only supported by HTTP clients implementing [HttpClient::set_emit_format], such as the default [ReqwestClient].
+    pub fn emit_format(mut self, emit_format: EmitFormat) -> Self {
+        self.emit_format = emit_format;
+        self
+    }
 
-            loop {
-                // `rx.recv().await` will not resolve until either a message is received,
-                // or the channel is closed and there are no more messages, in which case we exit the loop
+    /// Set the vendor path segment of the endpoint events are POSTed to, by default [CollectorVendor::Snowplow]
+    ///
+    /// Events are then sent to `/{vendor}/tp2`, for collectors accepting events from a custom vendor.
+    /// Only supported by HTTP clients implementing [HttpClient::set_vendor], such as the default [ReqwestClient].
+    pub fn vendor(mut self, vendor: CollectorVendor) -> Self {
+        self.vendor = Some(vendor);
+        self
+    }
 
-                // select! is used to check both the `retry_rx` channel and the `rx` channel for new messages
-                let message = match tokio::select! {
-                    // `biased;` is used to ensure that the `retry_rx` channel is checked first, so retries get priority
-                    biased;
+    /// Set the protocol version path segment of the endpoint events are POSTed to, by default [ProtocolVersion::Tp2]
+    ///
+    /// Events are then sent to `/{vendor}/{version}`, for collectors exposing another version of the API.
+    /// Only supported by HTTP clients implementing [HttpClient::set_protocol_version], such as the default [ReqwestClient].
+    pub fn protocol_version(mut self, protocol_version: ProtocolVersion) -> Self {
+        self.protocol_version = Some(protocol_version);
+        self
+    }
 
-                    retry = retry_rx.recv() => retry,
-                    event = rx.recv() => event,
-                } {
-                    Some(message) => message,
-                    None => break,
-                };
+    /// Capture the network user ID cookie set by the collector in `cookie_jar`, attaching it to later events
+    ///
+    /// The network user ID (`tnuid`) is added to the subject of events added once the collector has set the cookie,
+    /// unless the event already has one. The cookie is also sent back to the collector, so the ID stays the same.
+    /// Only supported by HTTP clients implementing [HttpClient::set_cookie_jar], such as the default [ReqwestClient].
+    pub fn cookie_jar(mut self, cookie_jar: CookieJar) -> Self {
+        self.cookie_jar = Some(cookie_jar);
+        self
+    }
 
-                match message {
-                    EmitterMessage::Send(batch) => {
-                        // Clone to move into the task
-                        let client = http_client.clone();
-                        let retry_transmitter = retry_tx.clone();
-                        let store = event_store.clone();
-
-                        // Spawn a new task to send the batch
-                        tokio_tasks.push(tokio::spawn(async move {
-                            Self::batch_send_task(
-                                batch,
-                                client,
-                                retry_transmitter,
-                                store,
-                                retry_policy,
-                            )
-                            .await
-                        }));
-                    }
+    /// Set the `User-Agent` header of requests to the collector, by default `snowplow-rust-tracker/<version>`
+    ///
+    /// This only applies to the default [ReqwestClient], and is ignored if an [HttpClient] is set.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = user_agent.to_string();
+        self
+    }
 
-                    // On break, the emitter and runtime will be dropped
-                    //
-                    // Tokio will cancel any running tasks once the runtime is dropped, meaning any queued or retry batches will be lost,
-                    // so we attempt to send any remaining batches before exiting
-                    EmitterMessage::Close => {
-                        let remaining = tokio_tasks.len();
-                        for (i, task) in tokio_tasks.iter_mut().enumerate() {
-                            log::debug!("Waiting for task {}/{remaining} to complete", i + 1);
-                            task.await.unwrap();
-                        }
-                        break;
-                    }
+    /// Set the [Clock] used for the sent timestamp (`stm`) of events, by default a [SystemClock]
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    // Builds the proxy requests are sent through, failing if its URL isn't an http or https URL with a host
+    fn build_proxy(
+        proxy_url: &str,
+        basic_auth: Option<&(String, String)>,
+    ) -> Result<reqwest::Proxy, Error> {
+        let invalid = |reason: String| {
+            Error::BuilderError(format!("Invalid proxy URL {proxy_url}: {reason}"))
+        };
+
+        let url = reqwest::Url::parse(proxy_url).map_err(|e| invalid(e.to_string()))?;
+        if !matches!(url.scheme(), "http" | "https") || url.host().is_none() {
+            return Err(invalid(
+                "must be an http or https URL with a host".to_string(),
+            ));
+        }
+
+        let proxy = reqwest::Proxy::all(url).map_err(|e| invalid(e.to_string()))?;
+        Ok(match basic_auth {
+            Some((username, password)) => proxy.basic_auth(username, password),
+            None => proxy,
+        })
+    }
+
+    // Builds the default HTTP client, applying the user agent, timeout and proxy
+    fn default_http_client(
+        collector_url: &str,
+        user_agent: &str,
+        timeout: Option<Duration>,
+        proxy: Option<reqwest::Proxy>,
+    ) -> Result<Box<dyn HttpClient + Send + Sync>, Error> {
+        let mut client = reqwest::Client::builder().user_agent(user_agent);
+        if let Some(timeout) = timeout {
+            client = client.timeout(timeout);
+        }
+        if let Some(proxy) = proxy {
+            client = client.proxy(proxy);
+        }
+
+        match client.build() {
+            Ok(client) => Ok(ReqwestClient::with_client(collector_url, client)),
+            Err(e) => Err(Error::BuilderError(format!(
+                "Failed to build HTTP client: {e}"
+            ))),
+        }
+    }
+
+    // Reports an error of a helper shared with other emitters as a builder error
+    fn builder_error(e: Error) -> Error {
+        match e {
+            Error::EmitterError(message) => Error::BuilderError(message),
+            e => e,
+        }
+    }
+
+    /// Set the path of the collector's health endpoint, checked by [Emitter::health_check], by default `/health`
+    ///
+    /// The path must start with `/`.
+    pub fn health_path(mut self, health_path: &str) -> Self {
+        self.health_path = health_path.to_string();
+        self
+    }
+
+    /// Build the [BatchEmitter]
+    ///
+    /// Fails with [Error::BuilderError] if an option is invalid, e.g. the collector URL is missing without a [Sink],
+    /// or isn't a valid URL (see [normalize_collector_url]).
+    pub fn build(self) -> Result<BatchEmitter, Error> {
+        let collector_url = match (&self.collector_url, &self.sink) {
+            (Some(collector_url), _) => {
+                normalize_collector_url(collector_url).map_err(Self::builder_error)?
+            }
+            // Events are sent to the sink rather than a collector
+            (None, Some(_)) => String::new(),
+            (None, None) => {
+                return Err(Error::BuilderError("Collector URL is required".to_string()))
+            }
+        };
+        if self.failover_after == 0 {
+            return Err(Error::BuilderError(
+                "Failover must happen after at least one failure".to_string(),
+            ));
+        }
+        let failover = match self.fallback_collector_urls.is_empty() {
+            true => None,
+            false => {
+                let mut collector_urls = vec![collector_url.clone()];
+                for fallback_url in &self.fallback_collector_urls {
+                    collector_urls
+                        .push(normalize_collector_url(fallback_url).map_err(Self::builder_error)?);
                 }
+                Some(CollectorFailover::new(collector_urls, self.failover_after)?)
+            }
+        };
+        if self.flush_interval == Some(Duration::ZERO) {
+            return Err(Error::BuilderError(
+                "Flush interval must be greater than zero".to_string(),
+            ));
+        }
+        if self.flush_policy.events_threshold() == Some(0)
+            || self.flush_policy.bytes_threshold() == Some(0)
+        {
+            return Err(Error::BuilderError(
+                "Flush policy thresholds must be greater than zero".to_string(),
+            ));
+        }
+        if self.flush_policy.flush_interval() == Some(Duration::ZERO) {
+            return Err(Error::BuilderError(
+                "Flush policy interval must be greater than zero".to_string(),
+            ));
+        }
+        if self.max_concurrent_requests == 0 {
+            return Err(Error::BuilderError(
+                "Max concurrent requests must be greater than zero".to_string(),
+            ));
+        }
+        if !self.health_path.starts_with('/') {
+            return Err(Error::BuilderError(format!(
+                "Health path must start with '/': {}",
+                self.health_path
+            )));
+        }
 
-                // Discard any completed tasks in the task list
-                tokio_tasks.retain(|t| !t.is_finished());
+        let (event_store_capacity, event_store_batch_size) = match self.event_store.lock() {
+            Ok(event_store) => (event_store.capacity(), event_store.batch_size()),
+            Err(e) => {
+                return Err(Error::EventStoreError(
+                    format!("Failed to lock event store: {}", e).to_string(),
+                ))
             }
-        });
+        };
+
+        let rate_limiter = match self.max_requests_per_second {
+            Some(requests_per_second) => Some(RateLimiter::new(requests_per_second)?),
+            None => None,
+        };
+
+        let adaptive_batch_size = match self.adaptive_batch_size {
+            Some((_, max)) if max > event_store_batch_size => {
+                return Err(Error::BuilderError(format!(
+                    "Adaptive batch size max {max} is greater than the event store batch size {event_store_batch_size}"
+                )))
+            }
+            Some((min, max)) => Some(AdaptiveBatchSize::new(min, max)?),
+            None => None,
+        };
+
+        let mut headers = self.headers;
+        if self.server_anonymisation {
+            headers.insert(ANONYMOUS_HEADER.to_string(), "*".to_string());
+        }
+
+        let proxy = match &self.proxy {
+            Some(proxy_url) => Some(Self::build_proxy(
+                proxy_url,
+                self.proxy_basic_auth.as_ref(),
+            )?),
+            None => None,
+        };
+
+        let mut http_client = match self.http_client {
+            Some(http_client) => http_client,
+            None => {
+                Self::default_http_client(&collector_url, &self.user_agent, self.timeout, proxy)?
+            }
+        };
+        if let Some(before_send) = self.before_send {
+            http_client.set_before_send(before_send);
+        }
+        if self.compress {
+            http_client.set_compression(true);
+        }
+        if let Some(signer) = self.signer {
+            http_client.set_signer(signer);
+        }
+        http_client.set_emit_format(self.emit_format);
+        if let Some(vendor) = &self.vendor {
+            vendor.validate()?;
+            http_client.set_vendor(vendor);
+        }
+        if let Some(protocol_version) = &self.protocol_version {
+            protocol_version.validate()?;
+            http_client.set_protocol_version(protocol_version);
+        }
+        if let Some(cookie_jar) = &self.cookie_jar {
+            http_client.set_cookie_jar(cookie_jar.clone());
+        }
+
+        let mut emitter = BatchEmitter::create_emitter(
+            &collector_url,
+            event_store_capacity,
+            self.event_store,
+            http_client,
+            self.retry_policy,
+            SendContext {
+                headers: Arc::new(RwLock::new(headers)),
+                get_fallback_status_codes: self.get_fallback_status_codes,
+                get_fallback_to_post: self.get_fallback_to_post,
+                connectivity_check: self.connectivity_check,
+                // By convention, events sent via GET are sent one at a time, and via POST in small batches
+                buffer_option: Arc::new(RwLock::new(self.buffer_option.unwrap_or(
+                    match self.method {
+                        HttpMethod::Get => BufferOption::Single,
+                        HttpMethod::Post => BufferOption::SmallBatch,
+                    },
+                ))),
+                request_permits: Arc::new(Semaphore::new(self.max_concurrent_requests)),
+                rate_limiter,
+                preserve_order: self.preserve_order,
+                flush_interval: self.flush_policy.flush_interval().or(self.flush_interval),
+                flush_policy: self.flush_policy,
+                max_event_age: self.max_event_age,
+                byte_limit: self.byte_limit,
+                partition_key: self.partition_key,
+                method: self.method,
+                clock: self.clock,
+                retry_jitter: self.retry_jitter,
+                no_retry_status_codes: self.no_retry_status_codes,
+                adaptive_batch_size,
+                cookie_jar: self.cookie_jar,
+                sink: self.sink,
+                failover,
+                on_success: self.on_success,
+                ..SendContext::default()
+            },
+        );
+        emitter.send_namespace_header = self.send_namespace_header;
+        emitter.health_path = self.health_path;
+
+        Ok(emitter)
     }
 }
 
-impl Drop for BatchEmitter {
-    fn drop(&mut self) {
-        // Get the join handle for the thread running the tokio runtime and wait for it to finish
-        //
-        // It's likely that the thread has already finished once the emitter loop has exited
-        if let Some(handle) = self.executor_handle.take() {
-            handle.join().unwrap();
-            log::debug!("BatchEmitter thread joined");
+// The path of the collector's health endpoint, unless set otherwise with [BatchEmitterBuilder::health_path]
+const DEFAULT_HEALTH_PATH: &str = "/health";
+
+// The header carrying the tracker namespace, if enabled
+const NAMESPACE_HEADER: &str = "X-SP-Namespace";
+
+// The header asking the collector to anonymise events, if enabled
+const ANONYMOUS_HEADER: &str = "SP-Anonymous";
+
+// The maximum length of the query string of a GET request, as longer URLs are often truncated or rejected
+const MAX_GET_QUERY_LENGTH: usize = 2048;
+
+// The default number of failed requests in a row after which the emitter fails over to the next collector
+const DEFAULT_FAILOVER_AFTER: usize = 3;
+
+// The default maximum number of requests to the collector in flight at once
+const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 2;
+
+// Settings and statistics shared between the emitter and the thread sending events
+#[derive(Clone)]
+struct SendContext {
+    // Additional headers sent with every request, which may change after the thread has started
+    headers: Arc<RwLock<HashMap<String, String>>>,
+    // POST response status codes that trigger re-sending the events via GET
+    get_fallback_status_codes: Vec<u16>,
+    // Whether events too large to be sent via GET are sent via POST, rather than rejected
+    get_fallback_to_post: bool,
+    // Counts of events handled, behind a single lock so a snapshot is consistent
+    stats: Arc<Mutex<EmitterStats>>,
+    // Whether sending has been paused with [Emitter::pause]
+    paused: Arc<AtomicBool>,
+    // A check of whether the device is online, sending is held back while it returns `false`
+    connectivity_check: Option<ConnectivityCheck>,
+    // The number of events buffered before they are sent, which may be set after the thread has started
+    buffer_option: Arc<RwLock<BufferOption>>,
+    // The number of events added with `add_nonblocking` that the thread hasn't added to the event store yet
+    pending_adds: Arc<AtomicUsize>,
+    // Permits to send a request, limiting the number of requests in flight
+    request_permits: Arc<Semaphore>,
+    // Caps the number of requests sent per second, if set
+    rate_limiter: Option<RateLimiter>,
+    // Whether batches are sent one at a time, retrying each before sending the next
+    preserve_order: bool,
+    // How often the thread flushes the event store, if at all
+    flush_interval: Option<Duration>,
+    // The numbers of events and bytes that trigger sending buffered events, if set
+    flush_policy: FlushPolicy,
+    // The size in bytes of the events in the event store, tracked while it is locked, if the flush policy has a byte threshold
+    buffered_bytes: Arc<AtomicUsize>,
+    // The age after which buffered events are discarded rather than sent, if any
+    max_event_age: Option<Duration>,
+    // The maximum size in bytes of a request, if any
+    byte_limit: Option<usize>,
+    // The key of events that batches are partitioned by, if set
+    partition_key: Option<PartitionKey>,
+    // The HTTP method events are sent with
+    method: HttpMethod,
+    // The source of the sent timestamp of events
+    clock: Arc<dyn Clock>,
+    // How the delay before retrying a batch is randomized
+    retry_jitter: Jitter,
+    // Response status codes that are not retried
+    no_retry_status_codes: HashSet<u16>,
+    // The batch size adapting to backpressure, used instead of `buffer_option` if set
+    adaptive_batch_size: Option<AdaptiveBatchSize>,
+    // Holds the network user ID set by the collector, attached to events added once it is set
+    cookie_jar: Option<CookieJar>,
+    // Where batches are sent instead of the collector, if set
+    sink: Option<Arc<dyn Sink + Send + Sync>>,
+    // The collector URLs requests fail over between, if fallback collectors are set
+    failover: Option<CollectorFailover>,
+    // Called with the delivery report of every batch sent successfully
+    on_success: Option<OnSuccess>,
+    // Called with the events of batches that are dropped, which may be set after the thread has started
+    dead_letter: Arc<RwLock<Option<DeadLetter>>>,
+}
+
+impl Default for SendContext {
+    fn default() -> Self {
+        Self {
+            headers: Arc::default(),
+            get_fallback_status_codes: Vec::new(),
+            get_fallback_to_post: false,
+            stats: Arc::default(),
+            paused: Arc::default(),
+            connectivity_check: None,
+            buffer_option: Arc::default(),
+            pending_adds: Arc::default(),
+            request_permits: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENT_REQUESTS)),
+            rate_limiter: None,
+            preserve_order: false,
+            flush_interval: None,
+            flush_policy: FlushPolicy::default(),
+            buffered_bytes: Arc::default(),
+            max_event_age: None,
+            byte_limit: None,
+            partition_key: None,
+            method: HttpMethod::default(),
+            clock: Arc::new(SystemClock),
+            retry_jitter: Jitter::default(),
+            no_retry_status_codes: HashSet::from(DEFAULT_NO_RETRY_STATUS_CODES),
+            adaptive_batch_size: None,
+            cookie_jar: None,
+            sink: None,
+            failover: None,
+            on_success: None,
+            dead_letter: Arc::default(),
         }
-        log::debug!("BatchEmitter dropped");
     }
 }
 
-impl Emitter for BatchEmitter {
-    /// Adds a payload to the event store
-    ///
-    /// This may also trigger sending a payload to the collector if the event store has enough events to fill a batch
-    fn add(&mut self, payload: PayloadBuilder) -> Result<(), Error> {
-        let batch = match self.event_store.lock() {
-            Ok(mut store) => {
-                match store.add(payload) {
-                    Ok(_) => log::debug!("Added event to event store"),
-                    Err(e) => {
-                        log::error!("Failed to add event to event store: {e}");
-                        return Err(e);
-                    }
+impl SendContext {
+    // Adds the network user ID set by the collector to the subject of the event, unless it already has one
+    fn attach_network_user_id(&self, payload: &mut PayloadBuilder) {
+        let Some(network_user_id) = self
+            .cookie_jar
+            .as_ref()
+            .and_then(CookieJar::network_user_id)
+        else {
+            return;
+        };
+
+        match payload.subject.as_mut() {
+            Some(Some(subject)) => {
+                subject.network_user_id.get_or_insert(network_user_id);
+            }
+            _ => {
+                payload.subject = Some(Some(Subject {
+                    network_user_id: Some(network_user_id),
+                    ..Subject::default()
+                }))
+            }
+        }
+    }
+
+    // Hands the events of a batch that won't be sent again to the dead letter callback, if set
+    fn dead_letter(&self, batch: &EventBatch) {
+        self.dead_letter_events(batch.events.clone());
+    }
+
+    // Hands events that won't be sent to the dead letter callback, if set
+    fn dead_letter_events(&self, events: Vec<Payload>) {
+        match self.dead_letter.read() {
+            Ok(dead_letter) => {
+                if let Some(dead_letter) = dead_letter.as_ref() {
+                    dead_letter(events);
                 }
-                // If the event store has enough events to fill a batch, return the batch
-                store.full_batch()
             }
-            Err(e) => return Err(Error::EmitterError(e.to_string())),
+            Err(e) => log::warn!("Failed to read dead letter callback: {e}"),
+        }
+    }
+
+    // Removes events older than the maximum event age from the batches, dropping any batches left empty
+    fn discard_expired(&self, batches: Vec<EventBatch>) -> Vec<EventBatch> {
+        let Some(max_event_age) = self.max_event_age else {
+            return batches;
         };
+        let Ok(max_event_age) = chrono::Duration::from_std(max_event_age) else {
+            return batches;
+        };
+        let oldest_allowed = self.clock.now() - max_event_age;
 
-        // We can ignore the error here, as the only error that can return is the event store being empty,
-        // in which case we don't want to send a batch
-        if let Ok(batch) = batch {
-            return match self.tx.try_send(EmitterMessage::Send(batch)) {
-                Ok(_) => Ok(()),
-                Err(e) => Err(Error::EmitterError(e.to_string())),
-            };
+        let mut expired = Vec::new();
+        let batches = batches
+            .into_iter()
+            .filter_map(|mut batch| {
+                let (kept, discarded): (Vec<_>, Vec<_>) = batch
+                    .events
+                    .into_iter()
+                    .partition(|event| event.dtm >= oldest_allowed);
+                expired.extend(discarded);
+                batch.events = kept;
+                (!batch.events.is_empty()).then_some(batch)
+            })
+            .collect();
+
+        if !expired.is_empty() {
+            log::warn!(
+                "Discarding {} events older than the maximum event age",
+                expired.len()
+            );
+            self.update_stats(|stats| stats.dropped += expired.len() as u64);
+            self.dead_letter_events(expired);
         }
 
-        Ok(())
+        batches
     }
 
-    /// Attempt to send all events currently in the event store
-    fn flush(&mut self) -> Result<(), Error> {
-        log::debug!("Flushing event store");
+    // Copies the headers, so the lock isn't held across requests
+    fn headers(&self) -> HashMap<String, String> {
+        match self.headers.read() {
+            Ok(headers) => headers.clone(),
+            Err(e) => {
+                log::warn!("Failed to read request headers: {e}");
+                HashMap::new()
+            }
+        }
+    }
 
-        // Get a lock on the event store
-        let mut store_lock = match self.event_store.lock() {
-            Ok(store) => store,
-            Err(e) => return Err(Error::EmitterError(e.to_string())),
+    // The number of events buffered before they are sent, within the batch size of the event store
+    fn batch_size(&self, store_batch_size: usize) -> usize {
+        let batch_size = match &self.adaptive_batch_size {
+            Some(adaptive_batch_size) => adaptive_batch_size.current(),
+            None => self
+                .flush_policy
+                .events_threshold()
+                .unwrap_or_else(|| self.buffer_option().size()),
         };
+        batch_size.min(store_batch_size)
+    }
 
-        // Send batches until the event store doesn't have enough events to fill a batch
-        while let Ok(batch) = store_lock.full_batch() {
-            if let Err(e) = self.tx.try_send(EmitterMessage::Send(batch)) {
-                return Err(Error::EmitterError(e.to_string()));
+    fn buffer_option(&self) -> BufferOption {
+        match self.buffer_option.read() {
+            Ok(buffer_option) => *buffer_option,
+            Err(e) => {
+                log::warn!("Failed to read buffer option: {e}");
+                BufferOption::default()
             }
         }
+    }
 
-        // Create a batch of the remaining events and send it
-        let remaining_events = store_lock.len();
-        let final_batch = store_lock.batch_of(remaining_events)?;
-        if let Err(e) = self.tx.try_send(EmitterMessage::Send(final_batch)) {
-            return Err(Error::EmitterError(e.to_string()));
-        };
+    // Removes the size of events taken from the event store from the buffered bytes
+    fn remove_buffered_bytes(&self, bytes: usize) {
+        let _ = self
+            .buffered_bytes
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |buffered| {
+                Some(buffered.saturating_sub(bytes))
+            });
+    }
 
-        log::debug!("Finished flushing event store");
+    fn is_sending_allowed(&self) -> bool {
+        !self.paused.load(Ordering::SeqCst)
+            && match &self.connectivity_check {
+                Some(check) => check(),
+                None => true,
+            }
+    }
+
+    // Returns an error if the payload is too large to be sent within the byte limit,
+    // or via GET unless it can fall back to POST
+    fn check_payload_size(&self, payload: &PayloadBuilder) -> Result<(), Error> {
+        let check_get = self.method == HttpMethod::Get && !self.get_fallback_to_post;
+        if self.byte_limit.is_none() && !check_get {
+            return Ok(());
+        }
+
+        let event = payload.clone().finalise_payload()?;
+        if let Some(limit) = self.byte_limit {
+            let size = EventBatch::request_size(&[EventBatch::event_size(&event)]);
+            if size > limit {
+                return Err(Error::PayloadTooLarge { size, limit });
+            }
+        }
+
+        if check_get {
+            let size = get_query_length(&event)?;
+            if size > MAX_GET_QUERY_LENGTH {
+                return Err(Error::PayloadTooLarge {
+                    size,
+                    limit: MAX_GET_QUERY_LENGTH,
+                });
+            }
+        }
 
         Ok(())
     }
 
-    /// Shut down and drop the emitter
-    ///
-    /// This will cancel any running tasks and may result in events being lost
-    fn close(&mut self) -> Result<(), Error> {
-        match self.tx.try_send(EmitterMessage::Close) {
-            Ok(_) => {
-                log::debug!("Closing emitter");
-                Ok(())
-            }
-            Err(e) => Err(Error::EmitterError(e.to_string())),
+    // Partitions batches by the partition key, then splits batches exceeding the byte limit, if either is set
+    fn split_batches(&self, batches: Vec<EventBatch>) -> Vec<EventBatch> {
+        let batches = match &self.partition_key {
+            Some(partition_key) => batches
+                .into_iter()
+                .flat_map(|batch| batch.partition_by(partition_key.as_ref()))
+                .collect(),
+            None => batches,
+        };
+
+        match self.byte_limit {
+            Some(limit) => batches
+                .into_iter()
+                .flat_map(|batch| batch.split_by_size(limit))
+                .collect(),
+            None => batches,
         }
     }
 
-    fn collector_url(&self) -> &str {
-        &self.collector_url
+    // Counts a failed request towards failing over from the collector it was sent to, if fallback collectors are set
+    fn record_failover(&self, collector: Option<usize>, result: &Result<HttpResponse, Error>) {
+        let (Some(failover), Some(collector)) = (&self.failover, collector) else {
+            return;
+        };
+
+        let failed = match result {
+            Ok(HttpResponse { status, .. }) | Err(Error::CollectorResponse { status, .. }) => {
+                BatchEmitter::should_retry(*status, &self.no_retry_status_codes)
+            }
+            Err(_) => true,
+        };
+        failover.record(collector, failed);
+    }
+
+    fn update_stats(&self, update: impl FnOnce(&mut EmitterStats)) {
+        match self.stats.lock() {
+            Ok(mut stats) => update(&mut stats),
+            Err(e) => log::warn!("Failed to update emitter stats: {e}"),
+        }
     }
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
+// The length in bytes of the URL-encoded query string of a GET request sending the event
+fn get_query_length(event: &Payload) -> Result<usize, Error> {
+    Ok(event.to_query_string()?.len())
+}
 
-    #[tokio::test]
-    async fn add_event_to_store() {
-        let mut emitter = BatchEmitter::new("http://localhost:8080");
-        let payload = PayloadBuilder::default();
+// A span around a flush, recording the number of events flushed
+#[cfg(feature = "tracing")]
+fn flush_span(batches: &[EventBatch]) -> tracing::Span {
+    let events: usize = batches.iter().map(|batch| batch.events.len()).sum();
+    tracing::info_span!("flush", events)
+}
 
-        emitter.add(payload).unwrap();
-        assert_eq!(emitter.event_store.lock().unwrap().len(), 1);
+// HTTP status codes that are not retried, unless set otherwise with [BatchEmitterBuilder::no_retry_status_codes]
+const DEFAULT_NO_RETRY_STATUS_CODES: [u16; 5] = [400, 401, 403, 410, 422];
 
-        emitter.close().unwrap();
+/// The batch sent to the Snowplow Collector, and the response code and body
+pub struct SentBatchResponse {
+    pub batch: EventBatch,
+    pub code: u16,
+    pub body: String,
+}
+
+impl BatchEmitter {
+    pub fn builder() -> BatchEmitterBuilder {
+        BatchEmitterBuilder::default()
     }
 
-    #[tokio::test]
-    async fn send_batch() {
-        let event_store = InMemoryEventStore::new(2, 2);
-        let mut emitter = BatchEmitter::builder()
-            .collector_url("http://localhost:8080")
-            .event_store(event_store)
-            .build()
-            .unwrap();
+    fn create_emitter(
+        collector_url: &str,
+        event_store_capacity: usize,
+        event_store: Arc<Mutex<dyn EventStore + Send + Sync>>,
+        http_client: Box<dyn HttpClient + Send + Sync>,
+        retry_policy: RetryPolicy,
+        send_context: SendContext,
+    ) -> BatchEmitter {
+        let (tx, rx) = tokio::sync::mpsc::channel(event_store_capacity);
+        let mut emitter = BatchEmitter {
+            collector_url: collector_url.to_string(),
+            http_client,
+            event_store,
+            executor_handle: None,
+            tx,
+            send_context: send_context.clone(),
+            send_namespace_header: false,
+            filter: None,
+            health_path: DEFAULT_HEALTH_PATH.to_string(),
+        };
 
-        emitter.add(PayloadBuilder::default()).unwrap();
-        assert_eq!(emitter.event_store.lock().unwrap().len(), 1);
+        // Clone http client to be used in the spawned thread
+        let client = emitter.http_client.clone();
+        let store = emitter.event_store.clone();
 
-        // Adding a second event should trigger a batch to be sent
-        emitter.add(PayloadBuilder::default()).unwrap();
-        assert_eq!(emitter.event_store.lock().unwrap().len(), 0);
+        // Spawn the tokio runtime in a separate thread
+        emitter.executor_handle = Some(std::thread::spawn(move || {
+            BatchEmitter::start_tokio(client, rx, store, retry_policy, send_context);
+        }));
 
-        emitter.close().unwrap();
+        emitter
     }
 
-    #[test]
-    fn should_retry() {
-        let below_200 = (0..=199).collect::<Vec<_>>();
-        let between_300_and_599 = (300..=599)
-            .into_iter()
-            .filter(|code| !DONT_RETRY_STATUS_CODES.contains(code))
-            .collect::<Vec<_>>();
-
-        let should_retry_codes = [below_200, between_300_and_599].concat();
+    /// Create a new [BatchEmitter] with an [InMemoryEventStore]
+    ///
+    /// The collector URL is normalized with [normalize_collector_url]. If it is invalid, it is
+    /// used as-is and a warning is logged; use [BatchEmitter::builder] to get an error instead.
+    pub fn new(collector_url: &str) -> BatchEmitter {
+        let collector_url = &normalize_collector_url_or_keep(collector_url);
+        BatchEmitter::create_emitter(
+            collector_url,
+            DEFAULT_EVENT_STORE_CAPACITY,
+            Arc::new(Mutex::new(InMemoryEventStore::default())),
+            ReqwestClient::new(collector_url),
+            RetryPolicy::MaxRetries(10),
+            SendContext::default(),
+        )
+    }
+
+    /// Create a new [BatchEmitter] with an [InMemoryEventStore], sending events using the provided [reqwest::Client]
+    ///
+    /// This lets the emitter share a client configured with TLS settings, proxies, or connection pooling.
+    pub fn with_client(collector_url: &str, client: reqwest::Client) -> BatchEmitter {
+        let collector_url = &normalize_collector_url_or_keep(collector_url);
+        BatchEmitter::create_emitter(
+            collector_url,
+            DEFAULT_EVENT_STORE_CAPACITY,
+            Arc::new(Mutex::new(InMemoryEventStore::default())),
+            ReqwestClient::with_client(collector_url, client),
+            RetryPolicy::MaxRetries(10),
+            SendContext::default(),
+        )
+    }
+
+    /// Create a new [BatchEmitter] with an [InMemoryEventStore], failing over between collectors
+    ///
+    /// Events are sent to the first collector URL, the primary one, and the others are fallbacks
+    /// tried in order (see [BatchEmitterBuilder::fallback_collector_url]).
+    /// Fails if no collector URL is provided, or any isn't a valid URL.
+    pub fn with_failover(collector_urls: Vec<&str>) -> Result<BatchEmitter, Error> {
+        let Some((primary_url, fallback_urls)) = collector_urls.split_first() else {
+            return Err(Error::EmitterError(
+                "At least one collector URL is required".to_string(),
+            ));
+        };
+
+        fallback_urls
+            .iter()
+            .fold(
+                BatchEmitter::builder().collector_url(primary_url),
+                |builder, fallback_url| builder.fallback_collector_url(fallback_url),
+            )
+            .build()
+    }
+
+    /// The URL of the collector events are currently sent to
+    ///
+    /// This is the collector URL, unless the emitter has failed over to a fallback collector.
+    pub fn active_collector_url(&self) -> String {
+        match &self.send_context.failover {
+            Some(failover) => failover.active().1.to_string(),
+            None => self.collector_url.clone(),
+        }
+    }
+
+    /// Create a new [BatchEmitter] with an [InMemoryEventStore], sending events to custom paths on the collector
+    ///
+    /// The standard paths are `/com.snowplowanalytics.snowplow/tp2` for POST and `/i` for GET requests,
+    /// but these are sometimes changed by proxies or CDNs in front of the collector. Both paths must start with `/`.
+    pub fn with_path(
+        collector_url: &str,
+        post_path: &str,
+        get_path: &str,
+    ) -> Result<BatchEmitter, Error> {
+        let collector_url = &normalize_collector_url(collector_url)?;
+        Ok(BatchEmitter::create_emitter(
+            collector_url,
+            DEFAULT_EVENT_STORE_CAPACITY,
+            Arc::new(Mutex::new(InMemoryEventStore::default())),
+            ReqwestClient::with_paths(collector_url, post_path, get_path)?,
+            RetryPolicy::MaxRetries(10),
+            SendContext::default(),
+        ))
+    }
+
+    /// Sends a GET request to the collector's health endpoint, succeeding if it responds with `200`
+    ///
+    /// This is what [Emitter::health_check] does, but the returned future is `Send`,
+    /// so it can be spawned on a multi-threaded runtime.
+    pub fn check_health(&self) -> impl Future<Output = Result<(), Error>> + Send + '_ {
+        let http_client = &self.http_client;
+        let health_path = &self.health_path;
+        async move {
+            match http_client.health_check(health_path).await? {
+                200 => Ok(()),
+                status => Err(Error::CollectorResponse {
+                    status,
+                    body: String::new(),
+                }),
+            }
+        }
+    }
+
+    // Whether the event is dropped by the filter, if set
+    fn is_filtered_out(&self, payload: &PayloadBuilder) -> bool {
+        let Some(filter) = &self.filter else {
+            return false;
+        };
+
+        match payload.clone().finalise_payload() {
+            Ok(payload) if !filter(&payload) => {
+                log::debug!("Event {} dropped by filter", payload.event_id());
+                true
+            }
+            Ok(_) => false,
+            // Kept so that it fails when sent, as it would without a filter
+            Err(_) => false,
+        }
+    }
+
+    /// Whether events can currently be sent, i.e. the emitter isn't paused and the device is online
+    pub fn is_sending_allowed(&self) -> bool {
+        self.send_context.is_sending_allowed()
+    }
+
+    /// The number of events buffered before they are sent, which changes over time with an adaptive batch size
+    pub fn batch_size(&self) -> usize {
+        let store_batch_size = match self.event_store.lock() {
+            Ok(store) => store.batch_size(),
+            Err(e) => {
+                log::warn!("Failed to lock event store: {e}");
+                return self.send_context.batch_size(usize::MAX);
+            }
+        };
+        self.send_context.batch_size(store_batch_size)
+    }
+
+    // Static Methods
+
+    // Adds an event to the event store, returning a batch to send if there are enough events
+    fn queue_event(
+        event_store: &Mutex<dyn EventStore + Send + Sync>,
+        mut payload: PayloadBuilder,
+        send_context: &SendContext,
+    ) -> Result<Option<EventBatch>, Error> {
+        send_context.attach_network_user_id(&mut payload);
+        let sending_allowed = send_context.is_sending_allowed();
+
+        let mut store = match event_store.lock() {
+            Ok(store) => store,
+            Err(e) => return Err(Error::EmitterError(e.to_string())),
+        };
+
+        if !sending_allowed && store.len() >= store.capacity() {
+            log::warn!("Emitter is paused and the event store is full, dropping event");
+            return Err(Error::Paused);
+        }
+
+        let event_size = match send_context.flush_policy.bytes_threshold() {
+            Some(_) => EventBatch::event_size(&payload.clone().finalise_payload()?),
+            None => 0,
+        };
+
+        match store.add(payload) {
+            Ok(_) => {
+                log::debug!("Added event to event store");
+                #[cfg(feature = "tracing")]
+                tracing::debug!(queue_depth = store.len(), "Event queued");
+            }
+            Err(e) => {
+                log::error!("Failed to add event to event store: {e}");
+                return Err(e);
+            }
+        }
+        let buffered_bytes = send_context
+            .buffered_bytes
+            .fetch_add(event_size, Ordering::SeqCst)
+            + event_size;
+
+        // Hold events in the event store until sending is allowed again
+        if !sending_allowed {
+            return Ok(None);
+        }
+
+        // If the buffered events reach the byte threshold, send as many as fit in a batch
+        if let Some(bytes_threshold) = send_context.flush_policy.bytes_threshold() {
+            if buffered_bytes >= bytes_threshold {
+                let batch_size = store.len().min(store.batch_size());
+                let batch = store.batch_of(batch_size).ok();
+                if let Some(batch) = &batch {
+                    let batch_bytes: usize = batch.events.iter().map(EventBatch::event_size).sum();
+                    send_context.remove_buffered_bytes(batch_bytes);
+                }
+                drop(store);
+                return Ok(send_context
+                    .discard_expired(batch.into_iter().collect())
+                    .pop());
+            }
+        }
+
+        // If the event store has enough events to fill a batch, return the batch
+        let batch_size = send_context.batch_size(store.batch_size());
+        if store.len() < batch_size {
+            return Ok(None);
+        }
+
+        // We can ignore the error here, as the only error that can return is the event store being empty,
+        // in which case we don't want to send a batch
+        let batch = store.batch_of(batch_size).ok();
+        if let (Some(batch), Some(_)) = (&batch, send_context.flush_policy.bytes_threshold()) {
+            let batch_bytes: usize = batch.events.iter().map(EventBatch::event_size).sum();
+            send_context.remove_buffered_bytes(batch_bytes);
+        }
+
+        // Events are backing up if another batch is ready, or no request can be sent right away
+        if let Some(adaptive_batch_size) = &send_context.adaptive_batch_size {
+            let backlogged =
+                store.len() >= batch_size || send_context.request_permits.available_permits() == 0;
+            adaptive_batch_size.adapt(backlogged);
+        }
+
+        drop(store);
+        Ok(send_context
+            .discard_expired(batch.into_iter().collect())
+            .pop())
+    }
+
+    // Removes all events from the event store, as full batches followed by a batch of any remaining events
+    fn drain_event_store(
+        event_store: &Mutex<dyn EventStore + Send + Sync>,
+        send_context: &SendContext,
+    ) -> Result<Vec<EventBatch>, Error> {
+        let mut store = match event_store.lock() {
+            Ok(store) => store,
+            Err(e) => return Err(Error::EmitterError(e.to_string())),
+        };
+        send_context.buffered_bytes.store(0, Ordering::SeqCst);
+
+        let mut batches = Vec::new();
+
+        // Batches stay within the bounds of an adaptive batch size
+        if let Some(adaptive_batch_size) = &send_context.adaptive_batch_size {
+            while store.len() > 0 {
+                let batch_size = store.len().min(adaptive_batch_size.max());
+                batches.push(store.batch_of(batch_size)?);
+            }
+        } else {
+            // Take batches until the event store doesn't have enough events to fill a batch
+            while let Ok(batch) = store.full_batch() {
+                batches.push(batch);
+            }
+
+            // Create a batch of the remaining events
+            let remaining_events = store.len();
+            if remaining_events > 0 {
+                batches.push(store.batch_of(remaining_events)?);
+            }
+        }
+
+        // The dead letter callback runs without holding the lock
+        drop(store);
+        Ok(send_context.discard_expired(batches))
+    }
+
+    fn is_successful_response(code: u16) -> bool {
+        code >= 200 && code < 300
+    }
+
+    // True if the code is outside 200-299 and not in `no_retry_status_codes`
+    fn should_retry(code: u16, no_retry_status_codes: &HashSet<u16>) -> bool {
+        match Self::is_successful_response(code) {
+            true => false,
+            false => !no_retry_status_codes.contains(&code),
+        }
+    }
+
+    fn retry_batch(
+        mut batch: EventBatch,
+        retry_jitter: Jitter,
+        retry_tx: tokio::sync::mpsc::UnboundedSender<EmitterMessage>,
+    ) {
+        batch.update_for_retry_with(retry_jitter, &mut rand::thread_rng());
+
+        let batch_id = batch.id;
+        match retry_tx.send(EmitterMessage::Send(batch)) {
+            Ok(_) => log::debug!("Batch {batch_id} re-queued"),
+            Err(e) => {
+                log::warn!("Failed to re-queue batch {batch_id}: {e}")
+            }
+        }
+    }
+
+    fn run_cleanup(
+        store: Arc<Mutex<dyn EventStore + Send + Sync>>,
+        batch: EventBatch,
+    ) -> Result<(), Error> {
+        let mut store_guard = match store.lock() {
+            Ok(guard) => guard,
+            Err(e) => {
+                return Err(Error::EmitterError(format!(
+                    "Failed to acquire event store lock: {e}"
+                )))
+            }
+        };
+
+        match store_guard.cleanup_after_send_attempt(batch.id) {
+            Ok(_) => log::debug!("Cleanup run for batch: {}", batch.id),
+            Err(e) => return Err(Error::EmitterError(format!("Failed to cleanup: {e}"))),
+        };
+
+        Ok(())
+    }
+
+    // Sends a batch, handling the response, and returns the response of the collector if it rejected
+    // the batch and it won't be retried, as a retry may still succeed
+    async fn batch_send_task(
+        mut batch: EventBatch,
+        client: Box<dyn HttpClient + Send + Sync>,
+        retry_tx: tokio::sync::mpsc::UnboundedSender<EmitterMessage>,
+        store: Arc<Mutex<dyn EventStore + Send + Sync>>,
+        retry_policy: RetryPolicy,
+        send_context: SendContext,
+    ) -> Result<(), Error> {
+        if let Some(delay) = batch.delay {
+            log::debug!("Delaying batch {} for {:?}", batch.id, delay);
+            tokio::time::sleep(delay).await;
+        };
+
+        // The sent timestamp is set right before sending, so it's also up to date on retries
+        batch.set_event_stm(send_context.clock.now());
+
+        let batch_length = batch.events.len() as u64;
+
+        // Hold a permit while sending, so at most `max_concurrent_requests` are in flight
+        let permit = send_context.request_permits.acquire().await;
+        let sent = Self::send_batch(batch, client, &send_context).await;
+        drop(permit);
+
+        // Events sent via GET before a failure are removed from the batch, and count as sent
+        let remaining = match &sent {
+            Ok(resp) => resp.batch.events.len(),
+            Err(batch) => batch.events.len(),
+        } as u64;
+        if remaining < batch_length {
+            send_context.update_stats(|stats| stats.sent += batch_length - remaining);
+        }
+        let batch_length = remaining;
+
+        match sent {
+            Ok(resp) => {
+                // We got a response from the collector, but need to check if
+                // it was successful
+
+                match (
+                    Self::should_retry(resp.code, &send_context.no_retry_status_codes),
+                    resp.batch.has_retry(retry_policy),
+                ) {
+                    // An unsuccessful response with retry attempts remaining
+                    (true, true) => {
+                        send_context.update_stats(|stats| {
+                            stats.failed += batch_length;
+                            stats.retried += batch_length;
+                        });
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(
+                            batch_id = %resp.batch.id,
+                            events = batch_length,
+                            status = resp.code,
+                            "Batch failed to send, retrying"
+                        );
+                        Self::retry_batch(resp.batch, send_context.retry_jitter, retry_tx);
+                        Ok(())
+                    }
+
+                    // An unsuccessful response with no retry attempts remaining
+                    (true, false) => {
+                        let rejection = Self::rejection(&resp);
+                        log::warn!("Batch {} failed to send, no retry available", resp.batch.id);
+                        #[cfg(feature = "tracing")]
+                        tracing::error!(
+                            batch_id = %resp.batch.id,
+                            events = batch_length,
+                            status = resp.code,
+                            "Batch failed to send, no retry available"
+                        );
+                        send_context.update_stats(|stats| {
+                            stats.failed += batch_length;
+                            stats.dropped += batch_length;
+                        });
+                        send_context.dead_letter(&resp.batch);
+                        match Self::run_cleanup(store, resp.batch) {
+                            Ok(_) => (),
+                            Err(e) => log::error!("{e}"),
+                        }
+                        Err(rejection)
+                    }
+
+                    // An unsuccessful response that shouldn't be retried
+                    (false, _) if !Self::is_successful_response(resp.code) => {
+                        let rejection = Self::rejection(&resp);
+                        log::warn!(
+                            "Batch {} failed to send with status code {}, not retrying",
+                            resp.batch.id,
+                            resp.code
+                        );
+                        #[cfg(feature = "tracing")]
+                        tracing::error!(
+                            batch_id = %resp.batch.id,
+                            events = batch_length,
+                            status = resp.code,
+                            "Batch failed to send, not retrying"
+                        );
+                        send_context.update_stats(|stats| {
+                            stats.failed += batch_length;
+                            stats.dropped += batch_length;
+                        });
+                        send_context.dead_letter(&resp.batch);
+                        match Self::run_cleanup(store, resp.batch) {
+                            Ok(_) => (),
+                            Err(e) => log::error!("{e}"),
+                        }
+                        Err(rejection)
+                    }
+
+                    // A successful response
+                    (false, _) => {
+                        log::info!("Sent batch {} of {batch_length} events", resp.batch.id);
+                        #[cfg(feature = "tracing")]
+                        tracing::info!(
+                            batch_id = %resp.batch.id,
+                            events = batch_length,
+                            status = resp.code,
+                            "Batch sent"
+                        );
+                        send_context.update_stats(|stats| stats.sent += batch_length);
+                        if let Some(on_success) = &send_context.on_success {
+                            on_success(&DeliveryReport::from_response(
+                                resp.code,
+                                &resp.body,
+                                batch_length,
+                            ));
+                        }
+                        match Self::run_cleanup(store, resp.batch) {
+                            Ok(_) => (),
+                            Err(e) => log::error!("{e}"),
+                        }
+                        Ok(())
+                    }
+                }
+            }
+
+            // The request to the collector failed - no response
+            Err(failed_batch) => {
+                if failed_batch.has_retry(retry_policy) {
+                    send_context.update_stats(|stats| {
+                        stats.failed += batch_length;
+                        stats.retried += batch_length;
+                    });
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(
+                        batch_id = %failed_batch.id,
+                        events = batch_length,
+                        "Batch failed to send, retrying"
+                    );
+                    Self::retry_batch(failed_batch, send_context.retry_jitter, retry_tx);
+                } else {
+                    log::warn!(
+                        "Batch {} failed to send, no retry available",
+                        failed_batch.id
+                    );
+                    #[cfg(feature = "tracing")]
+                    tracing::error!(
+                        batch_id = %failed_batch.id,
+                        events = batch_length,
+                        "Batch failed to send, no retry available"
+                    );
+                    send_context.update_stats(|stats| {
+                        stats.failed += batch_length;
+                        stats.dropped += batch_length;
+                    });
+                    send_context.dead_letter(&failed_batch);
+                    match Self::run_cleanup(store, failed_batch) {
+                        Ok(_) => (),
+                        Err(e) => log::error!("{e}"),
+                    }
+                }
+                // Only rejections by the collector are reported
+                Ok(())
+            }
+        }
+    }
+
+    // The error reported for a batch rejected by the collector
+    fn rejection(resp: &SentBatchResponse) -> Error {
+        Error::CollectorResponse {
+            status: resp.code,
+            body: resp.body.clone(),
+        }
+    }
+
+    // Sends batches one at a time in the order they are received, when the order of events is preserved
+    //
+    // Each batch is retried until it succeeds or runs out of retries before the next one is sent.
+    // A `FlushNow` message is answered once the batches received before it have been sent,
+    // with the last rejection by the collector since the previous `FlushNow`.
+    async fn ordered_send_task(
+        mut rx: tokio::sync::mpsc::UnboundedReceiver<EmitterMessage>,
+        client: Box<dyn HttpClient + Send + Sync>,
+        store: Arc<Mutex<dyn EventStore + Send + Sync>>,
+        retry_policy: RetryPolicy,
+        send_context: SendContext,
+        closing: Arc<AtomicBool>,
+    ) {
+        // Retries come back on this channel, rather than being queued behind later batches
+        let (retry_tx, mut retry_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut rejection = Ok(());
+
+        while let Some(message) = rx.recv().await {
+            let mut next = match message {
+                EmitterMessage::Send(batch) => Some(batch),
+                EmitterMessage::FlushNow(done) => {
+                    let _ = done.send(std::mem::replace(&mut rejection, Ok(())));
+                    None
+                }
+                _ => None,
+            };
+
+            while let Some(batch) = next.take() {
+                let sent = Self::batch_send_task(
+                    batch,
+                    client.clone(),
+                    retry_tx.clone(),
+                    store.clone(),
+                    retry_policy,
+                    send_context.clone(),
+                )
+                .await;
+
+                if let Ok(EmitterMessage::Send(retry)) = retry_rx.try_recv() {
+                    // As with unordered batches, retries are abandoned once the emitter is closed
+                    if !closing.load(Ordering::SeqCst) {
+                        // The retry decides whether the batch was rejected
+                        next = Some(retry);
+                        continue;
+                    }
+                    log::warn!("Emitter closed, batch {} will not be retried", retry.id);
+                }
+
+                if let Err(e) = sent {
+                    rejection = Err(e);
+                }
+            }
+        }
+    }
+
+    // Sends an EventBatch to the collector
+    async fn send_batch(
+        mut batch: EventBatch,
+        mut http_client: Box<dyn HttpClient + Send + Sync>,
+        send_context: &SendContext,
+    ) -> Result<SentBatchResponse, EventBatch> {
+        if let Some(sink) = &send_context.sink {
+            // A sink has no response, so a sent batch is treated as accepted by a collector
+            let result = sink.send(batch.events.clone()).await.map(|_| HttpResponse {
+                status: 200,
+                body: String::new(),
+            });
+            return Self::handle_send_result(batch, result);
+        }
+
+        let headers = send_context.headers();
+
+        // The client is a clone for this batch, so it can be pointed at the active collector
+        let collector = send_context.failover.as_ref().map(|failover| {
+            let (index, collector_url) = failover.active();
+            http_client.set_collector_url(collector_url);
+            index
+        });
+
+        if send_context.method == HttpMethod::Get {
+            let result = Self::send_batch_via_get(
+                &mut batch,
+                http_client.as_ref(),
+                &headers,
+                send_context.rate_limiter.as_ref(),
+                send_context.get_fallback_to_post,
+            )
+            .await;
+            send_context.record_failover(collector, &result);
+            return Self::handle_send_result(batch, result);
+        }
+
+        if let Some(rate_limiter) = &send_context.rate_limiter {
+            rate_limiter.acquire().await;
+        }
+        let mut result = http_client
+            .post_with_response(batch.as_payload(), &headers)
+            .await;
+
+        // Fall back to GET if the POST request was rejected with one of the configured status codes
+        let post_status = match &result {
+            Ok(resp) => Some(resp.status),
+            Err(Error::CollectorResponse { status, .. }) => Some(*status),
+            Err(_) => None,
+        };
+        if let Some(status) = post_status {
+            if send_context.get_fallback_status_codes.contains(&status) {
+                log::debug!(
+                    "POST of batch {} failed with status code {status}, falling back to GET",
+                    batch.id
+                );
+                result = Self::send_batch_via_get(
+                    &mut batch,
+                    http_client.as_ref(),
+                    &headers,
+                    send_context.rate_limiter.as_ref(),
+                    false,
+                )
+                .await;
+            }
+        }
+
+        send_context.record_failover(collector, &result);
+        Self::handle_send_result(batch, result)
+    }
+
+    // Turns the result of sending a batch into a response to handle, or the batch to re-queue
+    fn handle_send_result(
+        batch: EventBatch,
+        result: Result<HttpResponse, Error>,
+    ) -> Result<SentBatchResponse, EventBatch> {
+        match result {
+            Ok(HttpResponse { status, body }) => {
+                log::debug!("Batch {} sent with status code {}", batch.id, status);
+                Ok(SentBatchResponse {
+                    batch,
+                    code: status,
+                    body,
+                })
+            }
+            // The collector did respond, so the status code decides whether to retry
+            Err(Error::CollectorResponse { status, body }) => {
+                log::warn!(
+                    "Batch {} rejected by the collector with status code {status}: {body}",
+                    batch.id
+                );
+                Ok(SentBatchResponse {
+                    batch,
+                    code: status,
+                    body,
+                })
+            }
+            Err(e) => {
+                log::warn!("Failed to send batch {}: {e}, re-queueing...", batch.id);
+                Err(batch)
+            }
+        }
+    }
+
+    // Sends each event in the batch in its own GET request, stopping at the first failure
+    //
+    // Events sent before the failure are removed from the batch, so they aren't sent again if it's retried.
+    // If `post_oversized` is set, events too large to be sent via GET are sent on their own via POST.
+    // Each request waits for the rate limiter, if set.
+    async fn send_batch_via_get(
+        batch: &mut EventBatch,
+        http_client: &(dyn HttpClient + Send + Sync),
+        headers: &HashMap<String, String>,
+        rate_limiter: Option<&RateLimiter>,
+        post_oversized: bool,
+    ) -> Result<HttpResponse, Error> {
+        let mut result = Ok(200);
+        let mut sent = 0;
+        for event in batch.events.iter() {
+            if let Some(rate_limiter) = rate_limiter {
+                rate_limiter.acquire().await;
+            }
+            let oversized = match post_oversized.then(|| get_query_length(event)) {
+                Some(Ok(length)) => length > MAX_GET_QUERY_LENGTH,
+                Some(Err(e)) => {
+                    result = Err(e);
+                    break;
+                }
+                None => false,
+            };
+            result = if oversized {
+                let single = EventBatch::new(event.event_id(), vec![event.clone()]);
+                http_client
+                    .post_with_headers(single.as_payload(), headers)
+                    .await
+            } else {
+                http_client.get(event, headers).await
+            };
+            match result {
+                Ok(status) if Self::is_successful_response(status) => sent += 1,
+                _ => break,
+            }
+        }
+
+        if sent < batch.events.len() {
+            batch.events.drain(..sent);
+        }
+        result.map(|status| HttpResponse {
+            status,
+            body: String::new(),
+        })
+    }
+
+    // Waits for the next tick of the flush timer, or forever if periodic flushing is disabled
+    async fn next_flush(flush_timer: &mut Option<tokio::time::Interval>) {
+        match flush_timer {
+            Some(timer) => {
+                timer.tick().await;
+            }
+            None => std::future::pending().await,
+        }
+    }
+
+    // Starts a tokio runtime and runs the emitter loop
+    fn start_tokio(
+        http_client: Box<dyn HttpClient + Send + Sync>,
+        mut rx: tokio::sync::mpsc::Receiver<EmitterMessage>,
+        event_store: Arc<Mutex<dyn EventStore + Send + Sync>>,
+        retry_policy: RetryPolicy,
+        send_context: SendContext,
+    ) {
+        // Create a new runtime to handle the async tasks
+        // Unwrap here as if the runtime fails to start, there is nothing we can do
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        // The main emitter loop
+        // This continuously loops and checks for new batches to send
+        rt.block_on(async {
+            // The currently running tokio tasks
+            let mut tokio_tasks: Vec<tokio::task::JoinHandle<Result<(), Error>>> = Vec::new();
+            let (retry_tx, mut retry_rx) = tokio::sync::mpsc::unbounded_channel();
+            let mut flush_timer = send_context.flush_interval.map(|period| {
+                tokio::time::interval_at(tokio::time::Instant::now() + period, period)
+            });
+
+            // When preserving order, batches are handed to a single task sending them one at a time
+            let closing = Arc::new(AtomicBool::new(false));
+            let mut ordered_sender = send_context.preserve_order.then(|| {
+                let (ordered_tx, ordered_rx) = tokio::sync::mpsc::unbounded_channel();
+                let task = tokio::spawn(Self::ordered_send_task(
+                    ordered_rx,
+                    http_client.clone(),
+                    event_store.clone(),
+                    retry_policy,
+                    send_context.clone(),
+                    closing.clone(),
+                ));
+                (ordered_tx, task)
+            });
+
+            loop {
+                // `rx.recv().await` will not resolve until either a message is received,
+                // or the channel is closed and there are no more messages, in which case we exit the loop
+
+                // select! is used to check both the `retry_rx` channel and the `rx` channel for new messages
+                let message = match tokio::select! {
+                    // `biased;` is used to ensure that the `retry_rx` channel is checked first, so retries get priority
+                    biased;
+
+                    retry = retry_rx.recv() => retry,
+                    event = rx.recv() => event,
+                    _ = Self::next_flush(&mut flush_timer) => Some(EmitterMessage::Flush),
+                } {
+                    Some(message) => message,
+                    None => break,
+                };
+
+                let batches = match message {
+                    EmitterMessage::Send(batch) => vec![batch],
+
+                    EmitterMessage::Add(payload) => {
+                        let queued = Self::queue_event(&event_store, *payload, &send_context);
+                        send_context.pending_adds.fetch_sub(1, Ordering::SeqCst);
+
+                        match queued {
+                            Ok(batch) => batch.into_iter().collect(),
+                            Err(e) => {
+                                log::error!("Failed to add event: {e}");
+                                send_context.update_stats(|stats| stats.dropped += 1);
+                                continue;
+                            }
+                        }
+                    }
+
+                    EmitterMessage::Flush => {
+                        if !send_context.is_sending_allowed() {
+                            continue;
+                        }
+
+                        match Self::drain_event_store(&event_store, &send_context) {
+                            Ok(batches) => batches,
+                            Err(e) => {
+                                log::error!("Failed to flush event store: {e}");
+                                continue;
+                            }
+                        }
+                    }
+
+                    EmitterMessage::FlushNow(done) => {
+                        if let Some((ordered_tx, _)) = &ordered_sender {
+                            let mut batches = Vec::new();
+                            if send_context.is_sending_allowed() {
+                                match Self::drain_event_store(&event_store, &send_context) {
+                                    Ok(drained) => batches = drained,
+                                    Err(e) => log::error!("Failed to flush event store: {e}"),
+                                }
+                            }
+
+                            // Queued behind the batches, so the caller is notified once they have been sent
+                            let messages = send_context
+                                .split_batches(batches)
+                                .into_iter()
+                                .map(EmitterMessage::Send)
+                                .chain([EmitterMessage::FlushNow(done)]);
+                            for message in messages {
+                                if ordered_tx.send(message).is_err() {
+                                    log::error!("Ordered sending stopped, failed to flush");
+                                }
+                            }
+                            continue;
+                        }
+
+                        // Wait for in-flight batches, so all events sent before the flush are sent when it completes
+                        let mut rejection = Ok(());
+                        for task in tokio_tasks.drain(..) {
+                            if let Err(e) = task.await.unwrap() {
+                                rejection = Err(e);
+                            }
+                        }
+
+                        if send_context.is_sending_allowed() {
+                            match Self::drain_event_store(&event_store, &send_context) {
+                                Ok(batches) => {
+                                    #[cfg(feature = "tracing")]
+                                    let flush_span = flush_span(&batches);
+
+                                    for batch in send_context.split_batches(batches) {
+                                        let send = Self::batch_send_task(
+                                            batch,
+                                            http_client.clone(),
+                                            retry_tx.clone(),
+                                            event_store.clone(),
+                                            retry_policy,
+                                            send_context.clone(),
+                                        );
+                                        #[cfg(feature = "tracing")]
+                                        let send = tracing::Instrument::instrument(
+                                            send,
+                                            flush_span.clone(),
+                                        );
+                                        if let Err(e) = send.await {
+                                            rejection = Err(e);
+                                        }
+                                    }
+                                }
+                                Err(e) => log::error!("Failed to flush event store: {e}"),
+                            }
+                        }
+
+                        // The caller may have stopped waiting, in which case there is no one to notify
+                        let _ = done.send(rejection);
+                        continue;
+                    }
+
+                    // On break, the emitter and runtime will be dropped
+                    //
+                    // Tokio will cancel any running tasks once the runtime is dropped, meaning any queued or retry batches will be lost,
+                    // so we attempt to send any remaining batches before exiting
+                    EmitterMessage::Close => {
+                        if let Some((ordered_tx, task)) = ordered_sender.take() {
+                            closing.store(true, Ordering::SeqCst);
+                            drop(ordered_tx);
+                            task.await.unwrap();
+                        }
+
+                        let remaining = tokio_tasks.len();
+                        for (i, task) in tokio_tasks.iter_mut().enumerate() {
+                            log::debug!("Waiting for task {}/{remaining} to complete", i + 1);
+                            // Rejected batches have already been logged, and there is no caller to report them to
+                            let _ = task.await.unwrap();
+                        }
+                        break;
+                    }
+                };
+
+                for batch in send_context.split_batches(batches) {
+                    if let Some((ordered_tx, _)) = &ordered_sender {
+                        if let Err(e) = ordered_tx.send(EmitterMessage::Send(batch)) {
+                            log::error!("Ordered sending stopped, failed to send batch: {e}");
+                        }
+                        continue;
+                    }
+
+                    // Clone to move into the task
+                    let client = http_client.clone();
+                    let retry_transmitter = retry_tx.clone();
+                    let store = event_store.clone();
+                    let send_context = send_context.clone();
+
+                    // Spawn a new task to send the batch
+                    tokio_tasks.push(tokio::spawn(async move {
+                        Self::batch_send_task(
+                            batch,
+                            client,
+                            retry_transmitter,
+                            store,
+                            retry_policy,
+                            send_context,
+                        )
+                        .await
+                    }));
+                }
+
+                // Discard any completed tasks in the task list
+                tokio_tasks.retain(|t| !t.is_finished());
+            }
+        });
+    }
+}
+
+impl Drop for BatchEmitter {
+    fn drop(&mut self) {
+        // Get the join handle for the thread running the tokio runtime and wait for it to finish
+        //
+        // It's likely that the thread has already finished once the emitter loop has exited
+        if let Some(handle) = self.executor_handle.take() {
+            handle.join().unwrap();
+            log::debug!("BatchEmitter thread joined");
+        }
+        log::debug!("BatchEmitter dropped");
+    }
+}
+
+#[async_trait(?Send)]
+impl Emitter for BatchEmitter {
+    /// Adds a payload to the event store
+    ///
+    /// This may also trigger sending a payload to the collector if the event store has enough events to fill a batch
+    fn add(&mut self, payload: PayloadBuilder) -> Result<(), Error> {
+        if self.is_filtered_out(&payload) {
+            return Ok(());
+        }
+        self.send_context.check_payload_size(&payload)?;
+
+        if let Some(batch) = Self::queue_event(&self.event_store, payload, &self.send_context)? {
+            return match self.tx.try_send(EmitterMessage::Send(batch)) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(Error::EmitterError(e.to_string())),
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Hands a payload to the thread sending events, which adds it to the event store
+    ///
+    /// Returns an error without waiting if the emitter's queue of messages is full
+    fn add_nonblocking(&mut self, payload: PayloadBuilder) -> Result<(), Error> {
+        if self.is_filtered_out(&payload) {
+            return Ok(());
+        }
+        self.send_context.check_payload_size(&payload)?;
+
+        self.send_context
+            .pending_adds
+            .fetch_add(1, Ordering::SeqCst);
+
+        if let Err(e) = self.tx.try_send(EmitterMessage::Add(Box::new(payload))) {
+            self.send_context
+                .pending_adds
+                .fetch_sub(1, Ordering::SeqCst);
+            return Err(Error::EmitterError(e.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Attempt to send all events currently in the event store
+    fn flush(&mut self) -> Result<(), Error> {
+        if !self.is_sending_allowed() {
+            log::debug!("Emitter is paused, skipping flush");
+            return Ok(());
+        }
+
+        log::debug!("Flushing event store");
+
+        // Events added with `add_nonblocking` may not be in the event store yet,
+        // in which case the thread flushes again once it has added them.
+        // This is checked first, so events added while flushing aren't missed
+        let adds_pending = self.send_context.pending_adds.load(Ordering::SeqCst) > 0;
+
+        let batches = Self::drain_event_store(&self.event_store, &self.send_context)?;
+        #[cfg(feature = "tracing")]
+        let _flush_span = flush_span(&batches).entered();
+
+        for batch in batches {
+            if let Err(e) = self.tx.try_send(EmitterMessage::Send(batch)) {
+                return Err(Error::EmitterError(e.to_string()));
+            }
+        }
+
+        if adds_pending {
+            if let Err(e) = self.tx.try_send(EmitterMessage::Flush) {
+                return Err(Error::EmitterError(e.to_string()));
+            }
+        }
+
+        log::debug!("Finished flushing event store");
+
+        Ok(())
+    }
+
+    /// Send all events in the event store, waiting until they have been sent
+    ///
+    /// Events are sent regardless of the [BufferOption], after any batches already being sent.
+    /// Failed batches are retried as usual, but this doesn't wait for the retries.
+    /// Nothing is sent while the emitter is paused.
+    ///
+    /// If the collector rejected a batch sent by the flush, or one in flight when it started, and the batch
+    /// won't be retried, this returns [Error::CollectorResponse] with the status code and body of the last
+    /// such rejection. A batch being retried isn't reported, as the retry may still succeed.
+    /// When the order of events is preserved, retries are waited for, so every batch gets its final outcome.
+    fn flush_now(&mut self) -> Result<(), Error> {
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+
+        if let Err(e) = self.tx.try_send(EmitterMessage::FlushNow(done_tx)) {
+            return Err(Error::EmitterError(e.to_string()));
+        }
+
+        match done_rx.recv() {
+            Ok(result) => result,
+            Err(e) => Err(Error::EmitterError(format!(
+                "Emitter stopped before flushing: {e}"
+            ))),
+        }
+    }
+
+    /// Shut down and drop the emitter
+    ///
+    /// This will cancel any running tasks and may result in events being lost.
+    /// Events held while the emitter is paused are not sent, see [BatchEmitter::pause].
+    fn close(&mut self) -> Result<(), Error> {
+        match self.tx.try_send(EmitterMessage::Close) {
+            Ok(_) => {
+                log::debug!("Closing emitter");
+                Ok(())
+            }
+            Err(e) => Err(Error::EmitterError(e.to_string())),
+        }
+    }
+
+    fn collector_url(&self) -> &str {
+        &self.collector_url
+    }
+
+    /// Sends a GET request to the collector's health endpoint, succeeding if it responds with `200`
+    async fn health_check(&self) -> Result<(), Error> {
+        self.check_health().await
+    }
+
+    /// Drops events for which the filter returns `false` as they are added
+    fn set_filter(&mut self, filter: EventFilter) -> Result<(), Error> {
+        self.filter = Some(filter);
+        Ok(())
+    }
+
+    /// Sets the number of events buffered before they are sent, as with [BatchEmitterBuilder::buffer_option]
+    fn with_buffer_option(self, buffer_option: BufferOption) -> Result<Self, Error> {
+        match self.send_context.buffer_option.write() {
+            Ok(mut current) => *current = buffer_option,
+            Err(e) => {
+                return Err(Error::EmitterError(format!(
+                    "Failed to set buffer option: {e}"
+                )))
+            }
+        }
+        Ok(self)
+    }
+
+    /// Calls the callback with the events of batches dropped after running out of retries,
+    /// or rejected with a status code that isn't retried
+    fn on_dead_letter(&mut self, dead_letter: DeadLetter) -> Result<(), Error> {
+        match self.send_context.dead_letter.write() {
+            Ok(mut current) => {
+                *current = Some(dead_letter);
+                Ok(())
+            }
+            Err(e) => Err(Error::EmitterError(format!(
+                "Failed to set dead letter callback: {e}"
+            ))),
+        }
+    }
+
+    /// Sends the namespace in the `X-SP-Namespace` header, if enabled with [BatchEmitterBuilder::send_namespace_header]
+    fn set_namespace(&mut self, namespace: &str) {
+        if !self.send_namespace_header {
+            return;
+        }
+
+        match self.send_context.headers.write() {
+            Ok(mut headers) => {
+                headers.insert(NAMESPACE_HEADER.to_string(), namespace.to_string());
+            }
+            Err(e) => log::error!("Failed to set namespace header: {e}"),
+        }
+    }
+
+    /// Stop sending events, while still adding new events to the event store
+    ///
+    /// Adding an event fails with [Error::Paused] if the event store is full while paused.
+    /// Closing the emitter while paused doesn't send the events held in the event store,
+    /// so resume it first to send them.
+    fn pause(&mut self) -> Result<(), Error> {
+        log::debug!("Pausing emitter");
+        self.send_context.paused.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Resume sending events, sending all events queued in the event store while paused
+    fn resume(&mut self) -> Result<(), Error> {
+        log::debug!("Resuming emitter");
+        self.send_context.paused.store(false, Ordering::SeqCst);
+        self.flush()
+    }
+
+    fn stats(&self) -> EmitterStats {
+        let buffered = match self.event_store.lock() {
+            Ok(store) => store.len(),
+            Err(e) => {
+                log::warn!("Failed to lock event store: {e}");
+                0
+            }
+        };
+
+        match self.send_context.stats.lock() {
+            Ok(stats) => EmitterStats { buffered, ..*stats },
+            Err(e) => {
+                log::warn!("Failed to read emitter stats: {e}");
+                EmitterStats {
+                    buffered,
+                    ..EmitterStats::default()
+                }
+            }
+        }
+    }
+
+    fn reset_stats(&mut self) {
+        self.send_context
+            .update_stats(|stats| *stats = EmitterStats::default());
+    }
+
+    /// Removes and returns all events in the event store, without sending them
+    ///
+    /// The event store stays locked while it is drained, so the thread sending events can't take
+    /// any of them meanwhile. Events added with `add_nonblocking` that the thread hasn't added to
+    /// the event store yet, and batches already being sent or waiting to be retried, aren't returned.
+    fn drain(&mut self) -> Vec<Payload> {
+        let mut store = match self.event_store.lock() {
+            Ok(store) => store,
+            Err(e) => {
+                log::warn!("Failed to lock event store: {e}");
+                return Vec::new();
+            }
+        };
+        self.send_context.buffered_bytes.store(0, Ordering::SeqCst);
+
+        let mut payloads = Vec::with_capacity(store.len());
+        while store.len() > 0 {
+            let size = store.len().min(store.batch_size());
+            match store.batch_of(size) {
+                Ok(batch) => payloads.extend(batch.events),
+                Err(e) => {
+                    log::warn!("Failed to drain event store: {e}");
+                    break;
+                }
+            }
+        }
+
+        payloads
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use async_trait::async_trait;
+
+    use std::sync::mpsc::Receiver;
+    use std::time::Instant;
+
+    use crate::http_client::mock_collector::{
+        mock_collector, mock_collector_with_headers, MockRequest,
+    };
+    use crate::payload::{EventType, Payload, SelfDescribingEventData, SelfDescribingJson};
+
+    use super::*;
+
+    fn payload() -> PayloadBuilder {
+        Payload::builder()
+            .p("p".to_string())
+            .tv("tv".to_string())
+            .eid(Uuid::new_v4())
+            .dtm(Utc::now())
+            .aid("aid".to_string())
+    }
+
+    #[tokio::test]
+    async fn add_event_to_store() {
+        let mut emitter = BatchEmitter::new("http://localhost:8080");
+        let payload = PayloadBuilder::default();
+
+        emitter.add(payload).unwrap();
+        assert_eq!(emitter.event_store.lock().unwrap().len(), 1);
+
+        emitter.close().unwrap();
+    }
+
+    #[tokio::test]
+    async fn send_batch() {
+        let event_store = InMemoryEventStore::new(2, 2);
+        let mut emitter = BatchEmitter::builder()
+            .collector_url("http://localhost:8080")
+            .event_store(event_store)
+            .build()
+            .unwrap();
+
+        emitter.add(PayloadBuilder::default()).unwrap();
+        assert_eq!(emitter.event_store.lock().unwrap().len(), 1);
+
+        // Adding a second event should trigger a batch to be sent
+        emitter.add(PayloadBuilder::default()).unwrap();
+        assert_eq!(emitter.event_store.lock().unwrap().len(), 0);
+
+        emitter.close().unwrap();
+    }
+
+    #[tokio::test]
+    async fn paused_emitter_holds_events_until_resumed() {
+        let event_store = InMemoryEventStore::new(10, 2);
+        let mut emitter = BatchEmitter::builder()
+            .collector_url("http://localhost:8080")
+            .event_store(event_store)
+            .build()
+            .unwrap();
+
+        emitter.pause().unwrap();
+        for _ in 0..5 {
+            emitter.add(payload()).unwrap();
+        }
+        assert_eq!(emitter.event_store.lock().unwrap().len(), 5);
+
+        emitter.flush().unwrap();
+        assert_eq!(emitter.event_store.lock().unwrap().len(), 5);
+
+        emitter.resume().unwrap();
+        assert_eq!(emitter.event_store.lock().unwrap().len(), 0);
+
+        emitter.close().unwrap();
+    }
+
+    #[test]
+    fn drain_returns_buffered_events_without_sending() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 10))
+            .build()
+            .unwrap();
+
+        let builders: Vec<_> = (0..5).map(|_| payload()).collect();
+        let expected_eids: Vec<_> = builders.iter().map(|b| b.eid.unwrap()).collect();
+        for builder in builders {
+            emitter.add(builder).unwrap();
+        }
+
+        let drained = emitter.drain();
+        let buffer_len = emitter.buffer_len();
+        let drained_again = emitter.drain();
+
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let drained_eids: Vec<_> = drained.iter().map(|p| p.eid).collect();
+        assert_eq!(drained_eids, expected_eids);
+        assert_eq!(buffer_len, 0);
+        assert!(drained_again.is_empty());
+        assert!(requests.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn paused_emitter_with_full_event_store_returns_paused_error() {
+        let event_store = InMemoryEventStore::new(2, 2);
+        let mut emitter = BatchEmitter::builder()
+            .collector_url("http://localhost:8080")
+            .event_store(event_store)
+            .build()
+            .unwrap();
+
+        emitter.pause().unwrap();
+        emitter.add(payload()).unwrap();
+        emitter.add(payload()).unwrap();
+
+        assert!(matches!(emitter.add(payload()), Err(Error::Paused)));
+
+        emitter.resume().unwrap();
+        emitter.close().unwrap();
+    }
+
+    #[tokio::test]
+    async fn offline_emitter_holds_events_until_online() {
+        let online = Arc::new(AtomicBool::new(false));
+        let online_check = online.clone();
+
+        let event_store = InMemoryEventStore::new(10, 2);
+        let mut emitter = BatchEmitter::builder()
+            .collector_url("http://localhost:8080")
+            .event_store(event_store)
+            .connectivity_check(move || online_check.load(Ordering::SeqCst))
+            .build()
+            .unwrap();
+
+        emitter.add(payload()).unwrap();
+        emitter.add(payload()).unwrap();
+        assert_eq!(emitter.event_store.lock().unwrap().len(), 2);
+
+        online.store(true, Ordering::SeqCst);
+        emitter.flush().unwrap();
+        assert_eq!(emitter.event_store.lock().unwrap().len(), 0);
+
+        emitter.close().unwrap();
+    }
+
+    #[test]
+    fn should_retry() {
+        let below_200 = (0..=199).collect::<Vec<_>>();
+        let between_300_and_599 = (300..=599)
+            .into_iter()
+            .filter(|code| !DEFAULT_NO_RETRY_STATUS_CODES.contains(code))
+            .collect::<Vec<_>>();
+
+        let should_retry_codes = [below_200, between_300_and_599].concat();
+
+        for code in 0..=599 {
+            assert_eq!(
+                BatchEmitter::should_retry(code, &HashSet::from(DEFAULT_NO_RETRY_STATUS_CODES)),
+                should_retry_codes.contains(&code)
+            )
+        }
+    }
+
+    #[test]
+    fn falls_back_to_get_when_post_is_rejected() {
+        let (url, requests) = mock_collector(|request| match request.method.as_str() {
+            "POST" => (405, "Method Not Allowed".to_string()),
+            _ => (200, String::new()),
+        });
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 1))
+            .retry_policy(RetryPolicy::NoRetry)
+            .get_fallback_status_codes(&[403, 405])
+            .build()
+            .unwrap();
+
+        let payload = payload();
+        let event_id = payload.eid.unwrap();
+        emitter.add(payload).unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let requests: Vec<_> = requests.try_iter().collect();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].headers["content-type"], "application/json");
+        assert!(requests[0].body.contains(&event_id.to_string()));
+        assert_eq!(requests[1].method, "GET");
+        assert!(requests[1].path.starts_with("/i?"));
+        assert!(requests[1].path.contains(&format!("eid={event_id}")));
+    }
+
+    #[test]
+    fn does_not_fall_back_to_get_by_default() {
+        let (url, requests) = mock_collector(|_| (405, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 1))
+            .retry_policy(RetryPolicy::NoRetry)
+            .build()
+            .unwrap();
+
+        emitter.add(payload()).unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let methods: Vec<_> = requests.try_iter().map(|r| r.method).collect();
+        assert_eq!(methods, vec!["POST"]);
+    }
+
+    #[test]
+    fn server_anonymisation_sends_anonymous_header() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 1))
+            .server_anonymisation(true)
+            .build()
+            .unwrap();
+
+        emitter.add(payload()).unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let request = requests.try_recv().unwrap();
+        assert_eq!(request.headers["sp-anonymous"], "*");
+    }
+
+    #[test]
+    fn on_success_reports_counts_from_response_body() {
+        for (body, expected) in [(r#"{"accepted": 1, "rejected": 1}"#, (1, 1)), ("", (2, 0))] {
+            let (url, _requests) = mock_collector(move |_| (200, body.to_string()));
+            let reports = Arc::new(Mutex::new(Vec::new()));
+            let reported = reports.clone();
+            let mut emitter = BatchEmitter::builder()
+                .collector_url(&url)
+                .event_store(InMemoryEventStore::new(10, 2))
+                .on_success(move |report| reported.lock().unwrap().push(*report))
+                .build()
+                .unwrap();
+
+            emitter.add(payload()).unwrap();
+            emitter.add(payload()).unwrap();
+            emitter.flush_now().unwrap();
+            emitter.close().unwrap();
+            drop(emitter);
+
+            let reports = reports.lock().unwrap();
+            assert_eq!(reports.len(), 1);
+            assert_eq!(reports[0].status, 200);
+            assert_eq!((reports[0].accepted, reports[0].rejected), expected);
+        }
+    }
+
+    #[test]
+    fn requests_are_sent_through_proxy() {
+        let (proxy_url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url("http://collector.example.com")
+            .event_store(InMemoryEventStore::new(10, 1))
+            .proxy(&proxy_url)
+            .proxy_basic_auth("user", "pass")
+            .build()
+            .unwrap();
+
+        emitter.add(payload()).unwrap();
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let request = requests.try_recv().unwrap();
+        assert_eq!(
+            request.path,
+            "http://collector.example.com/com.snowplowanalytics.snowplow/tp2"
+        );
+        assert_eq!(request.headers["proxy-authorization"], "Basic dXNlcjpwYXNz");
+    }
+
+    #[test]
+    fn invalid_proxy_url_fails_to_build() {
+        for proxy_url in [
+            "not a url",
+            "ftp://proxy.example.com",
+            "proxy.example.com:3128",
+        ] {
+            let result = BatchEmitter::builder()
+                .collector_url("http://collector.example.com")
+                .proxy(proxy_url)
+                .build();
+            assert!(matches!(result, Err(Error::BuilderError(_))), "{proxy_url}");
+        }
+    }
+
+    #[test]
+    fn preserve_order_holds_later_batches_until_retry_succeeds() {
+        let attempts = AtomicUsize::new(0);
+        let (url, requests) =
+            mock_collector(move |_| match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 => (500, String::new()),
+                _ => (200, String::new()),
+            });
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 1))
+            .retry_jitter(Jitter::Full)
+            .preserve_order(true)
+            .build()
+            .unwrap();
+
+        let first = payload();
+        let second = payload();
+        let expected_eids = vec![first.eid.unwrap(), first.eid.unwrap(), second.eid.unwrap()];
+        emitter.add(first).unwrap();
+        emitter.add(second).unwrap();
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let sent_eids: Vec<_> = requests
+            .try_iter()
+            .map(|request| {
+                let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+                Uuid::parse_str(body["data"][0]["eid"].as_str().unwrap()).unwrap()
+            })
+            .collect();
+        assert_eq!(sent_eids, expected_eids);
+    }
+
+    #[test]
+    fn events_fail_over_to_fallback_collector() {
+        let (primary_url, primary_requests) = mock_collector(|_| (503, String::new()));
+        let (fallback_url, fallback_requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&primary_url)
+            .fallback_collector_url(&fallback_url)
+            .failover_after(2)
+            .event_store(InMemoryEventStore::new(10, 1))
+            .retry_jitter(Jitter::None)
+            .retry_policy(RetryPolicy::MaxRetries(2))
+            .preserve_order(true)
+            .build()
+            .unwrap();
+
+        emitter.add(payload()).unwrap();
+        emitter.flush_now().unwrap();
+        let active_collector_url = emitter.active_collector_url();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        assert_eq!(primary_requests.try_iter().count(), 2);
+        assert_eq!(fallback_requests.try_iter().count(), 1);
+        assert_eq!(
+            active_collector_url,
+            normalize_collector_url(&fallback_url).unwrap()
+        );
+    }
+
+    #[test]
+    fn requests_are_spaced_by_rate_limit() {
+        let received_at = Arc::new(Mutex::new(Vec::new()));
+        let received = received_at.clone();
+        let (url, _requests) = mock_collector(move |_| {
+            received.lock().unwrap().push(Instant::now());
+            (200, String::new())
+        });
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(20, 1))
+            .max_requests_per_second(5)
+            .build()
+            .unwrap();
+
+        for _ in 0..8 {
+            emitter.add(payload()).unwrap();
+        }
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let received_at = received_at.lock().unwrap();
+        assert_eq!(received_at.len(), 8);
+        // The first 5 requests are a burst, and the rest are 200ms apart
+        let elapsed = received_at[7].duration_since(received_at[0]);
+        assert!(elapsed >= Duration::from_millis(550), "{elapsed:?}");
+    }
+
+    #[test]
+    fn zero_rate_limit_fails_to_build() {
+        let result = BatchEmitter::builder()
+            .collector_url("http://localhost:8080")
+            .max_requests_per_second(0)
+            .build();
+
+        assert!(matches!(result, Err(Error::BuilderError(_))));
+    }
+
+    #[test]
+    fn zero_failover_after_fails_to_build() {
+        let result = BatchEmitter::builder()
+            .collector_url("http://localhost:8080")
+            .failover_after(0)
+            .build();
+
+        assert!(matches!(result, Err(Error::BuilderError(_))));
+    }
+
+    #[test]
+    fn with_failover_requires_a_collector_url() {
+        assert!(BatchEmitter::with_failover(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn events_older_than_max_event_age_are_discarded_at_flush() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 10))
+            .max_event_age(Duration::from_secs(3600))
+            .build()
+            .unwrap();
+
+        let dead_letters = Arc::new(Mutex::new(Vec::new()));
+        let received = dead_letters.clone();
+        emitter
+            .on_dead_letter(Box::new(move |events| {
+                received
+                    .lock()
+                    .unwrap()
+                    .extend(events.into_iter().map(|e| e.eid))
+            }))
+            .unwrap();
+
+        let old_event = payload().dtm(Utc::now() - chrono::Duration::hours(2));
+        let old_eid = old_event.eid.unwrap();
+        emitter.add(old_event).unwrap();
+        emitter.add(payload()).unwrap();
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        assert_eq!(*dead_letters.lock().unwrap(), vec![old_eid]);
+        assert_eq!(received_batch_sizes(&requests, Duration::ZERO), vec![1]);
+    }
+
+    #[test]
+    fn dead_letter_receives_events_out_of_retries() {
+        let (url, _requests) = mock_collector(|_| (500, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 2))
+            .retry_policy(RetryPolicy::NoRetry)
+            .build()
+            .unwrap();
+
+        let dead_letters = Arc::new(Mutex::new(Vec::new()));
+        let received = dead_letters.clone();
+        emitter
+            .on_dead_letter(Box::new(move |events| {
+                received
+                    .lock()
+                    .unwrap()
+                    .extend(events.into_iter().map(|e| e.eid))
+            }))
+            .unwrap();
+
+        let builders: Vec<_> = (0..2).map(|_| payload()).collect();
+        let expected_eids: Vec<_> = builders.iter().map(|b| b.eid.unwrap()).collect();
+        for builder in builders {
+            emitter.add(builder).unwrap();
+        }
+        let flushed = emitter.flush_now();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        assert!(matches!(
+            flushed,
+            Err(Error::CollectorResponse { status: 500, .. })
+        ));
+        assert_eq!(*dead_letters.lock().unwrap(), expected_eids);
+    }
+
+    #[test]
+    fn before_send_hook_can_add_headers() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 1))
+            .before_send(|request| {
+                assert_eq!(request.method, "POST");
+                assert!(request.body.as_ref().unwrap().contains("payload_data"));
+                request
+                    .headers
+                    .insert("X-Trace-Id".to_string(), "trace-1".to_string());
+            })
+            .build()
+            .unwrap();
+
+        emitter.add(payload()).unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let request = requests.try_recv().unwrap();
+        assert_eq!(request.headers["x-trace-id"], "trace-1");
+        assert_eq!(request.headers["content-type"], "application/json");
+    }
+
+    #[test]
+    fn flush_now_sends_buffered_events_in_one_batch() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 10))
+            .build()
+            .unwrap();
+
+        for _ in 0..3 {
+            emitter.add(payload()).unwrap();
+        }
+        emitter.flush_now().unwrap();
+        let requests: Vec<_> = requests.try_iter().collect();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        assert_eq!(requests.len(), 1);
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(body["data"].as_array().unwrap().len(), 3);
+    }
+
+    // Counts the requests in flight, each taking a while to complete
+    #[derive(Clone, Default)]
+    struct ConcurrencyCountingClient {
+        in_flight: Arc<AtomicUsize>,
+        max_in_flight: Arc<AtomicUsize>,
+        requests: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl HttpClient for ConcurrencyCountingClient {
+        async fn post(&self, _payload: SelfDescribingJson) -> Result<u16, Error> {
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            Ok(200)
+        }
+
+        fn clone(&self) -> Box<dyn HttpClient + Send + Sync> {
+            Box::new(Clone::clone(self))
+        }
+    }
+
+    #[test]
+    fn concurrent_requests_do_not_exceed_limit() {
+        let client = ConcurrencyCountingClient::default();
+        let mut emitter = BatchEmitter::builder()
+            .collector_url("http://localhost:8080")
+            .event_store(InMemoryEventStore::new(10, 1))
+            .http_client(Clone::clone(&client))
+            .max_concurrent_requests(2)
+            .build()
+            .unwrap();
+
+        for _ in 0..6 {
+            emitter.add(payload()).unwrap();
+        }
+        emitter.close().unwrap();
+        drop(emitter);
+
+        assert_eq!(client.requests.load(Ordering::SeqCst), 6);
+        assert!(client.max_in_flight.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[test]
+    fn builder_rejects_zero_concurrent_requests() {
+        assert!(matches!(
+            BatchEmitter::builder()
+                .collector_url("http://localhost:8080")
+                .max_concurrent_requests(0)
+                .build(),
+            Err(Error::BuilderError(_))
+        ));
+    }
+
+    #[test]
+    fn flush_interval_sends_events_below_batch_threshold() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 10))
+            .flush_interval(Duration::from_millis(50))
+            .build()
+            .unwrap();
+
+        emitter.add(payload()).unwrap();
+        let request = requests.recv_timeout(Duration::from_secs(5));
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let body: serde_json::Value = serde_json::from_str(&request.unwrap().body).unwrap();
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+        assert!(matches!(
+            BatchEmitter::builder()
+                .collector_url(&url)
+                .flush_interval(Duration::ZERO)
+                .build(),
+            Err(Error::BuilderError(_))
+        ));
+    }
+
+    // The number of events in each request received within the timeout, without flushing the emitter
+    fn received_batch_sizes(requests: &Receiver<MockRequest>, timeout: Duration) -> Vec<usize> {
+        let mut sizes = Vec::new();
+        while let Ok(request) = requests.recv_timeout(timeout) {
+            let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+            sizes.push(body["data"].as_array().unwrap().len());
+        }
+        sizes
+    }
+
+    #[test]
+    fn flush_policy_sends_once_enough_events_are_buffered() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(20, 20))
+            .flush_policy(FlushPolicy::new().events(3).bytes(1_000_000))
+            .build()
+            .unwrap();
+
+        for _ in 0..4 {
+            emitter.add(payload()).unwrap();
+        }
+        let sizes = received_batch_sizes(&requests, Duration::from_millis(500));
+        emitter.close().unwrap();
+        drop(emitter);
+
+        assert_eq!(sizes, vec![3]);
+    }
+
+    #[test]
+    fn flush_policy_sends_once_enough_bytes_are_buffered() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(20, 20))
+            .flush_policy(FlushPolicy::new().events(10).bytes(1500))
+            .build()
+            .unwrap();
+
+        // Each event is over 500 bytes, so the third one reaches the threshold
+        for _ in 0..2 {
+            emitter.add(payload().aid("a".repeat(500))).unwrap();
+        }
+        let before_threshold = received_batch_sizes(&requests, Duration::from_millis(200));
+        emitter.add(payload().aid("a".repeat(500))).unwrap();
+        emitter.add(payload()).unwrap();
+        let sizes = received_batch_sizes(&requests, Duration::from_millis(500));
+        emitter.close().unwrap();
+        drop(emitter);
+
+        assert!(before_threshold.is_empty());
+        assert_eq!(sizes, vec![3]);
+    }
+
+    #[test]
+    fn byte_threshold_sends_at_most_a_store_batch() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let online = Arc::new(AtomicBool::new(false));
+        let online_check = online.clone();
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(20, 2))
+            .flush_policy(FlushPolicy::new().bytes(1500))
+            .connectivity_check(move || online_check.load(Ordering::SeqCst))
+            .build()
+            .unwrap();
+
+        // The events are held while offline, so more than a store batch is buffered once the threshold is reached
+        for _ in 0..3 {
+            emitter.add(payload().aid("a".repeat(500))).unwrap();
+        }
+        online.store(true, Ordering::SeqCst);
+        emitter.add(payload()).unwrap();
+        let at_threshold = received_batch_sizes(&requests, Duration::from_millis(500));
+        emitter.flush_now().unwrap();
+        let flushed = received_batch_sizes(&requests, Duration::from_millis(200));
+        emitter.close().unwrap();
+        drop(emitter);
+
+        assert_eq!(at_threshold, vec![2]);
+        assert_eq!(flushed, vec![2]);
+    }
+
+    #[test]
+    fn flush_policy_sends_buffered_events_after_interval() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(20, 20))
+            .flush_policy(
+                FlushPolicy::new()
+                    .events(10)
+                    .bytes(1_000_000)
+                    .interval(Duration::from_millis(50)),
+            )
+            .build()
+            .unwrap();
+
+        emitter.add(payload()).unwrap();
+        emitter.add(payload()).unwrap();
+        let sizes = received_batch_sizes(&requests, Duration::from_millis(500));
+        emitter.close().unwrap();
+        drop(emitter);
+
+        assert_eq!(sizes, vec![2]);
+        assert!(matches!(
+            BatchEmitter::builder()
+                .collector_url(&url)
+                .flush_policy(FlushPolicy::new().bytes(0))
+                .build(),
+            Err(Error::BuilderError(_))
+        ));
+        assert!(matches!(
+            BatchEmitter::builder()
+                .collector_url(&url)
+                .flush_policy(FlushPolicy::new().interval(Duration::ZERO))
+                .build(),
+            Err(Error::BuilderError(_))
+        ));
+    }
+
+    #[test]
+    fn byte_limit_splits_batches_across_requests() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 10))
+            .byte_limit(4000)
+            .build()
+            .unwrap();
+
+        for _ in 0..6 {
+            emitter.add(payload().aid("a".repeat(1000))).unwrap();
+        }
+        emitter.flush_now().unwrap();
+        let requests: Vec<_> = requests.try_iter().collect();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        assert!(requests.len() > 1);
+        let mut events_sent = 0;
+        for request in requests {
+            assert!(request.body.len() <= 4000);
+            let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+            events_sent += body["data"].as_array().unwrap().len();
+        }
+        assert_eq!(events_sent, 6);
+    }
+
+    // An event store recording the IDs of the batches it gives out, and of those it is asked to clean up
+    #[derive(Default)]
+    struct IdRecordingStore {
+        store: InMemoryEventStore,
+        batch_ids: Arc<Mutex<Vec<Uuid>>>,
+        cleaned_up_ids: Arc<Mutex<Vec<Uuid>>>,
+    }
+
+    impl IdRecordingStore {
+        fn record(&self, batch: Result<EventBatch, Error>) -> Result<EventBatch, Error> {
+            if let Ok(batch) = &batch {
+                self.batch_ids.lock().unwrap().push(batch.id);
+            }
+            batch
+        }
+    }
+
+    impl EventStore for IdRecordingStore {
+        fn add(&mut self, payload: PayloadBuilder) -> Result<(), Error> {
+            self.store.add(payload)
+        }
+
+        fn len(&self) -> usize {
+            self.store.len()
+        }
+
+        fn batch_size(&self) -> usize {
+            self.store.batch_size()
+        }
+
+        fn capacity(&self) -> usize {
+            self.store.capacity()
+        }
+
+        fn full_batch(&mut self) -> Result<EventBatch, Error> {
+            let batch = self.store.full_batch();
+            self.record(batch)
+        }
+
+        fn batch_of(&mut self, size: usize) -> Result<EventBatch, Error> {
+            let batch = self.store.batch_of(size);
+            self.record(batch)
+        }
+
+        fn cleanup_after_send_attempt(&mut self, batch_id: Uuid) -> Result<(), Error> {
+            self.cleaned_up_ids.lock().unwrap().push(batch_id);
+            self.store.cleanup_after_send_attempt(batch_id)
+        }
+    }
+
+    #[test]
+    fn split_batches_are_cleaned_up_with_the_event_store_batch_id() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let store = IdRecordingStore {
+            store: InMemoryEventStore::new(10, 10),
+            ..IdRecordingStore::default()
+        };
+        let (batch_ids, cleaned_up_ids) = (store.batch_ids.clone(), store.cleaned_up_ids.clone());
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(store)
+            .byte_limit(4000)
+            .build()
+            .unwrap();
+
+        for _ in 0..6 {
+            emitter.add(payload().aid("a".repeat(1000))).unwrap();
+        }
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let batch_ids = batch_ids.lock().unwrap();
+        let cleaned_up_ids = cleaned_up_ids.lock().unwrap();
+        assert_eq!(batch_ids.len(), 1);
+        assert!(requests.try_iter().count() > 1);
+        assert!(!cleaned_up_ids.is_empty());
+        assert!(cleaned_up_ids.iter().all(|id| *id == batch_ids[0]));
+    }
+
+    #[test]
+    fn batches_are_partitioned_by_app_id() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let store = IdRecordingStore {
+            store: InMemoryEventStore::new(10, 10),
+            ..IdRecordingStore::default()
+        };
+        let (batch_ids, cleaned_up_ids) = (store.batch_ids.clone(), store.cleaned_up_ids.clone());
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(store)
+            .partition_batches_by(|payload| payload.app_id().to_string())
+            .build()
+            .unwrap();
+
+        for app_id in ["web", "mobile", "web", "mobile", "web"] {
+            emitter.add(payload().aid(app_id.to_string())).unwrap();
+        }
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let mut batches: Vec<(String, usize)> = requests
+            .try_iter()
+            .map(|request| {
+                let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+                let events = body["data"].as_array().unwrap();
+                let app_id = events[0]["aid"].as_str().unwrap().to_string();
+                assert!(events.iter().all(|event| event["aid"] == app_id));
+                (app_id, events.len())
+            })
+            .collect();
+        batches.sort();
+        assert_eq!(
+            batches,
+            vec![("mobile".to_string(), 2), ("web".to_string(), 3)]
+        );
+        let batch_ids = batch_ids.lock().unwrap();
+        assert_eq!(batch_ids.len(), 1);
+        assert_eq!(*cleaned_up_ids.lock().unwrap(), vec![batch_ids[0]; 2]);
+    }
+
+    #[test]
+    fn byte_limit_rejects_oversized_event() {
+        let mut emitter = BatchEmitter::builder()
+            .collector_url("http://localhost:8080")
+            .event_store(InMemoryEventStore::new(10, 10))
+            .byte_limit(1000)
+            .build()
+            .unwrap();
+
+        let result = emitter.add(payload().aid("a".repeat(2000)));
+        let store_len = emitter.event_store.lock().unwrap().len();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        match result {
+            Err(Error::PayloadTooLarge { size, limit }) => {
+                assert!(size > 2000);
+                assert_eq!(limit, 1000);
+            }
+            other => panic!("Expected a payload too large error, got {other:?}"),
+        }
+        assert_eq!(store_len, 0);
+    }
+
+    #[test]
+    fn with_client_sends_events_using_provided_client() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let client = reqwest::Client::builder()
+            .user_agent("custom-agent/1.0")
+            .build()
+            .unwrap();
+        let mut emitter = BatchEmitter::with_client(&url, client);
+
+        emitter.add(payload()).unwrap();
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let request = requests.try_recv().unwrap();
+        assert_eq!(request.headers["user-agent"], "custom-agent/1.0");
+    }
+
+    #[test]
+    fn with_path_sends_events_to_custom_path() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter =
+            BatchEmitter::with_path(&url, "/collector/events", "/collector/i").unwrap();
+
+        emitter.add(payload()).unwrap();
+        emitter.flush().unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let request = requests.try_recv().unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/collector/events");
+    }
+
+    #[test]
+    fn with_path_rejects_paths_without_leading_slash() {
+        assert!(BatchEmitter::with_path("http://localhost:8080", "tp2", "/i").is_err());
+    }
+
+    // Polls the emitter stats until they match the condition, or a timeout is reached
+    fn wait_for_stats(emitter: &BatchEmitter, condition: impl Fn(&EmitterStats) -> bool) {
+        for _ in 0..100 {
+            if condition(&emitter.stats()) {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+    }
+
+    #[test]
+    fn stats_count_sent_failed_retried_and_dropped_events() {
+        let requests_received = AtomicUsize::new(0);
+        let (url, _requests) =
+            mock_collector(
+                move |_| match requests_received.fetch_add(1, Ordering::SeqCst) {
+                    0 => (500, String::new()),
+                    1 => (200, String::new()),
+                    _ => (400, String::new()),
+                },
+            );
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 1))
+            .retry_policy(RetryPolicy::MaxRetries(1))
+            .build()
+            .unwrap();
+
+        // Fails, then is sent on retry
+        emitter.add(payload()).unwrap();
+        wait_for_stats(&emitter, |stats| stats.sent == 1);
+
+        // Rejected without retrying
+        emitter.add(payload()).unwrap();
+        wait_for_stats(&emitter, |stats| stats.dropped == 1);
+
+        let expected = EmitterStats {
+            sent: 1,
+            failed: 2,
+            retried: 1,
+            dropped: 1,
+            buffered: 0,
+        };
+        let stats = emitter.stats();
+        emitter.reset_stats();
+        let reset = emitter.stats();
+        emitter.close().unwrap();
+
+        assert_eq!(stats, expected);
+        assert_eq!(reset, EmitterStats::default());
+    }
+
+    #[test]
+    fn no_retry_status_code_is_not_retried() {
+        let (url, requests) = mock_collector(|_| (422, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 1))
+            .retry_policy(RetryPolicy::MaxRetries(1))
+            .build()
+            .unwrap();
+
+        emitter.add(payload()).unwrap();
+        wait_for_stats(&emitter, |stats| stats.dropped == 1);
+        let stats = emitter.stats();
+        emitter.close().unwrap();
+
+        assert_eq!(stats.retried, 0);
+        assert_eq!(requests.try_iter().count(), 1);
+    }
+
+    #[test]
+    fn flush_now_returns_the_collector_rejection() {
+        for preserve_order in [false, true] {
+            let (url, _requests) = mock_collector(|_| (422, "Invalid payload".to_string()));
+            let mut emitter = BatchEmitter::builder()
+                .collector_url(&url)
+                .event_store(InMemoryEventStore::new(10, 10))
+                .preserve_order(preserve_order)
+                .build()
+                .unwrap();
+
+            emitter.add(payload()).unwrap();
+            let rejected = emitter.flush_now();
+            let empty = emitter.flush_now();
+            emitter.close().unwrap();
+
+            assert!(
+                matches!(
+                    rejected,
+                    Err(Error::CollectorResponse { status: 422, ref body }) if body == "Invalid payload"
+                ),
+                "{rejected:?}"
+            );
+            assert!(empty.is_ok());
+        }
+    }
+
+    #[test]
+    fn flush_now_does_not_report_rejections_of_batches_being_retried() {
+        let requests_received = AtomicUsize::new(0);
+        let (url, _requests) =
+            mock_collector(
+                move |_| match requests_received.fetch_add(1, Ordering::SeqCst) {
+                    0 => (503, String::new()),
+                    _ => (200, String::new()),
+                },
+            );
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 10))
+            .retry_policy(RetryPolicy::MaxRetries(1))
+            .build()
+            .unwrap();
+
+        emitter.add(payload()).unwrap();
+        let flushed = emitter.flush_now();
+        wait_for_stats(&emitter, |stats| stats.sent == 1);
+        emitter.close().unwrap();
+
+        assert!(flushed.is_ok(), "{flushed:?}");
+    }
+
+    #[test]
+    fn retryable_status_code_is_retried() {
+        let requests_received = AtomicUsize::new(0);
+        let (url, requests) =
+            mock_collector(
+                move |_| match requests_received.fetch_add(1, Ordering::SeqCst) {
+                    0 => (503, String::new()),
+                    _ => (200, String::new()),
+                },
+            );
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 1))
+            .retry_policy(RetryPolicy::MaxRetries(1))
+            .build()
+            .unwrap();
+
+        emitter.add(payload()).unwrap();
+        wait_for_stats(&emitter, |stats| stats.sent == 1);
+        let stats = emitter.stats();
+        emitter.close().unwrap();
+
+        assert_eq!(stats.retried, 1);
+        assert_eq!(requests.try_iter().count(), 2);
+    }
+
+    #[test]
+    fn no_retry_status_codes_can_be_configured() {
+        let (url, requests) = mock_collector(|_| (503, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 1))
+            .retry_policy(RetryPolicy::MaxRetries(1))
+            .no_retry_status_codes(HashSet::from([503]))
+            .build()
+            .unwrap();
+
+        emitter.add(payload()).unwrap();
+        wait_for_stats(&emitter, |stats| stats.dropped == 1);
+        let stats = emitter.stats();
+        emitter.close().unwrap();
+
+        assert_eq!(stats.retried, 0);
+        assert_eq!(requests.try_iter().count(), 1);
+    }
+
+    #[test]
+    fn adaptive_batch_size_grows_within_bounds_under_backlog() {
+        // A slow collector, so events back up behind the single request allowed in flight
+        let (url, requests) = mock_collector(|_| {
+            std::thread::sleep(Duration::from_millis(50));
+            (200, String::new())
+        });
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(100, 100))
+            .max_concurrent_requests(1)
+            .adaptive_batch_size(2, 8)
+            .build()
+            .unwrap();
+
+        for _ in 0..40 {
+            emitter.add(payload()).unwrap();
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        let batch_size = emitter.batch_size();
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+
+        let batch_sizes: Vec<usize> = requests
+            .try_iter()
+            .map(|request| {
+                let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+                body["data"].as_array().unwrap().len()
+            })
+            .collect();
+
+        assert_eq!(batch_sizes.iter().sum::<usize>(), 40);
+        assert_eq!(batch_sizes[0], 2);
+        assert!(batch_sizes.iter().all(|size| *size <= 8));
+        assert!(batch_sizes.iter().any(|size| *size > 2));
+        assert!((2..=8).contains(&batch_size));
+    }
+
+    #[test]
+    fn invalid_adaptive_batch_size_fails_to_build() {
+        let result = BatchEmitter::builder()
+            .collector_url("http://localhost:8080")
+            .adaptive_batch_size(10, 5)
+            .build();
+
+        assert!(matches!(result, Err(Error::BuilderError(_))));
+
+        let result = BatchEmitter::builder()
+            .collector_url("http://localhost:8080")
+            .event_store(InMemoryEventStore::new(100, 4))
+            .adaptive_batch_size(2, 8)
+            .build();
+
+        assert!(matches!(result, Err(Error::BuilderError(_))));
+    }
+
+    #[test]
+    fn adaptive_batch_size_drains_more_events_than_a_store_batch() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(100, 4))
+            .adaptive_batch_size(2, 4)
+            .build()
+            .unwrap();
+
+        emitter.pause().unwrap();
+        for _ in 0..10 {
+            emitter.add(payload()).unwrap();
+        }
+        emitter.resume().unwrap();
+        emitter.flush_now().unwrap();
+        let stats = emitter.stats();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let batch_sizes = received_batch_sizes(&requests, Duration::from_millis(100));
+        assert_eq!(batch_sizes.iter().sum::<usize>(), 10);
+        assert!(batch_sizes.iter().all(|size| *size <= 4));
+        assert_eq!(stats.buffered, 0);
+    }
+
+    #[test]
+    fn counters_update_as_events_are_buffered_and_flushed() {
+        let requests_received = AtomicUsize::new(0);
+        let (url, _requests) =
+            mock_collector(
+                move |_| match requests_received.fetch_add(1, Ordering::SeqCst) {
+                    0 => (200, String::new()),
+                    _ => (500, String::new()),
+                },
+            );
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 5))
+            .retry_policy(RetryPolicy::NoRetry)
+            .build()
+            .unwrap();
+
+        for _ in 0..3 {
+            emitter.add(payload()).unwrap();
+        }
+        let buffered = emitter.buffer_len();
 
-        for code in 0..=599 {
-            assert_eq!(
-                BatchEmitter::should_retry(code),
-                should_retry_codes.contains(&code)
+        emitter.flush_now().unwrap();
+        let after_success = (
+            emitter.buffer_len(),
+            emitter.total_sent(),
+            emitter.total_failed(),
+        );
+
+        emitter.add(payload()).unwrap();
+        emitter.add(payload()).unwrap();
+        let failed_flush = emitter.flush_now();
+        let after_failure = (
+            emitter.buffer_len(),
+            emitter.total_sent(),
+            emitter.total_failed(),
+        );
+
+        emitter.close().unwrap();
+
+        assert_eq!(buffered, 3);
+        assert_eq!(after_success, (0, 3, 0));
+        assert!(matches!(
+            failed_flush,
+            Err(Error::CollectorResponse { status: 500, .. })
+        ));
+        assert_eq!(after_failure, (0, 3, 2));
+    }
+
+    #[test]
+    fn filtered_out_events_never_reach_the_collector() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 5))
+            .build()
+            .unwrap();
+        emitter
+            .set_filter(Box::new(|payload| payload.app_id() != "debug_app"))
+            .unwrap();
+
+        emitter.add(payload().aid("debug_app".to_string())).unwrap();
+        emitter.add(payload().aid("shop_app".to_string())).unwrap();
+        emitter
+            .add_nonblocking(payload().aid("debug_app".to_string()))
+            .unwrap();
+        let buffered = emitter.buffer_len();
+
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        assert_eq!(buffered, 1);
+
+        let bodies: Vec<serde_json::Value> = requests
+            .try_iter()
+            .map(|request| serde_json::from_str(&request.body).unwrap())
+            .collect();
+        let app_ids: Vec<&str> = bodies
+            .iter()
+            .flat_map(|body| body["data"].as_array().unwrap())
+            .map(|event| event["aid"].as_str().unwrap())
+            .collect();
+        assert_eq!(app_ids, vec!["shop_app"]);
+    }
+
+    // Sends one event with the emitter, returning the User-Agent header of the request
+    fn sent_user_agent(mut emitter: BatchEmitter, requests: Receiver<MockRequest>) -> String {
+        emitter.add(payload()).unwrap();
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        requests.try_recv().unwrap().headers["user-agent"].clone()
+    }
+
+    #[test]
+    fn sends_default_user_agent() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+
+        let user_agent = sent_user_agent(BatchEmitter::new(&url), requests);
+
+        assert_eq!(
+            user_agent,
+            format!("snowplow-rust-tracker/{}", env!("CARGO_PKG_VERSION"))
+        );
+    }
+
+    #[test]
+    fn builder_overrides_user_agent() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .user_agent("my-service/2.1")
+            .build()
+            .unwrap();
+
+        assert_eq!(sent_user_agent(emitter, requests), "my-service/2.1");
+    }
+
+    #[test]
+    fn clock_sets_sent_timestamp() {
+        use chrono::TimeZone;
+
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .clock(crate::FixedClock::new(
+                Utc.timestamp_millis_opt(1_700_000_000_123).unwrap(),
+            ))
+            .build()
+            .unwrap();
+
+        emitter.add(payload()).unwrap();
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let body: serde_json::Value =
+            serde_json::from_str(&requests.try_recv().unwrap().body).unwrap();
+        assert_eq!(body["data"][0]["stm"], "1700000000123");
+    }
+
+    fn emitter_with_buffer_option(url: &str, buffer_option: BufferOption) -> BatchEmitter {
+        BatchEmitter::builder()
+            .collector_url(url)
+            .event_store(InMemoryEventStore::new(100, 50))
+            .retry_policy(RetryPolicy::NoRetry)
+            .buffer_option(buffer_option)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn single_buffer_option_sends_events_immediately() {
+        let (url, _requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = emitter_with_buffer_option(&url, BufferOption::Single);
+
+        emitter.add(payload()).unwrap();
+        let buffered = emitter.event_store.lock().unwrap().len();
+        emitter.close().unwrap();
+
+        assert_eq!(buffered, 0);
+    }
+
+    #[test]
+    fn small_batch_buffer_option_waits_for_ten_events() {
+        let (url, _requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = emitter_with_buffer_option(&url, BufferOption::SmallBatch);
+
+        for _ in 0..9 {
+            emitter.add(payload()).unwrap();
+        }
+        let buffered_before = emitter.event_store.lock().unwrap().len();
+        emitter.add(payload()).unwrap();
+        let buffered_after = emitter.event_store.lock().unwrap().len();
+        emitter.close().unwrap();
+
+        assert_eq!(buffered_before, 9);
+        assert_eq!(buffered_after, 0);
+    }
+
+    #[test]
+    fn default_buffer_option_depends_on_the_method() {
+        let batch_size = |method| {
+            let mut emitter = BatchEmitter::builder()
+                .collector_url("https://collector.example.com")
+                .event_store(InMemoryEventStore::new(100, 40))
+                .method(method)
+                .build()
+                .unwrap();
+            let batch_size = emitter.batch_size();
+            emitter.close().unwrap();
+            batch_size
+        };
+
+        assert_eq!(batch_size(HttpMethod::Get), 1);
+        assert_eq!(batch_size(HttpMethod::Post), 10);
+    }
+
+    #[test]
+    fn buffer_option_is_capped_by_the_event_store_batch_size() {
+        let mut emitter = BatchEmitter::builder()
+            .collector_url("https://collector.example.com")
+            .event_store(InMemoryEventStore::new(100, 20))
+            .buffer_option(BufferOption::LargeBatch)
+            .build()
+            .unwrap();
+        let batch_size = emitter.batch_size();
+        emitter.close().unwrap();
+
+        assert_eq!(batch_size, 20);
+    }
+
+    #[test]
+    fn with_buffer_option_changes_the_batch_size_of_a_running_emitter() {
+        let (url, _requests) = mock_collector(|_| (200, String::new()));
+        let emitter = emitter_with_buffer_option(&url, BufferOption::SmallBatch);
+        let mut emitter = emitter.with_buffer_option(BufferOption::Single).unwrap();
+
+        emitter.add(payload()).unwrap();
+        let buffered = emitter.event_store.lock().unwrap().len();
+        let batch_size = emitter.batch_size();
+        emitter.close().unwrap();
+
+        assert_eq!(batch_size, 1);
+        assert_eq!(buffered, 0);
+    }
+
+    #[test]
+    fn builder_with_defaults_builds() {
+        let mut emitter = BatchEmitter::builder()
+            .collector_url("https://collector.example.com")
+            .build()
+            .unwrap();
+
+        assert_eq!(emitter.send_context.method, HttpMethod::Post);
+        assert_eq!(
+            emitter.send_context.buffer_option(),
+            BufferOption::SmallBatch
+        );
+        assert!(emitter.send_context.headers().is_empty());
+
+        emitter.close().unwrap();
+    }
+
+    #[test]
+    fn builder_rejects_missing_or_invalid_collector_url() {
+        assert!(BatchEmitter::builder().build().is_err());
+
+        for collector_url in ["not a url", "/relative/path", ""] {
+            assert!(matches!(
+                BatchEmitter::builder().collector_url(collector_url).build(),
+                Err(Error::BuilderError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn fully_configured_builder_applies_every_option() {
+        use std::io::Read;
+
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 5))
+            .method(HttpMethod::Post)
+            .buffer_option(BufferOption::Single)
+            .retry_policy(RetryPolicy::NoRetry)
+            .timeout(Duration::from_secs(5))
+            .header("X-Custom", "value")
+            .compress(true)
+            .build()
+            .unwrap();
+
+        emitter.add(payload()).unwrap();
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let request = requests.try_recv().unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.headers["x-custom"], "value");
+        assert_eq!(request.headers["content-encoding"], "gzip");
+
+        let mut body = String::new();
+        flate2::read::GzDecoder::new(request.raw_body.as_slice())
+            .read_to_string(&mut body)
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn signature_header_covers_compressed_body() {
+        use hmac::{Hmac, Mac};
+
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 5))
+            .compress(true)
+            .sign_requests("X-Signature", b"secret")
+            .build()
+            .unwrap();
+
+        emitter.add(payload()).unwrap();
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let request = requests.try_recv().unwrap();
+        assert_eq!(request.headers["content-encoding"], "gzip");
+
+        let mut mac = Hmac::<sha2::Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(&request.raw_body);
+        let expected: String = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        assert_eq!(request.headers["x-signature"], expected);
+    }
+
+    #[test]
+    fn get_method_sends_each_event_in_its_own_request() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 5))
+            .method(HttpMethod::Get)
+            .build()
+            .unwrap();
+
+        emitter.add(payload()).unwrap();
+        emitter.add(payload()).unwrap();
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let requests: Vec<MockRequest> = requests.try_iter().collect();
+        assert_eq!(requests.len(), 2);
+        for request in requests {
+            assert_eq!(request.method, "GET");
+            assert!(request.path.starts_with("/i?"));
+        }
+    }
+
+    #[test]
+    fn get_retry_does_not_resend_accepted_events() {
+        let requests_received = AtomicUsize::new(0);
+        let (url, requests) =
+            mock_collector(
+                move |_| match requests_received.fetch_add(1, Ordering::SeqCst) {
+                    1 => (503, String::new()),
+                    _ => (200, String::new()),
+                },
+            );
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 5))
+            .method(HttpMethod::Get)
+            // Buffered, so the events are sent in one batch
+            .buffer_option(BufferOption::SmallBatch)
+            .retry_policy(RetryPolicy::MaxRetries(1))
+            .retry_jitter(Jitter::None)
+            .build()
+            .unwrap();
+
+        let builders: Vec<_> = (0..3).map(|_| payload()).collect();
+        let event_ids: Vec<_> = builders.iter().map(|b| b.eid.unwrap()).collect();
+        for builder in builders {
+            emitter.add(builder).unwrap();
+        }
+        let _ = emitter.flush_now();
+        wait_for_stats(&emitter, |stats| stats.sent == 3);
+        let stats = emitter.stats();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let received_ids: Vec<_> = requests
+            .try_iter()
+            .map(|request| {
+                let start = request.path.find("eid=").unwrap() + "eid=".len();
+                Uuid::parse_str(&request.path[start..start + 36]).unwrap()
+            })
+            .collect();
+        // Only the event rejected by the collector is sent again
+        assert_eq!(
+            received_ids,
+            vec![event_ids[0], event_ids[1], event_ids[1], event_ids[2]]
+        );
+        assert_eq!(stats.retried, 2);
+    }
+
+    fn large_self_describing_payload() -> PayloadBuilder {
+        payload()
+            .e(EventType::SelfDescribingEvent)
+            .ue_pr(SelfDescribingEventData::new(SelfDescribingJson::new(
+                "iglu:com.acme/large/jsonschema/1-0-0",
+                serde_json::json!({ "text": "x".repeat(3000) }),
+            )))
+    }
+
+    #[test]
+    fn get_rejects_event_exceeding_url_length() {
+        let mut emitter = BatchEmitter::builder()
+            .collector_url("http://localhost:8080")
+            .event_store(InMemoryEventStore::new(10, 10))
+            .method(HttpMethod::Get)
+            // Buffered, so the accepted event stays in the event store
+            .buffer_option(BufferOption::SmallBatch)
+            .build()
+            .unwrap();
+
+        let result = emitter.add(large_self_describing_payload());
+        let small_result = emitter.add(payload());
+        let store_len = emitter.event_store.lock().unwrap().len();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        match result {
+            Err(Error::PayloadTooLarge { size, limit }) => {
+                assert!(size > 3000);
+                assert_eq!(limit, MAX_GET_QUERY_LENGTH);
+            }
+            other => panic!("Expected a payload too large error, got {other:?}"),
+        }
+        assert!(small_result.is_ok());
+        assert_eq!(store_len, 1);
+    }
+
+    #[test]
+    fn get_fallback_to_post_sends_oversized_event_via_post() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 5))
+            .method(HttpMethod::Get)
+            .get_fallback_to_post(true)
+            .build()
+            .unwrap();
+
+        emitter.add(large_self_describing_payload()).unwrap();
+        emitter.add(payload()).unwrap();
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let requests: Vec<MockRequest> = requests.try_iter().collect();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/com.snowplowanalytics.snowplow/tp2");
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+        assert_eq!(requests[1].method, "GET");
+        assert!(requests[1].path.starts_with("/i?"));
+    }
+
+    #[tokio::test]
+    async fn health_check_requires_200_from_health_endpoint() {
+        let (url, requests) = mock_collector(|request| match request.path.as_str() {
+            "/health" => (200, "OK".to_string()),
+            _ => (503, "Unavailable".to_string()),
+        });
+        let mut healthy = BatchEmitter::builder().collector_url(&url).build().unwrap();
+        let mut unhealthy = BatchEmitter::builder()
+            .collector_url(&url)
+            .health_path("/status")
+            .build()
+            .unwrap();
+
+        fn assert_send<T: Send>(_: &T) {}
+        let healthy_check = healthy.check_health();
+        assert_send(&healthy_check);
+        let healthy_result = healthy_check.await;
+        let unhealthy_result = unhealthy.health_check().await;
+        healthy.close().unwrap();
+        unhealthy.close().unwrap();
+        drop((healthy, unhealthy));
+
+        assert!(healthy_result.is_ok());
+        assert!(matches!(
+            unhealthy_result,
+            Err(Error::CollectorResponse { status: 503, .. })
+        ));
+        let paths: Vec<String> = requests.try_iter().map(|request| request.path).collect();
+        assert_eq!(paths, vec!["/health", "/status"]);
+        assert!(matches!(
+            BatchEmitter::builder()
+                .collector_url(&url)
+                .health_path("health")
+                .build(),
+            Err(Error::BuilderError(_))
+        ));
+    }
+
+    #[test]
+    fn custom_vendor_changes_post_endpoint() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 5))
+            .vendor(CollectorVendor::Custom("com.acme".to_string()))
+            .build()
+            .unwrap();
+
+        emitter.add(payload()).unwrap();
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let request = requests.try_recv().unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/com.acme/tp2");
+
+        for vendor in ["", "com.acme/events"] {
+            assert!(matches!(
+                BatchEmitter::builder()
+                    .collector_url(&url)
+                    .vendor(CollectorVendor::Custom(vendor.to_string()))
+                    .build(),
+                Err(Error::BuilderError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn protocol_version_changes_post_endpoint() {
+        for (vendor, expected_path) in [
+            (None, "/com.snowplowanalytics.snowplow/tp3"),
+            (Some("com.acme"), "/com.acme/tp3"),
+        ] {
+            let (url, requests) = mock_collector(|_| (200, String::new()));
+            let mut builder = BatchEmitter::builder()
+                .collector_url(&url)
+                .protocol_version(ProtocolVersion::Custom("tp3".to_string()));
+            if let Some(vendor) = vendor {
+                builder = builder.vendor(CollectorVendor::Custom(vendor.to_string()));
+            }
+            let mut emitter = builder.build().unwrap();
+
+            emitter.add(payload()).unwrap();
+            emitter.flush_now().unwrap();
+            emitter.close().unwrap();
+            drop(emitter);
+
+            assert_eq!(requests.try_recv().unwrap().path, expected_path);
+        }
+
+        for version in ["", "tp3/v2"] {
+            assert!(matches!(
+                BatchEmitter::builder()
+                    .collector_url("http://localhost:8080")
+                    .protocol_version(ProtocolVersion::Custom(version.to_string()))
+                    .build(),
+                Err(Error::BuilderError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn emitter_exposes_normalized_collector_url() {
+        let mut emitter = BatchEmitter::new("example.com/");
+        assert_eq!(emitter.collector_url(), "https://example.com");
+        emitter.close().unwrap();
+
+        let mut emitter = BatchEmitter::builder()
+            .collector_url("http://example.com/")
+            .build()
+            .unwrap();
+        assert_eq!(emitter.collector_url(), "http://example.com");
+        emitter.close().unwrap();
+    }
+
+    // A sink keeping the event IDs of every batch it is sent
+    struct InMemorySink {
+        batches: Arc<Mutex<Vec<Vec<Uuid>>>>,
+    }
+
+    #[async_trait]
+    impl Sink for InMemorySink {
+        async fn send(&self, batch: Vec<Payload>) -> Result<(), Error> {
+            let eids = batch.iter().map(|event| event.eid).collect();
+            self.batches.lock().unwrap().push(eids);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn batches_are_sent_to_custom_sink() {
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let mut emitter = BatchEmitter::builder()
+            .sink(InMemorySink {
+                batches: batches.clone(),
+            })
+            .event_store(InMemoryEventStore::new(10, 2))
+            .build()
+            .unwrap();
+
+        let builders: Vec<_> = (0..3).map(|_| payload()).collect();
+        let eids: Vec<_> = builders.iter().map(|b| b.eid.unwrap()).collect();
+        for builder in builders {
+            emitter.add(builder).unwrap();
+        }
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+        let stats = emitter.stats();
+        drop(emitter);
+
+        assert_eq!(
+            *batches.lock().unwrap(),
+            vec![eids[..2].to_vec(), eids[2..].to_vec()]
+        );
+        assert_eq!(stats.sent, 3);
+    }
+
+    #[test]
+    fn network_user_id_cookie_is_attached_to_later_events() {
+        let network_user_id = Uuid::new_v4();
+        let (url, requests) = mock_collector_with_headers(move |_| {
+            let set_cookie = format!("sp={network_user_id}; Path=/; HttpOnly");
+            (
+                200,
+                vec![("Set-Cookie".to_string(), set_cookie)],
+                String::new(),
             )
+        });
+        let cookie_jar = CookieJar::new();
+        let mut emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .cookie_jar(cookie_jar.clone())
+            .build()
+            .unwrap();
+
+        emitter.add(payload()).unwrap();
+        emitter.flush_now().unwrap();
+        emitter.add(payload()).unwrap();
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let requests: Vec<_> = requests.try_iter().collect();
+        let events: Vec<serde_json::Value> = requests
+            .iter()
+            .map(|request| serde_json::from_str::<serde_json::Value>(&request.body).unwrap())
+            .map(|body| body["data"][0].clone())
+            .collect();
+        assert_eq!(cookie_jar.network_user_id(), Some(network_user_id));
+        assert!(events[0].get("tnuid").is_none());
+        assert_eq!(events[1]["tnuid"], network_user_id.to_string());
+        assert_eq!(
+            requests[1].headers.get("cookie"),
+            Some(&format!("sp={network_user_id}"))
+        );
+    }
+
+    // The level, message and status code of a tracing event
+    #[cfg(feature = "tracing")]
+    type RecordedEvent = (tracing::Level, String, Option<u64>);
+
+    // Records every tracing event
+    #[cfg(feature = "tracing")]
+    struct RecordingSubscriber {
+        events: Arc<Mutex<Vec<RecordedEvent>>>,
+        next_span_id: AtomicUsize,
+    }
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for RecordingSubscriber {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            tracing::span::Id::from_u64(self.next_span_id.fetch_add(1, Ordering::SeqCst) as u64 + 1)
+        }
+
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            #[derive(Default)]
+            struct Fields {
+                message: String,
+                status: Option<u64>,
+            }
+
+            impl tracing::field::Visit for Fields {
+                fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+                    if field.name() == "status" {
+                        self.status = Some(value);
+                    }
+                }
+
+                fn record_debug(
+                    &mut self,
+                    field: &tracing::field::Field,
+                    value: &dyn std::fmt::Debug,
+                ) {
+                    if field.name() == "message" {
+                        self.message = format!("{value:?}");
+                    }
+                }
+            }
+
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+            self.events.lock().unwrap().push((
+                *event.metadata().level(),
+                fields.message,
+                fields.status,
+            ));
         }
+
+        fn enter(&self, _: &tracing::span::Id) {}
+
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn sending_a_batch_emits_info_tracing_event() {
+        // Events are sent from the emitter thread, so the subscriber has to be the global default
+        let events = Arc::new(Mutex::new(Vec::new()));
+        tracing::subscriber::set_global_default(RecordingSubscriber {
+            events: events.clone(),
+            next_span_id: AtomicUsize::new(0),
+        })
+        .unwrap();
+
+        // An unusual status code, to tell this batch apart from those sent by other tests
+        let (url, _requests) = mock_collector(|_| (203, String::new()));
+        let mut emitter = BatchEmitter::builder().collector_url(&url).build().unwrap();
+
+        emitter.add(payload()).unwrap();
+        emitter.flush_now().unwrap();
+        emitter.close().unwrap();
+        drop(emitter);
+
+        let events = events.lock().unwrap();
+        assert!(events.iter().any(|(level, message, status)| {
+            *level == tracing::Level::INFO && message == "Batch sent" && *status == Some(203)
+        }));
     }
 }
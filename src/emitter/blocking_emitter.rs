@@ -0,0 +1,194 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use std::sync::Arc;
+
+use tokio::runtime::{Builder, Handle, Runtime};
+use uuid::Uuid;
+
+use crate::clock::{Clock, SystemClock};
+use crate::emitter::{normalize_collector_url, Emitter, EmitterStats};
+use crate::event_batch::EventBatch;
+use crate::http_client::ReqwestClient;
+use crate::payload::PayloadBuilder;
+use crate::{Error, HttpClient};
+
+/// An [Emitter] sending each event as soon as it is added, blocking until the collector responds
+///
+/// Unlike the [BatchEmitter](crate::BatchEmitter), no background thread is started and events
+/// aren't buffered or retried, which suits scripts and command line tools that send a few events.
+/// Requests are sent on a single-threaded runtime owned by the emitter, so it must not be used
+/// from within an async runtime.
+///
+/// ## Example
+/// ```
+/// use snowplow_tracker::{BlockingEmitter, Tracker};
+///
+/// let emitter = BlockingEmitter::new("https://collector.example.com").unwrap();
+/// let tracker = Tracker::new("ns", "app_id", emitter, None);
+/// ```
+pub struct BlockingEmitter {
+    collector_url: String,
+    http_client: Box<dyn HttpClient + Send + Sync>,
+    runtime: Runtime,
+    clock: Arc<dyn Clock>,
+    stats: EmitterStats,
+}
+
+impl BlockingEmitter {
+    /// Create a [BlockingEmitter] sending events with a [ReqwestClient]
+    pub fn new(collector_url: &str) -> Result<Self, Error> {
+        let collector_url = normalize_collector_url(collector_url)?;
+        let http_client = ReqwestClient::new(&collector_url);
+        Self::create(collector_url, http_client)
+    }
+
+    /// Create a [BlockingEmitter] sending events with the provided [HttpClient]
+    pub fn with_client(
+        collector_url: &str,
+        http_client: impl HttpClient + Send + Sync + 'static,
+    ) -> Result<Self, Error> {
+        let collector_url = normalize_collector_url(collector_url)?;
+        Self::create(collector_url, Box::new(http_client))
+    }
+
+    /// Set the [Clock] used for the sent timestamp (`stm`) of events, by default a [SystemClock]
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Arc::new(clock);
+    }
+
+    fn create(
+        collector_url: String,
+        http_client: Box<dyn HttpClient + Send + Sync>,
+    ) -> Result<Self, Error> {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| Error::EmitterError(format!("Failed to start runtime: {e}")))?;
+
+        Ok(Self {
+            collector_url,
+            http_client,
+            runtime,
+            clock: Arc::new(SystemClock),
+            stats: EmitterStats::default(),
+        })
+    }
+}
+
+impl Emitter for BlockingEmitter {
+    /// Sends the event to the collector, returning once it has responded
+    fn add(&mut self, payload: PayloadBuilder) -> Result<(), Error> {
+        // Blocking on a runtime from within another one panics, so fail instead
+        if Handle::try_current().is_ok() {
+            return Err(Error::EmitterError(
+                "BlockingEmitter can't be used from within an async runtime".to_string(),
+            ));
+        }
+
+        let payload = payload.finalise_payload_at(self.clock.now())?;
+        let batch = EventBatch::new(Uuid::new_v4(), vec![payload]);
+
+        let result = self
+            .runtime
+            .block_on(self.http_client.post(batch.as_payload()));
+
+        match result {
+            Ok(code) if (200..300).contains(&code) => {
+                self.stats.sent += 1;
+                Ok(())
+            }
+            Ok(code) => {
+                self.stats.failed += 1;
+                Err(Error::EmitterError(format!(
+                    "Collector responded with status code {code}"
+                )))
+            }
+            Err(e) => {
+                self.stats.failed += 1;
+                Err(e)
+            }
+        }
+    }
+
+    /// Events are sent as they are added, so there is nothing to flush
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn collector_url(&self) -> &str {
+        &self.collector_url
+    }
+
+    fn stats(&self) -> EmitterStats {
+        self.stats
+    }
+
+    fn reset_stats(&mut self) {
+        self.stats = EmitterStats::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::http_client::mock_collector::mock_collector;
+    use crate::{StructuredEvent, Tracker};
+
+    use super::*;
+
+    fn event() -> StructuredEvent {
+        StructuredEvent::builder()
+            .category("cli")
+            .action("run")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn tracks_event_synchronously() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let emitter = BlockingEmitter::new(&url).unwrap();
+        let mut tracker = Tracker::new("ns", "app_id", emitter, None);
+
+        let event_id = tracker.track_blocking(event(), None).unwrap();
+
+        // The request has been received by the time tracking returns
+        let request = requests.try_recv().unwrap();
+        assert_eq!(request.method, "POST");
+        assert!(request.body.contains(&event_id.to_string()));
+        assert_eq!(tracker.emitter().stats().sent, 1);
+    }
+
+    #[test]
+    fn failed_request_returns_error() {
+        let (url, _requests) = mock_collector(|_| (500, String::new()));
+        let emitter = BlockingEmitter::new(&url).unwrap();
+        let mut tracker = Tracker::new("ns", "app_id", emitter, None);
+
+        assert!(tracker.track_blocking(event(), None).is_err());
+        assert_eq!(tracker.emitter().stats().failed, 1);
+    }
+
+    #[tokio::test]
+    async fn fails_within_async_runtime() {
+        let mut emitter = BlockingEmitter::new("http://localhost:8080").unwrap();
+        assert!(emitter.add(PayloadBuilder::default()).is_err());
+
+        // The emitter's runtime can't be dropped from within an async runtime
+        tokio::task::spawn_blocking(move || drop(emitter))
+            .await
+            .unwrap();
+    }
+}
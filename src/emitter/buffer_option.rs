@@ -0,0 +1,36 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+/// The number of events buffered by the [BatchEmitter](crate::BatchEmitter) before they are sent
+///
+/// Set with [Emitter::with_buffer_option](crate::Emitter::with_buffer_option). By convention, and by default,
+/// `Single` is used when sending events via GET, and `SmallBatch` when sending via POST.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum BufferOption {
+    /// Send each event as soon as it is added
+    Single,
+    /// Send events in batches of 10
+    #[default]
+    SmallBatch,
+    /// Send events in batches of 25
+    LargeBatch,
+}
+
+impl BufferOption {
+    /// The number of events sent in each batch
+    pub fn size(&self) -> usize {
+        match self {
+            BufferOption::Single => 1,
+            BufferOption::SmallBatch => 10,
+            BufferOption::LargeBatch => 25,
+        }
+    }
+}
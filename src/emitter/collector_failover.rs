@@ -0,0 +1,133 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::Error;
+
+/// Collector URLs that requests fail over between, set with `BatchEmitterBuilder::fallback_collector_url`
+///
+/// Requests are sent to the active collector, starting with the primary one. Once `max_failures`
+/// requests in a row have failed, the next collector becomes active, going back to the primary
+/// after the last fallback. A successful request resets the count. Clones share the active collector.
+#[derive(Debug, Clone)]
+pub(crate) struct CollectorFailover {
+    collector_urls: Arc<Vec<String>>,
+    max_failures: usize,
+    active: Arc<AtomicUsize>,
+    failures: Arc<AtomicUsize>,
+}
+
+impl CollectorFailover {
+    pub(crate) fn new(collector_urls: Vec<String>, max_failures: usize) -> Result<Self, Error> {
+        if max_failures == 0 {
+            return Err(Error::BuilderError(
+                "Failover must happen after at least one failure".to_string(),
+            ));
+        }
+        if collector_urls.is_empty() {
+            return Err(Error::BuilderError(
+                "At least one collector URL is required".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            collector_urls: Arc::new(collector_urls),
+            max_failures,
+            active: Arc::default(),
+            failures: Arc::default(),
+        })
+    }
+
+    /// The index and URL of the collector requests are sent to
+    pub(crate) fn active(&self) -> (usize, &str) {
+        let index = self.active.load(Ordering::SeqCst);
+        (index, &self.collector_urls[index])
+    }
+
+    /// Records the outcome of a request to the collector at `index`, failing over if it failed too often
+    ///
+    /// Outcomes of requests to a collector that is no longer active, e.g. sent concurrently, are ignored.
+    pub(crate) fn record(&self, index: usize, failed: bool) {
+        if self.active.load(Ordering::SeqCst) != index {
+            return;
+        }
+        if !failed {
+            self.failures.store(0, Ordering::SeqCst);
+            return;
+        }
+
+        if self.failures.fetch_add(1, Ordering::SeqCst) + 1 < self.max_failures {
+            return;
+        }
+        let next = (index + 1) % self.collector_urls.len();
+        if self
+            .active
+            .compare_exchange(index, next, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            self.failures.store(0, Ordering::SeqCst);
+            log::warn!(
+                "Collector {} failed {} times in a row, failing over to {}",
+                self.collector_urls[index],
+                self.max_failures,
+                self.collector_urls[next]
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failover(max_failures: usize) -> CollectorFailover {
+        CollectorFailover::new(
+            vec!["http://primary".to_string(), "http://fallback".to_string()],
+            max_failures,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn fails_over_in_order_after_consecutive_failures() {
+        let failover = failover(2);
+
+        failover.record(0, true);
+        failover.record(0, false);
+        failover.record(0, true);
+        assert_eq!(failover.active(), (0, "http://primary"));
+
+        failover.record(0, true);
+        assert_eq!(failover.active(), (1, "http://fallback"));
+
+        failover.record(1, true);
+        failover.record(1, true);
+        assert_eq!(failover.active(), (0, "http://primary"));
+    }
+
+    #[test]
+    fn ignores_outcomes_of_inactive_collectors() {
+        let failover = failover(1);
+
+        failover.record(0, true);
+        failover.record(0, true);
+
+        assert_eq!(failover.active(), (1, "http://fallback"));
+    }
+
+    #[test]
+    fn invalid_settings_are_an_error() {
+        assert!(CollectorFailover::new(vec!["http://primary".to_string()], 0).is_err());
+        assert!(CollectorFailover::new(Vec::new(), 1).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use crate::error::Error;
+
+/// Normalizes the URL of a collector, as done when creating an [Emitter](crate::Emitter)
+///
+/// A bare hostname, such as `collector.example.com`, is upgraded to `https://`, and trailing
+/// slashes are removed so the collector paths can be appended. Fails if the URL is invalid,
+/// or isn't an `http` or `https` URL with a host.
+///
+/// ## Example
+/// ```
+/// use snowplow_tracker::normalize_collector_url;
+///
+/// assert_eq!(normalize_collector_url("example.com").unwrap(), "https://example.com");
+/// assert_eq!(normalize_collector_url("http://example.com//").unwrap(), "http://example.com");
+/// assert!(normalize_collector_url("not a url").is_err());
+/// ```
+pub fn normalize_collector_url(collector_url: &str) -> Result<String, Error> {
+    let collector_url = collector_url.trim();
+    // A relative URL would otherwise have its first segment taken as the host
+    if collector_url.starts_with('/') {
+        return Err(Error::EmitterError(format!(
+            "Invalid collector URL {collector_url}: must be an absolute URL"
+        )));
+    }
+
+    let with_scheme = match collector_url.contains("://") {
+        true => collector_url.to_string(),
+        false => format!("https://{collector_url}"),
+    };
+
+    let url = match reqwest::Url::parse(&with_scheme) {
+        Ok(url) => url,
+        Err(e) => {
+            return Err(Error::EmitterError(format!(
+                "Invalid collector URL {collector_url}: {e}"
+            )))
+        }
+    };
+
+    if !matches!(url.scheme(), "http" | "https") || url.host().is_none() {
+        return Err(Error::EmitterError(format!(
+            "Invalid collector URL {collector_url}: must be an http or https URL with a host"
+        )));
+    }
+
+    Ok(url.as_str().trim_end_matches('/').to_string())
+}
+
+// Normalizes the collector URL for constructors that can't fail, keeping it as-is if invalid
+pub(crate) fn normalize_collector_url_or_keep(collector_url: &str) -> String {
+    normalize_collector_url(collector_url).unwrap_or_else(|e| {
+        log::warn!("{e}, using it as-is");
+        collector_url.to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_collector_url_upgrades_bare_hostnames() {
+        assert_eq!(
+            normalize_collector_url("example.com").unwrap(),
+            "https://example.com"
+        );
+        assert_eq!(
+            normalize_collector_url("localhost:9090").unwrap(),
+            "https://localhost:9090"
+        );
+    }
+
+    #[test]
+    fn normalize_collector_url_strips_trailing_slashes() {
+        assert_eq!(
+            normalize_collector_url("http://example.com/").unwrap(),
+            "http://example.com"
+        );
+        assert_eq!(
+            normalize_collector_url("https://example.com/proxy//").unwrap(),
+            "https://example.com/proxy"
+        );
+    }
+
+    #[test]
+    fn normalize_collector_url_rejects_invalid_urls() {
+        for collector_url in [
+            "not a url",
+            "",
+            "/relative/path",
+            "ftp://example.com",
+            "https://",
+        ] {
+            assert!(
+                normalize_collector_url(collector_url).is_err(),
+                "{collector_url} should be rejected"
+            );
+        }
+    }
+}
@@ -0,0 +1,48 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::Error;
+
+/// The vendor path segment of the collector endpoint events are POSTed to, i.e. `/{vendor}/{version}`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum CollectorVendor {
+    /// `com.snowplowanalytics.snowplow`, as expected by a Snowplow collector
+    #[default]
+    Snowplow,
+    /// A custom vendor, for collectors set up to accept events on another path, e.g. `com.acme`
+    ///
+    /// It must not be empty, nor contain `/`.
+    Custom(String),
+}
+
+impl CollectorVendor {
+    /// The path segment of the vendor
+    pub fn as_str(&self) -> &str {
+        match self {
+            CollectorVendor::Snowplow => "com.snowplowanalytics.snowplow",
+            CollectorVendor::Custom(vendor) => vendor,
+        }
+    }
+
+    // Checks the vendor is a single, non-empty path segment
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        let vendor = self.as_str();
+        if vendor.is_empty() || vendor.contains('/') {
+            return Err(Error::BuilderError(format!(
+                "Collector vendor must be a single non-empty path segment: {vendor:?}"
+            )));
+        }
+
+        Ok(())
+    }
+}
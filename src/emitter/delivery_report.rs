@@ -0,0 +1,88 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use std::sync::Arc;
+
+use serde_json::Value;
+
+/// A callback called with the [DeliveryReport] of every batch sent successfully, set with `BatchEmitterBuilder::on_success`
+pub type OnSuccess = Arc<dyn Fn(&DeliveryReport) + Send + Sync>;
+
+/// How many events of a batch the collector accepted, for a successful (2xx) response
+///
+/// Collectors, or endpoints in front of them, may report events they rejected despite responding
+/// successfully, with a JSON body such as `{"accepted": 8, "rejected": 2}`. If only one count is
+/// given, the other is the rest of the batch. If the body has neither, all events count as accepted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeliveryReport {
+    /// The status code of the response
+    pub status: u16,
+    /// The number of events accepted by the collector
+    pub accepted: u64,
+    /// The number of events rejected by the collector
+    pub rejected: u64,
+}
+
+impl DeliveryReport {
+    /// Parses the counts from the body of a successful response to a batch of `batch_size` events
+    pub(crate) fn from_response(status: u16, body: &str, batch_size: u64) -> Self {
+        let counts = serde_json::from_str::<Value>(body).ok();
+        let count = |field: &str| {
+            counts
+                .as_ref()
+                .and_then(|counts| counts.get(field))
+                .and_then(Value::as_u64)
+                .map(|count| count.min(batch_size))
+        };
+
+        let (accepted, rejected) = match (count("accepted"), count("rejected")) {
+            (Some(accepted), Some(rejected)) => (accepted, rejected),
+            (Some(accepted), None) => (accepted, batch_size - accepted),
+            (None, Some(rejected)) => (batch_size - rejected, rejected),
+            (None, None) => (batch_size, 0),
+        };
+
+        Self {
+            status,
+            accepted,
+            rejected,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_are_parsed_from_body() {
+        let report = DeliveryReport::from_response(200, r#"{"accepted": 8, "rejected": 2}"#, 10);
+        assert_eq!(
+            report,
+            DeliveryReport {
+                status: 200,
+                accepted: 8,
+                rejected: 2
+            }
+        );
+
+        let report = DeliveryReport::from_response(200, r#"{"rejected": 3}"#, 10);
+        assert_eq!((report.accepted, report.rejected), (7, 3));
+    }
+
+    #[test]
+    fn all_events_are_accepted_without_counts() {
+        for body in ["", "ok", r#"{"status": "ok"}"#, r#"{"accepted": "all"}"#] {
+            let report = DeliveryReport::from_response(200, body, 10);
+            assert_eq!((report.accepted, report.rejected), (10, 0));
+        }
+    }
+}
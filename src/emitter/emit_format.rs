@@ -0,0 +1,22 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+/// The shape of the body of POST requests sent by the [BatchEmitter](crate::BatchEmitter)
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum EmitFormat {
+    /// The `payload_data` self-describing JSON expected by a Snowplow collector,
+    /// i.e. `{"schema": "iglu:.../payload_data/...", "data": [event, ...]}`
+    #[default]
+    Tp2Envelope,
+    /// A plain JSON array of events, i.e. `[event, ...]`, for endpoints other than a collector,
+    /// such as a webhook forwarding events
+    RawJsonArray,
+}
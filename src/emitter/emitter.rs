@@ -9,20 +9,122 @@
 // "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
 
-use crate::payload::PayloadBuilder;
+use async_trait::async_trait;
+
+use crate::emitter::{BufferOption, EmitterStats};
+use crate::payload::{Payload, PayloadBuilder};
 use crate::Error;
 
+/// A predicate deciding whether an event is kept, set with [Emitter::set_filter]
+pub type EventFilter = Box<dyn Fn(&Payload) -> bool + Send + Sync>;
+
+/// A callback receiving events the Emitter gave up sending, set with [Emitter::on_dead_letter]
+pub type DeadLetter = Box<dyn Fn(Vec<Payload>) + Send + Sync>;
+
 /// An Emitter is responsible for handling events in an [EventStore](crate::EventStore),
 /// which are sent to the collector using a [HttpClient](crate::HttpClient).
 ///
 /// Implement this trait to use your own Emitter implementation on a tracker.
+/// Its async methods use the [async_trait crate](https://crates.io/crates/async-trait), without requiring `Send` futures.
+#[async_trait(?Send)]
 pub trait Emitter {
     /// Add a [PayloadBuilder] to the Emitter
     fn add(&mut self, payload: PayloadBuilder) -> Result<(), Error>;
+    /// Add a [PayloadBuilder] to the Emitter, without waiting for it to be queued
+    ///
+    /// By default, this is the same as [Emitter::add]
+    fn add_nonblocking(&mut self, payload: PayloadBuilder) -> Result<(), Error> {
+        self.add(payload)
+    }
     /// Try to send all events in the Emitter's queue
     fn flush(&mut self) -> Result<(), Error>;
+    /// Send all events in the Emitter's queue, waiting until they have been sent
+    ///
+    /// Useful for deterministic control over batches, e.g. in tests.
+    /// By default, this is the same as [Emitter::flush]
+    fn flush_now(&mut self) -> Result<(), Error> {
+        self.flush()
+    }
     /// Safely shuts down the Emitter.
     fn close(&mut self) -> Result<(), Error>;
-    /// The provided URL of the Snowplow collector
+    /// The URL of the Snowplow collector, after any normalization by the Emitter
     fn collector_url(&self) -> &str;
+    /// Set a filter deciding which events are sent, dropping events for which it returns `false`
+    ///
+    /// Events are filtered when added, before they are buffered.
+    /// By default, filters are not supported and an error is returned
+    fn set_filter(&mut self, _filter: EventFilter) -> Result<(), Error> {
+        Err(Error::EmitterError(
+            "Event filters are not supported by this emitter".to_string(),
+        ))
+    }
+    /// Set a callback called with the events the Emitter gives up sending, e.g. once they run out of retries
+    ///
+    /// This lets callers persist events elsewhere rather than losing them.
+    /// By default, dead letter callbacks are not supported and an error is returned
+    fn on_dead_letter(&mut self, _dead_letter: DeadLetter) -> Result<(), Error> {
+        Err(Error::EmitterError(
+            "Dead letter callbacks are not supported by this emitter".to_string(),
+        ))
+    }
+    /// Set the [BufferOption] deciding how many events are buffered before they are sent
+    ///
+    /// By default, buffering is not supported and an error is returned
+    fn with_buffer_option(self, _buffer_option: BufferOption) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        Err(Error::EmitterError(
+            "Buffer options are not supported by this emitter".to_string(),
+        ))
+    }
+    /// Check whether the collector is available, e.g. to decide whether to buffer or send events
+    ///
+    /// By default, health checks are not supported and an error is returned
+    async fn health_check(&self) -> Result<(), Error> {
+        Err(Error::EmitterError(
+            "Health checks are not supported by this emitter".to_string(),
+        ))
+    }
+    /// Called by the [Tracker](crate::Tracker) using this Emitter with its namespace
+    fn set_namespace(&mut self, _namespace: &str) {}
+    /// Stop sending events to the collector, while still accepting new events
+    fn pause(&mut self) -> Result<(), Error> {
+        Err(Error::EmitterError(
+            "Pausing is not supported by this emitter".to_string(),
+        ))
+    }
+    /// Resume sending events to the collector, sending any events queued while paused
+    fn resume(&mut self) -> Result<(), Error> {
+        Err(Error::EmitterError(
+            "Resuming is not supported by this emitter".to_string(),
+        ))
+    }
+    /// A snapshot of the number of events handled by the Emitter
+    ///
+    /// By default, no statistics are kept and all counts are zero
+    fn stats(&self) -> EmitterStats {
+        EmitterStats::default()
+    }
+    /// Reset the counts of events handled by the Emitter to zero
+    fn reset_stats(&mut self) {}
+    /// The number of events currently waiting to be sent, from [Emitter::stats]
+    fn buffer_len(&self) -> usize {
+        self.stats().buffered
+    }
+    /// Remove and return the events waiting to be sent, without sending them
+    ///
+    /// Useful for inspecting or auditing events before they are sent, e.g. in tests.
+    /// By default, no events are buffered and none are returned
+    fn drain(&mut self) -> Vec<Payload> {
+        Vec::new()
+    }
+    /// The number of events successfully sent to the collector, from [Emitter::stats]
+    fn total_sent(&self) -> u64 {
+        self.stats().sent
+    }
+    /// The number of events in failed send attempts, counted once per attempt, from [Emitter::stats]
+    fn total_failed(&self) -> u64 {
+        self.stats().failed
+    }
 }
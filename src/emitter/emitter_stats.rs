@@ -0,0 +1,27 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+/// A snapshot of the number of events handled by an [Emitter](crate::Emitter)
+///
+/// Counts are of events, not of requests or batches.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EmitterStats {
+    /// Events successfully sent to the collector
+    pub sent: u64,
+    /// Events in send attempts that failed, counted once per attempt
+    pub failed: u64,
+    /// Events queued to be sent again after a failed attempt
+    pub retried: u64,
+    /// Events given up on after failing to send
+    pub dropped: u64,
+    /// Events currently waiting in the event store
+    pub buffered: usize,
+}
@@ -0,0 +1,84 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use std::time::Duration;
+
+/// When the [BatchEmitter](crate::BatchEmitter) sends buffered events: once enough events, or bytes,
+/// are buffered, or after an interval, whichever comes first
+///
+/// Conditions left unset are not checked. Without a number of events, the [BufferOption](crate::BufferOption)
+/// of the emitter decides how many events are sent in each batch.
+///
+/// ## Example
+/// ```
+/// use std::time::Duration;
+/// use snowplow_tracker::{BatchEmitter, Emitter, FlushPolicy};
+///
+/// let mut emitter = BatchEmitter::builder()
+///     .collector_url("https://example.com")
+///     .flush_policy(
+///         FlushPolicy::new()
+///             .events(50)
+///             .bytes(64_000)
+///             .interval(Duration::from_secs(5)),
+///     )
+///     .build()
+///     .unwrap();
+///
+/// // Close the emitter thread
+/// emitter.close().unwrap();
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FlushPolicy {
+    events: Option<usize>,
+    bytes: Option<usize>,
+    interval: Option<Duration>,
+}
+
+impl FlushPolicy {
+    /// A policy with no conditions set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send events once this many are buffered
+    pub fn events(mut self, events: usize) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Send events once they add up to this many bytes, serialized as JSON
+    pub fn bytes(mut self, bytes: usize) -> Self {
+        self.bytes = Some(bytes);
+        self
+    }
+
+    /// Send all buffered events at this interval
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// The number of buffered events that triggers sending them, if set
+    pub fn events_threshold(&self) -> Option<usize> {
+        self.events
+    }
+
+    /// The number of buffered bytes that triggers sending them, if set
+    pub fn bytes_threshold(&self) -> Option<usize> {
+        self.bytes
+    }
+
+    /// The interval at which buffered events are sent, if set
+    pub fn flush_interval(&self) -> Option<Duration> {
+        self.interval
+    }
+}
@@ -0,0 +1,20 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+/// The HTTP method the [BatchEmitter](crate::BatchEmitter) sends events with
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum HttpMethod {
+    /// Send events in batches, in the body of POST requests
+    #[default]
+    Post,
+    /// Send each event in its own GET request, as query parameters
+    Get,
+}
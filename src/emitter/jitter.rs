@@ -0,0 +1,81 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use std::time::Duration;
+
+use rand::Rng;
+
+/// How the delay before retrying a batch is randomized, so that many emitters don't retry in lockstep
+///
+/// The delay before each retry doubles, starting at 1 second. This is the backoff window
+/// the jitter randomizes the delay within.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Jitter {
+    /// Wait the whole backoff window
+    None,
+    /// Wait a random time between zero and the backoff window
+    Full,
+    /// Wait half the backoff window, plus a random time up to the other half
+    #[default]
+    Equal,
+}
+
+impl Jitter {
+    /// The delay to wait within the backoff window
+    pub(crate) fn apply(&self, backoff: Duration, rng: &mut impl Rng) -> Duration {
+        match self {
+            Jitter::None => backoff,
+            Jitter::Full => backoff.mul_f64(rng.gen_range(0.0..=1.0)),
+            Jitter::Equal => {
+                let half = backoff / 2;
+                half + half.mul_f64(rng.gen_range(0.0..=1.0))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn jittered_delay_stays_within_backoff_window() {
+        let backoff = Duration::from_secs(8);
+        let mut rng = StdRng::seed_from_u64(42);
+
+        for _ in 0..100 {
+            let full = Jitter::Full.apply(backoff, &mut rng);
+            assert!(full <= backoff);
+
+            let equal = Jitter::Equal.apply(backoff, &mut rng);
+            assert!(equal >= backoff / 2 && equal <= backoff);
+        }
+
+        assert_eq!(Jitter::None.apply(backoff, &mut rng), backoff);
+    }
+
+    #[test]
+    fn jitter_is_deterministic_with_a_seeded_rng() {
+        let backoff = Duration::from_secs(8);
+        let delays = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..5)
+                .map(|_| Jitter::Full.apply(backoff, &mut rng))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(delays(7), delays(7));
+        assert_ne!(delays(7), delays(8));
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use std::sync::{Arc, Mutex};
+
+use crate::clock::{Clock, SystemClock};
+use crate::emitter::{Emitter, EmitterStats};
+use crate::payload::{Payload, PayloadBuilder};
+use crate::Error;
+
+/// An [Emitter] that records events rather than sending them, for testing code that tracks events
+///
+/// Clones share the recorded events, so a clone can be kept to inspect the events tracked by a [Tracker](crate::Tracker).
+///
+/// ## Example
+/// ```
+/// use snowplow_tracker::{MockEmitter, StructuredEvent, Tracker};
+///
+/// let emitter = MockEmitter::new();
+/// let mut tracker = Tracker::new("ns", "app_id", emitter.clone(), None);
+///
+/// let event = StructuredEvent::builder()
+///     .category("shop")
+///     .action("add-to-basket")
+///     .build()
+///     .unwrap();
+/// tracker.track(event, None).unwrap();
+///
+/// let payloads = emitter.payloads();
+/// assert_eq!(payloads.len(), 1);
+///
+/// let payload = serde_json::to_value(&payloads[0]).unwrap();
+/// assert_eq!(payload["se_ac"], "add-to-basket");
+/// ```
+#[derive(Clone)]
+pub struct MockEmitter {
+    payloads: Arc<Mutex<Vec<Payload>>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for MockEmitter {
+    fn default() -> Self {
+        Self {
+            payloads: Arc::default(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+impl MockEmitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a [MockEmitter] using the [Clock] for the sent timestamp (`stm`) of events
+    pub fn with_clock(clock: impl Clock + 'static) -> Self {
+        Self {
+            payloads: Arc::default(),
+            clock: Arc::new(clock),
+        }
+    }
+
+    /// The payloads of all events added to the emitter, in the order they were added
+    pub fn payloads(&self) -> Vec<Payload> {
+        match self.payloads.lock() {
+            Ok(payloads) => payloads.clone(),
+            Err(e) => {
+                log::error!("Failed to lock recorded payloads: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    /// Removes all recorded payloads
+    pub fn clear(&self) {
+        match self.payloads.lock() {
+            Ok(mut payloads) => payloads.clear(),
+            Err(e) => log::error!("Failed to lock recorded payloads: {e}"),
+        }
+    }
+}
+
+impl Emitter for MockEmitter {
+    /// Records the finalised payload
+    fn add(&mut self, payload: PayloadBuilder) -> Result<(), Error> {
+        let payload = payload.finalise_payload_at(self.clock.now())?;
+
+        match self.payloads.lock() {
+            Ok(mut payloads) => {
+                payloads.push(payload);
+                Ok(())
+            }
+            Err(e) => Err(Error::EmitterError(format!(
+                "Failed to lock recorded payloads: {e}"
+            ))),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn collector_url(&self) -> &str {
+        ""
+    }
+
+    fn pause(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// All recorded events count as sent
+    fn stats(&self) -> EmitterStats {
+        EmitterStats {
+            sent: self.payloads().len() as u64,
+            ..EmitterStats::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{BufferOption, SelfDescribingEvent, SelfDescribingJson, StructuredEvent, Tracker};
+
+    use super::*;
+
+    #[test]
+    fn records_structured_events() {
+        let emitter = MockEmitter::new();
+        let mut tracker = Tracker::new("ns", "app_id", emitter.clone(), None);
+
+        let event = StructuredEvent::builder()
+            .category("shop")
+            .action("add-to-basket")
+            .label("shoes")
+            .value(2.5)
+            .build()
+            .unwrap();
+        let event_id = tracker.track(event, None).unwrap();
+
+        let payloads = emitter.payloads();
+        assert_eq!(payloads.len(), 1);
+        let payload = serde_json::to_value(&payloads[0]).unwrap();
+        assert_eq!(payload["e"], "se");
+        assert_eq!(payload["eid"], event_id.to_string());
+        assert_eq!(payload["aid"], "app_id");
+        assert_eq!(payload["se_ca"], "shop");
+        assert_eq!(payload["se_la"], "shoes");
+        assert_eq!(payload["se_va"], "2.5");
+    }
+
+    #[test]
+    fn records_self_describing_events_with_context() {
+        let emitter = MockEmitter::new();
+        let mut tracker = Tracker::new("ns", "app_id", emitter.clone(), None);
+
+        let event = SelfDescribingEvent::builder()
+            .schema("iglu:com.acme/button_click/jsonschema/1-0-0")
+            .data(json!({ "id": "buy" }))
+            .build()
+            .unwrap();
+        let context = SelfDescribingJson::new(
+            "iglu:com.acme/page/jsonschema/1-0-0",
+            json!({ "name": "basket" }),
+        );
+        tracker.track(event, Some(vec![context])).unwrap();
+
+        let payload = serde_json::to_value(&emitter.payloads()[0]).unwrap();
+        assert_eq!(payload["e"], "ue");
+
+        // The event and context entities are sent as JSON strings
+        let ue_pr: serde_json::Value =
+            serde_json::from_str(payload["ue_pr"].as_str().unwrap()).unwrap();
+        assert_eq!(
+            ue_pr["data"]["schema"],
+            "iglu:com.acme/button_click/jsonschema/1-0-0"
+        );
+        assert_eq!(ue_pr["data"]["data"]["id"], "buy");
+
+        let co: serde_json::Value = serde_json::from_str(payload["co"].as_str().unwrap()).unwrap();
+        assert_eq!(co["data"][0]["data"]["name"], "basket");
+    }
+
+    #[test]
+    fn clear_removes_recorded_payloads() {
+        let emitter = MockEmitter::new();
+        let mut tracker = Tracker::new("ns", "app_id", emitter.clone(), None);
+
+        let event = StructuredEvent::builder()
+            .category("shop")
+            .action("add")
+            .build()
+            .unwrap();
+        tracker.track(event, None).unwrap();
+        emitter.clear();
+
+        assert!(emitter.payloads().is_empty());
+        assert_eq!(emitter.stats().sent, 0);
+    }
+
+    #[test]
+    fn unsupported_filter_and_dead_letter_callback_are_errors() {
+        let mut emitter = MockEmitter::new();
+
+        assert!(emitter.set_filter(Box::new(|_| false)).is_err());
+        assert!(emitter.on_dead_letter(Box::new(|_| {})).is_err());
+    }
+
+    #[test]
+    fn unsupported_buffer_option_is_an_error() {
+        let emitter = MockEmitter::new();
+
+        assert!(emitter.with_buffer_option(BufferOption::Single).is_err());
+    }
+}
@@ -9,10 +9,56 @@
 // "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
 
+#[cfg(not(target_arch = "wasm32"))]
+mod adaptive_batch_size;
+#[cfg(not(target_arch = "wasm32"))]
 mod batch_emitter;
+#[cfg(feature = "blocking")]
+mod blocking_emitter;
+mod buffer_option;
+#[cfg(not(target_arch = "wasm32"))]
+mod collector_failover;
+mod collector_url;
+mod collector_vendor;
+#[cfg(not(target_arch = "wasm32"))]
+mod delivery_report;
+mod emit_format;
 mod emitter;
+mod emitter_stats;
+#[cfg(not(target_arch = "wasm32"))]
+mod flush_policy;
+mod http_method;
+mod jitter;
+mod mock_emitter;
+mod protocol_version;
+#[cfg(not(target_arch = "wasm32"))]
+mod rate_limiter;
 mod retry_policy;
+#[cfg(not(target_arch = "wasm32"))]
+mod sink;
+#[cfg(target_arch = "wasm32")]
+mod wasm_emitter;
 
+#[cfg(not(target_arch = "wasm32"))]
 pub use batch_emitter::BatchEmitter;
-pub use emitter::Emitter;
+#[cfg(feature = "blocking")]
+pub use blocking_emitter::BlockingEmitter;
+pub use buffer_option::BufferOption;
+pub use collector_url::normalize_collector_url;
+pub use collector_vendor::CollectorVendor;
+#[cfg(not(target_arch = "wasm32"))]
+pub use delivery_report::{DeliveryReport, OnSuccess};
+pub use emit_format::EmitFormat;
+pub use emitter::{DeadLetter, Emitter, EventFilter};
+pub use emitter_stats::EmitterStats;
+#[cfg(not(target_arch = "wasm32"))]
+pub use flush_policy::FlushPolicy;
+pub use http_method::HttpMethod;
+pub use jitter::Jitter;
+pub use mock_emitter::MockEmitter;
+pub use protocol_version::ProtocolVersion;
 pub use retry_policy::RetryPolicy;
+#[cfg(not(target_arch = "wasm32"))]
+pub use sink::Sink;
+#[cfg(target_arch = "wasm32")]
+pub use wasm_emitter::WasmEmitter;
@@ -0,0 +1,48 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::Error;
+
+/// The protocol version path segment of the collector endpoint events are POSTed to, i.e. `/{vendor}/{version}`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum ProtocolVersion {
+    /// `tp2`, the current version of the tracker protocol
+    #[default]
+    Tp2,
+    /// A custom version, for collectors exposing another version of the API
+    ///
+    /// It must not be empty, nor contain `/`.
+    Custom(String),
+}
+
+impl ProtocolVersion {
+    /// The path segment of the version
+    pub fn as_str(&self) -> &str {
+        match self {
+            ProtocolVersion::Tp2 => "tp2",
+            ProtocolVersion::Custom(version) => version,
+        }
+    }
+
+    // Checks the version is a single, non-empty path segment
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        let version = self.as_str();
+        if version.is_empty() || version.contains('/') {
+            return Err(Error::BuilderError(format!(
+                "Protocol version must be a single non-empty path segment: {version:?}"
+            )));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::Error;
+
+/// A token bucket capping the rate of requests, set with `BatchEmitterBuilder::max_requests_per_second`
+///
+/// The bucket holds up to one second's worth of requests, so a burst after a quiet period is sent
+/// right away, and later requests wait for tokens to be refilled at the configured rate.
+/// Clones share the bucket.
+#[derive(Debug, Clone)]
+pub(crate) struct RateLimiter {
+    requests_per_second: f64,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(requests_per_second: u32) -> Result<Self, Error> {
+        if requests_per_second == 0 {
+            return Err(Error::BuilderError(
+                "Rate limit must allow at least one request per second".to_string(),
+            ));
+        }
+
+        let requests_per_second = f64::from(requests_per_second);
+        Ok(Self {
+            requests_per_second,
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: requests_per_second,
+                refilled_at: Instant::now(),
+            })),
+        })
+    }
+
+    /// Waits until a request can be sent, taking a token from the bucket
+    ///
+    /// Requests waiting for a token are let through in the order they started waiting.
+    pub(crate) async fn acquire(&self) {
+        // The lock is held while waiting, so later requests queue up behind this one
+        let mut bucket = self.bucket.lock().await;
+        self.refill(&mut bucket);
+
+        if bucket.tokens < 1.0 {
+            let wait = Duration::from_secs_f64((1.0 - bucket.tokens) / self.requests_per_second);
+            log::debug!("Rate limit reached, waiting {wait:?} before sending");
+            tokio::time::sleep(wait).await;
+            self.refill(&mut bucket);
+        }
+
+        bucket.tokens -= 1.0;
+    }
+
+    fn refill(&self, bucket: &mut Bucket) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens =
+            (bucket.tokens + elapsed * self.requests_per_second).min(self.requests_per_second);
+        bucket.refilled_at = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn waits_for_tokens_once_the_burst_is_used() {
+        let rate_limiter = RateLimiter::new(20).unwrap();
+        let started_at = Instant::now();
+
+        for _ in 0..20 {
+            rate_limiter.acquire().await;
+        }
+        assert!(started_at.elapsed() < Duration::from_millis(50));
+
+        for _ in 0..4 {
+            rate_limiter.acquire().await;
+        }
+        assert!(started_at.elapsed() >= Duration::from_millis(190));
+    }
+
+    #[test]
+    fn zero_rate_is_an_error() {
+        assert!(RateLimiter::new(0).is_err());
+        assert!(RateLimiter::new(1).is_ok());
+    }
+}
@@ -0,0 +1,60 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::event_batch::EventBatch;
+use crate::{Error, HttpClient, Payload, ReqwestClient};
+
+/// A destination for batches of events, such as a message queue, used instead of a collector
+///
+/// The [BatchEmitter](crate::BatchEmitter) still buffers and retries events, and delegates sending each
+/// batch to the sink, set with `sink` on the [BatchEmitter::builder](crate::BatchEmitter::builder).
+/// A batch is retried according to the retry policy of the emitter if the sink returns an error.
+/// Events are sent to the collector via HTTP by default, as with the [ReqwestClient] implementation.
+///
+/// This is an async trait, using the [async_trait crate](https://crates.io/crates/async-trait).
+///
+/// ## Example
+/// ```
+/// use async_trait::async_trait;
+/// use snowplow_tracker::{BatchEmitter, Emitter, Error, Payload, Sink};
+///
+/// struct StdoutSink;
+///
+/// #[async_trait]
+/// impl Sink for StdoutSink {
+///     async fn send(&self, batch: Vec<Payload>) -> Result<(), Error> {
+///         for event in batch {
+///             println!("{}", serde_json::to_string(&event)?);
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let mut emitter = BatchEmitter::builder().sink(StdoutSink).build().unwrap();
+/// emitter.close().unwrap();
+/// ```
+#[async_trait]
+pub trait Sink {
+    /// Send a batch of events, returning an error if it should be retried
+    async fn send(&self, batch: Vec<Payload>) -> Result<(), Error>;
+}
+
+#[async_trait]
+impl Sink for ReqwestClient {
+    async fn send(&self, batch: Vec<Payload>) -> Result<(), Error> {
+        let payload = EventBatch::new(Uuid::new_v4(), batch).as_payload();
+        self.post(payload).await?;
+        Ok(())
+    }
+}
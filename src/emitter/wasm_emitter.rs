@@ -0,0 +1,128 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::clock::{Clock, SystemClock};
+use crate::emitter::collector_url::normalize_collector_url_or_keep;
+use crate::emitter::{Emitter, EmitterStats};
+use crate::event_batch::EventBatch;
+use crate::http_client::ReqwestClient;
+use crate::payload::PayloadBuilder;
+use crate::{Error, HttpClient};
+
+/// An [Emitter] for WebAssembly in the browser, sending each event in its own request using `fetch`
+///
+/// Only available on `wasm32` targets, with the `wasm` feature. Requests are spawned on the
+/// browser's event loop, so adding an event returns right away. Events aren't buffered or retried,
+/// and the outcome of each request is only reflected in [Emitter::stats].
+///
+/// ## Example
+/// ```ignore
+/// use snowplow_tracker::{Tracker, WasmEmitter};
+///
+/// let emitter = WasmEmitter::new("https://collector.example.com");
+/// let tracker = Tracker::new("ns", "app_id", emitter, None);
+/// ```
+pub struct WasmEmitter {
+    collector_url: String,
+    http_client: Box<dyn HttpClient + Send + Sync>,
+    clock: Arc<dyn Clock>,
+    // Shared with the spawned requests, which run on the same thread
+    stats: Rc<RefCell<EmitterStats>>,
+}
+
+impl WasmEmitter {
+    /// Create a [WasmEmitter] sending events with a [ReqwestClient]
+    ///
+    /// The collector URL is normalized with [normalize_collector_url](crate::normalize_collector_url),
+    /// or used as-is if invalid.
+    pub fn new(collector_url: &str) -> Self {
+        let collector_url = normalize_collector_url_or_keep(collector_url);
+        let http_client = ReqwestClient::new(&collector_url);
+        Self::create(collector_url, http_client)
+    }
+
+    /// Create a [WasmEmitter] sending events with the provided [HttpClient]
+    pub fn with_client(
+        collector_url: &str,
+        http_client: impl HttpClient + Send + Sync + 'static,
+    ) -> Self {
+        let collector_url = normalize_collector_url_or_keep(collector_url);
+        Self::create(collector_url, Box::new(http_client))
+    }
+
+    /// Set the [Clock] used for the sent timestamp (`stm`) of events, by default a [SystemClock]
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Arc::new(clock);
+    }
+
+    fn create(collector_url: String, http_client: Box<dyn HttpClient + Send + Sync>) -> Self {
+        Self {
+            collector_url,
+            http_client,
+            clock: Arc::new(SystemClock),
+            stats: Rc::default(),
+        }
+    }
+}
+
+impl Emitter for WasmEmitter {
+    /// Spawns a request sending the event to the collector, without waiting for it
+    fn add(&mut self, payload: PayloadBuilder) -> Result<(), Error> {
+        let payload = payload.finalise_payload_at(self.clock.now())?;
+        let batch = EventBatch::new(Uuid::new_v4(), vec![payload]);
+
+        let http_client = self.http_client.clone();
+        let stats = self.stats.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            match http_client.post(batch.as_payload()).await {
+                Ok(code) if (200..300).contains(&code) => stats.borrow_mut().sent += 1,
+                result => {
+                    match result {
+                        Ok(code) => log::warn!("Collector responded with status code {code}"),
+                        Err(e) => log::warn!("Failed to send event: {e}"),
+                    }
+                    let mut stats = stats.borrow_mut();
+                    stats.failed += 1;
+                    stats.dropped += 1;
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Events are sent as they are added, so there is nothing to flush
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn collector_url(&self) -> &str {
+        &self.collector_url
+    }
+
+    fn stats(&self) -> EmitterStats {
+        *self.stats.borrow()
+    }
+
+    fn reset_stats(&mut self) {
+        *self.stats.borrow_mut() = EmitterStats::default();
+    }
+}
@@ -10,6 +10,7 @@
 // See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
 
 use std::fmt::{Display, Formatter, Result};
+use std::time::SystemTimeError;
 
 /// The errors that can occur when using the Snowplow Tracker
 #[derive(Debug)]
@@ -21,6 +22,30 @@ pub enum Error {
     EmitterError(String),
     /// An error occurred in the event store
     EventStoreError(String),
+    /// The emitter is paused and its event store is full, so the event could not be queued
+    Paused,
+    /// An event or context entity failed validation against its schema
+    ValidationError(String),
+    /// The collector responded with a non-2xx status code
+    CollectorResponse {
+        /// The HTTP status code of the response
+        status: u16,
+        /// The body of the response
+        body: String,
+    },
+    /// An event is too large to be sent within the byte limit of the emitter
+    PayloadTooLarge {
+        /// The size in bytes of a request sending only this event
+        size: usize,
+        /// The byte limit of the emitter
+        limit: usize,
+    },
+    /// A request to the collector failed without a response, e.g. because it couldn't connect
+    Http(reqwest::Error),
+    /// A value could not be serialized to or deserialized from JSON
+    Serialization(serde_json::Error),
+    /// The system time is before the Unix epoch
+    SystemTime(SystemTimeError),
 }
 
 impl Display for Error {
@@ -29,11 +54,52 @@ impl Display for Error {
             Error::BuilderError(builder_err) => write!(f, "{}", builder_err),
             Error::EmitterError(emitter_err) => write!(f, "{}", emitter_err),
             Error::EventStoreError(event_store_err) => write!(f, "{}", event_store_err),
+            Error::Paused => write!(f, "Emitter is paused and the event store is full"),
+            Error::ValidationError(validation_err) => write!(f, "{}", validation_err),
+            Error::CollectorResponse { status, body } => {
+                write!(f, "Collector responded with status {status}: {body}")
+            }
+            Error::PayloadTooLarge { size, limit } => {
+                write!(
+                    f,
+                    "Payload of {size} bytes exceeds the limit of {limit} bytes"
+                )
+            }
+            Error::Http(http_err) => write!(f, "HTTP request failed: {http_err}"),
+            Error::Serialization(serde_err) => write!(f, "JSON serialization failed: {serde_err}"),
+            Error::SystemTime(time_err) => write!(f, "System time is invalid: {time_err}"),
         }
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Http(http_err) => Some(http_err),
+            Error::Serialization(serde_err) => Some(serde_err),
+            Error::SystemTime(time_err) => Some(time_err),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Error {
+        Error::Http(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Error {
+        Error::Serialization(e)
+    }
+}
+
+impl From<SystemTimeError> for Error {
+    fn from(e: SystemTimeError) -> Error {
+        Error::SystemTime(e)
+    }
+}
 
 // This allows us to use `#[builder(build_fn(error = "Error"))]` on builders
 // to return `Error` instead of `UninitializedFieldError`
@@ -42,3 +108,57 @@ impl From<derive_builder::UninitializedFieldError> for Error {
         Error::BuilderError(e.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use super::*;
+
+    #[test]
+    fn display_describes_the_error() {
+        assert_eq!(
+            Error::CollectorResponse {
+                status: 400,
+                body: "Malformed payload".to_string()
+            }
+            .to_string(),
+            "Collector responded with status 400: Malformed payload"
+        );
+        assert_eq!(
+            Error::Paused.to_string(),
+            "Emitter is paused and the event store is full"
+        );
+
+        let serde_err = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        assert_eq!(
+            Error::from(serde_json::from_str::<serde_json::Value>("{").unwrap_err()).to_string(),
+            format!("JSON serialization failed: {serde_err}")
+        );
+    }
+
+    #[test]
+    fn conversions_keep_the_source() {
+        let http_err = reqwest::Client::new().get("not a url").build().unwrap_err();
+        let expected = http_err.to_string();
+        let err = Error::from(http_err);
+        assert!(matches!(err, Error::Http(_)));
+        assert_eq!(err.source().unwrap().to_string(), expected);
+
+        let err: Error = serde_json::from_str::<serde_json::Value>("{")
+            .unwrap_err()
+            .into();
+        assert!(matches!(err, Error::Serialization(_)));
+        assert!(err.source().unwrap().is::<serde_json::Error>());
+
+        let err: Error = UNIX_EPOCH
+            .duration_since(SystemTime::now())
+            .unwrap_err()
+            .into();
+        assert!(matches!(err, Error::SystemTime(_)));
+        assert!(err.source().unwrap().is::<SystemTimeError>());
+
+        assert!(Error::Paused.source().is_none());
+    }
+}
@@ -11,18 +11,87 @@
 
 use chrono::{DateTime, Utc};
 use derive_builder::Builder;
+use serde::ser::SerializeMap;
 use serde::{Deserialize, Serialize, Serializer};
 use serde_json::{json, Value};
 use uuid::Uuid;
 
 use crate::error::Error;
-use crate::payload::{EventType, PayloadBuilder, SelfDescribingEventData, SelfDescribingJson};
+use crate::payload::{
+    EventType, NumericEncoding, PayloadBuilder, SelfDescribingEventData, SelfDescribingJson,
+};
 use crate::subject::Subject;
 
 /// Trait implemented by event types that enables the event to add itself to a PayloadBuilder.
 pub trait PayloadAddable {
     fn add_to_payload(self, payload_builder: PayloadBuilder) -> PayloadBuilder;
+    /// The event-level [Subject], which the tracker merges with its own, with the event's fields taking priority
     fn subject(&self) -> &Option<Subject>;
+    /// Moves the event-level [Subject] out of the event, so the tracker can merge it without cloning it
+    ///
+    /// Defaults to a clone of [PayloadAddable::subject].
+    fn take_subject(&mut self) -> Option<Subject> {
+        self.subject().clone()
+    }
+    /// Whether the tracker attaches its default context entities, such as the environment or platform contexts, to the event
+    ///
+    /// Context entities passed when tracking the event are always attached. Defaults to true.
+    fn attach_default_contexts(&self) -> bool {
+        true
+    }
+}
+
+/// Any event type, boxed so events of different types can be tracked together with [Tracker::track_all](crate::Tracker::track_all)
+///
+/// This is implemented for every event, e.g. `Box::new(event) as Box<dyn EventBuildable>`.
+pub trait EventBuildable {
+    #[doc(hidden)]
+    fn add_boxed_to_payload(self: Box<Self>, payload_builder: PayloadBuilder) -> PayloadBuilder;
+    #[doc(hidden)]
+    fn event_subject(&self) -> &Option<Subject>;
+    #[doc(hidden)]
+    fn event_take_subject(&mut self) -> Option<Subject>;
+    #[doc(hidden)]
+    fn event_attaches_default_contexts(&self) -> bool;
+}
+
+/// A boxed event with optional context entities, as tracked with [Tracker::track_all](crate::Tracker::track_all)
+pub type EventWithContext = (Box<dyn EventBuildable>, Option<Vec<SelfDescribingJson>>);
+
+impl<T: PayloadAddable> EventBuildable for T {
+    fn add_boxed_to_payload(self: Box<Self>, payload_builder: PayloadBuilder) -> PayloadBuilder {
+        (*self).add_to_payload(payload_builder)
+    }
+
+    fn event_subject(&self) -> &Option<Subject> {
+        self.subject()
+    }
+
+    fn event_take_subject(&mut self) -> Option<Subject> {
+        self.take_subject()
+    }
+
+    fn event_attaches_default_contexts(&self) -> bool {
+        self.attach_default_contexts()
+    }
+}
+
+impl PayloadAddable for Box<dyn EventBuildable> {
+    fn add_to_payload(self, payload_builder: PayloadBuilder) -> PayloadBuilder {
+        self.add_boxed_to_payload(payload_builder)
+    }
+
+    fn subject(&self) -> &Option<Subject> {
+        self.as_ref().event_subject()
+    }
+
+    fn take_subject(&mut self) -> Option<Subject> {
+        self.as_mut().event_take_subject()
+    }
+
+    fn attach_default_contexts(&self) -> bool {
+        self.as_ref().event_attaches_default_contexts()
+    }
 }
 
 /// Event to track custom information that does not fit into the out-of-the box events.
@@ -43,7 +112,9 @@ pub struct SelfDescribingEvent {
     /// This data must conform to the schema specified in the schema argument, or the event will fail validation and land in bad rows.
     pub data: Value,
 
-    /// The [Subject] of the event.
+    /// The [Subject] of the event, set with `.subject(subject)` on the builder.
+    ///
+    /// Its fields take priority over those of the tracker [Subject], for this event only.
     #[builder(default)]
     #[serde(skip_serializing)]
     pub subject: Option<Subject>,
@@ -77,69 +148,108 @@ impl PayloadAddable for SelfDescribingEvent {
     fn subject(&self) -> &Option<Subject> {
         &self.subject
     }
+
+    fn take_subject(&mut self) -> Option<Subject> {
+        self.subject.take()
+    }
 }
 
 /// Event to capture custom consumer interactions without the need to define a custom schema.
-#[derive(Serialize, Deserialize, Builder, Debug, Clone)]
+#[derive(Deserialize, Builder, Debug, Clone)]
 #[builder(setter(into, strip_option))]
-#[builder(build_fn(error = "Error"))]
+#[builder(build_fn(validate = "Self::validate", error = "Error"))]
 pub struct StructuredEvent {
     /// Name you for the group of objects you want to track e.g. "media", "ecomm".
-    #[serde(rename(serialize = "se_ca"))]
     pub category: String,
 
     /// Defines the type of user interaction for the web object.
     ///
     /// E.g., "play-video", "add-to-basket".
-    #[serde(rename(serialize = "se_ac"))]
     pub action: String,
 
     /// Describes the object or the action performed on it.
     ///
     /// This might be the quantity of an item added to basket
     #[builder(default)]
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(rename(serialize = "se_pr"))]
     pub property: Option<String>,
 
     /// Identifies the specific object being actioned.
     ///
     /// E.g., ID of the video being played, or the SKU or the product added-to-basket.
     #[builder(default)]
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(rename(serialize = "se_la"))]
     pub label: Option<String>,
 
-    /// Identifies the specific object being actioned.
+    /// A numeric value associated with the action, e.g. the price of the product added-to-basket.
     ///
-    /// E.g., ID of the video being played, or the SKU or the product added-to-basket.
+    /// Sent as `se_va`, by default as the shortest string representing the number, e.g. `"2.5"`,
+    /// `"-1"` or `"0"`, so whole numbers have no decimal point. With [NumericEncoding::Number] it is
+    /// sent as a JSON number instead. Must be finite, building the event fails for `NaN` or infinity.
     #[builder(default)]
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(rename(serialize = "se_va"))]
-    #[serde(serialize_with = "optional_f64_to_string")]
     pub value: Option<f64>,
 
-    /// The [Subject] of the event.
+    /// The [Subject] of the event, set with `.subject(subject)` on the builder.
+    ///
+    /// Its fields take priority over those of the tracker [Subject], for this event only.
     #[builder(default)]
-    #[serde(skip_serializing)]
     pub subject: Option<Subject>,
 
     /// The true timestamp of the event
     #[builder(default)]
-    #[serde(skip_serializing)]
     pub true_tstamp: Option<DateTime<Utc>>,
 }
 
-// Serializer to convert the optional f64 to the JSON `String` type
-// expected by the collector, rather than the default JSON `Number`
-fn optional_f64_to_string<S>(num: &Option<f64>, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    if let Some(num) = num {
-        serializer.serialize_str(&num.to_string())
-    } else {
-        serializer.serialize_none()
+// The event is flattened into the payload, using the field names of the tracker protocol
+impl Serialize for StructuredEvent {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        EncodedStructuredEvent(self, NumericEncoding::String).serialize(serializer)
+    }
+}
+
+// A structured event serialized with the numeric encoding of the payload it is part of
+pub(crate) struct EncodedStructuredEvent<'a>(
+    pub(crate) &'a StructuredEvent,
+    pub(crate) NumericEncoding,
+);
+
+impl Serialize for EncodedStructuredEvent<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let EncodedStructuredEvent(event, numeric_encoding) = self;
+
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("se_ca", &event.category)?;
+        map.serialize_entry("se_ac", &event.action)?;
+        if let Some(property) = &event.property {
+            map.serialize_entry("se_pr", property)?;
+        }
+        if let Some(label) = &event.label {
+            map.serialize_entry("se_la", label)?;
+        }
+        if let Some(value) = event.value {
+            match numeric_encoding {
+                // The collector expects the JSON `String` type, rather than the default JSON `Number`
+                NumericEncoding::String => map.serialize_entry("se_va", &value.to_string())?,
+                NumericEncoding::Number => map.serialize_entry("se_va", &value)?,
+            }
+        }
+        map.end()
+    }
+}
+
+impl StructuredEventBuilder {
+    fn validate(&self) -> Result<(), Error> {
+        match self.value {
+            // Neither has a representation in the tracker protocol
+            Some(Some(value)) if !value.is_finite() => Err(Error::BuilderError(format!(
+                "Invalid value: {value}, expected a finite number"
+            ))),
+            _ => Ok(()),
+        }
     }
 }
 
@@ -163,6 +273,10 @@ impl PayloadAddable for StructuredEvent {
     fn subject(&self) -> &Option<Subject> {
         &self.subject
     }
+
+    fn take_subject(&mut self) -> Option<Subject> {
+        self.subject.take()
+    }
 }
 
 /// Event to track user viewing a screen within the application.
@@ -205,7 +319,9 @@ pub struct ScreenViewEvent {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub transition_type: Option<String>,
 
-    /// The [Subject] of the event.
+    /// The [Subject] of the event, set with `.subject(subject)` on the builder.
+    ///
+    /// Its fields take priority over those of the tracker [Subject], for this event only.
     #[builder(default)]
     #[serde(skip_serializing)]
     pub subject: Option<Subject>,
@@ -237,6 +353,10 @@ impl PayloadAddable for ScreenViewEvent {
     fn subject(&self) -> &Option<Subject> {
         &self.subject
     }
+
+    fn take_subject(&mut self) -> Option<Subject> {
+        self.subject.take()
+    }
 }
 
 /// Event to track user timing events, such as how long resources take to load.
@@ -259,7 +379,9 @@ pub struct TimingEvent {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub label: Option<String>,
 
-    /// The [Subject] of the event.
+    /// The [Subject] of the event, set with `.subject(subject)` on the builder.
+    ///
+    /// Its fields take priority over those of the tracker [Subject], for this event only.
     #[builder(default)]
     #[serde(skip_serializing)]
     pub subject: Option<Subject>,
@@ -291,11 +413,378 @@ impl PayloadAddable for TimingEvent {
     fn subject(&self) -> &Option<Subject> {
         &self.subject
     }
+
+    fn take_subject(&mut self) -> Option<Subject> {
+        self.subject.take()
+    }
+}
+
+/// Event to track that a user is still engaged with a page, sent periodically after a page view.
+///
+/// The scroll offsets describe the range the user scrolled through since the previous page ping.
+#[derive(Serialize, Deserialize, Builder, Default, Debug, Clone)]
+#[builder(setter(into, strip_option), default)]
+#[builder(build_fn(error = "Error"))]
+pub struct PagePingEvent {
+    /// The minimum horizontal scroll offset, in pixels
+    #[serde(rename(serialize = "pp_mix"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "optional_i64_to_string")]
+    pub min_x_offset: Option<i64>,
+
+    /// The maximum horizontal scroll offset, in pixels
+    #[serde(rename(serialize = "pp_max"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "optional_i64_to_string")]
+    pub max_x_offset: Option<i64>,
+
+    /// The minimum vertical scroll offset, in pixels
+    #[serde(rename(serialize = "pp_miy"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "optional_i64_to_string")]
+    pub min_y_offset: Option<i64>,
+
+    /// The maximum vertical scroll offset, in pixels
+    #[serde(rename(serialize = "pp_may"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "optional_i64_to_string")]
+    pub max_y_offset: Option<i64>,
+
+    /// The [Subject] of the event, set with `.subject(subject)` on the builder.
+    ///
+    /// Its fields take priority over those of the tracker [Subject], for this event only.
+    #[serde(skip_serializing)]
+    pub subject: Option<Subject>,
+
+    /// The true timestamp of the event
+    #[serde(skip_serializing)]
+    pub true_tstamp: Option<DateTime<Utc>>,
+}
+
+// Serializer to convert the optional i64 to the JSON `String` type
+// expected by the collector, rather than the default JSON `Number`
+fn optional_i64_to_string<S>(num: &Option<i64>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match num {
+        Some(num) => serializer.serialize_str(&num.to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+impl PagePingEvent {
+    pub fn builder() -> PagePingEventBuilder {
+        PagePingEventBuilder::default()
+    }
+}
+
+impl PayloadAddable for PagePingEvent {
+    fn add_to_payload(self, mut payload_builder: PayloadBuilder) -> PayloadBuilder {
+        if let Some(ttm) = self.true_tstamp {
+            payload_builder = payload_builder.ttm(ttm);
+        }
+
+        payload_builder.e(EventType::PagePing).page_ping(self)
+    }
+
+    fn subject(&self) -> &Option<Subject> {
+        &self.subject
+    }
+
+    fn take_subject(&mut self) -> Option<Subject> {
+        self.subject.take()
+    }
+}
+
+/// The schema of the [LinkClickEvent]
+pub(crate) const LINK_CLICK_SCHEMA: &str =
+    "iglu:com.snowplowanalytics.snowplow/link_click/jsonschema/1-0-1";
+
+/// Event to track a user clicking a link, e.g. on a web page.
+///
+/// It is a self-describing event with the schema "iglu:com.snowplowanalytics.snowplow/link_click/jsonschema/1-0-1"
+#[derive(Serialize, Deserialize, Builder, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+#[builder(setter(into, strip_option))]
+#[builder(build_fn(validate = "Self::validate", error = "Error"))]
+pub struct LinkClickEvent {
+    /// The URL the link points to, building the event fails if it isn't a valid URL
+    pub target_url: String,
+
+    /// The id of the link element
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub element_id: Option<String>,
+
+    /// The classes of the link element
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub element_classes: Option<Vec<String>>,
+
+    /// The target of the link element, e.g. `_blank`
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub element_target: Option<String>,
+
+    /// The content of the link element, such as its text
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub element_content: Option<String>,
+
+    /// The [Subject] of the event, set with `.subject(subject)` on the builder.
+    ///
+    /// Its fields take priority over those of the tracker [Subject], for this event only.
+    #[builder(default)]
+    #[serde(skip_serializing)]
+    pub subject: Option<Subject>,
+
+    /// The true timestamp of the event
+    #[builder(default)]
+    #[serde(skip_serializing)]
+    pub true_tstamp: Option<DateTime<Utc>>,
+}
+
+impl LinkClickEventBuilder {
+    fn validate(&self) -> Result<(), Error> {
+        match &self.target_url {
+            Some(target_url) => match reqwest::Url::parse(target_url) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(Error::BuilderError(format!(
+                    "Invalid target URL {target_url}: {e}"
+                ))),
+            },
+            // Reported as an uninitialized field when building
+            None => Ok(()),
+        }
+    }
+}
+
+impl LinkClickEvent {
+    pub fn builder() -> LinkClickEventBuilder {
+        LinkClickEventBuilder::default()
+    }
+}
+
+impl PayloadAddable for LinkClickEvent {
+    fn add_to_payload(self, payload_builder: PayloadBuilder) -> PayloadBuilder {
+        let event = SelfDescribingEvent {
+            schema: LINK_CLICK_SCHEMA.to_string(),
+            data: json!(self),
+            subject: self.subject,
+            true_tstamp: self.true_tstamp,
+        };
+
+        event.add_to_payload(payload_builder)
+    }
+
+    fn subject(&self) -> &Option<Subject> {
+        &self.subject
+    }
+
+    fn take_subject(&mut self) -> Option<Subject> {
+        self.subject.take()
+    }
+}
+
+/// The schema of the context entity describing a consent document
+pub(crate) const CONSENT_DOCUMENT_SCHEMA: &str =
+    "iglu:com.snowplowanalytics.snowplow/consent_document/jsonschema/1-0-0";
+
+/// A document, such as a privacy policy, that a user grants or withdraws consent to
+///
+/// It is attached to consent events as a `consent_document` context entity.
+#[derive(Serialize, Deserialize, Builder, Debug, Clone)]
+#[builder(setter(into, strip_option))]
+#[builder(build_fn(error = "Error"))]
+pub struct ConsentDocument {
+    /// The ID of the document
+    pub id: String,
+
+    /// The version of the document
+    pub version: String,
+
+    /// The name of the document
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// A description of the document
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl ConsentDocument {
+    pub fn builder() -> ConsentDocumentBuilder {
+        ConsentDocumentBuilder::default()
+    }
+
+    fn to_self_describing_json(&self) -> SelfDescribingJson {
+        SelfDescribingJson::new(CONSENT_DOCUMENT_SCHEMA, json!(self))
+    }
+}
+
+/// The schema of the [ConsentGrantedEvent]
+pub(crate) const CONSENT_GRANTED_SCHEMA: &str =
+    "iglu:com.snowplowanalytics.snowplow/consent_granted/jsonschema/1-0-0";
+
+/// Event to track a user granting consent to a document, such as a privacy policy.
+///
+/// It is a self-describing event with the schema "iglu:com.snowplowanalytics.snowplow/consent_granted/jsonschema/1-0-0".
+/// The document, along with any additional `documents`, is attached as a `consent_document` context entity.
+#[derive(Serialize, Deserialize, Builder, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+#[builder(setter(into, strip_option))]
+#[builder(build_fn(error = "Error"))]
+pub struct ConsentGrantedEvent {
+    /// The ID of the document consent is granted to
+    pub document_id: String,
+
+    /// The version of the document consent is granted to
+    pub version: String,
+
+    /// The name of the document
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// A description of the document
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// When the consent expires
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiry: Option<DateTime<Utc>>,
+
+    /// Additional documents consent is granted to
+    #[builder(default)]
+    pub documents: Vec<ConsentDocument>,
+
+    /// The [Subject] of the event, set with `.subject(subject)` on the builder.
+    ///
+    /// Its fields take priority over those of the tracker [Subject], for this event only.
+    #[builder(default)]
+    #[serde(skip_serializing)]
+    pub subject: Option<Subject>,
+
+    /// The true timestamp of the event
+    #[builder(default)]
+    #[serde(skip_serializing)]
+    pub true_tstamp: Option<DateTime<Utc>>,
+}
+
+impl ConsentGrantedEvent {
+    pub fn builder() -> ConsentGrantedEventBuilder {
+        ConsentGrantedEventBuilder::default()
+    }
+}
+
+impl PayloadAddable for ConsentGrantedEvent {
+    fn add_to_payload(self, payload_builder: PayloadBuilder) -> PayloadBuilder {
+        let data = match self.expiry {
+            Some(expiry) => json!({ "expiry": expiry }),
+            None => json!({}),
+        };
+
+        let document = ConsentDocument {
+            id: self.document_id,
+            version: self.version,
+            name: self.name,
+            description: self.description,
+        };
+        let event = SelfDescribingEvent {
+            schema: CONSENT_GRANTED_SCHEMA.to_string(),
+            data,
+            subject: self.subject,
+            true_tstamp: self.true_tstamp,
+        };
+
+        let mut payload_builder = event.add_to_payload(payload_builder);
+        for document in std::iter::once(&document).chain(self.documents.iter()) {
+            payload_builder.add_context(document.to_self_describing_json());
+        }
+        payload_builder
+    }
+
+    fn subject(&self) -> &Option<Subject> {
+        &self.subject
+    }
+
+    fn take_subject(&mut self) -> Option<Subject> {
+        self.subject.take()
+    }
+}
+
+/// The schema of the [ConsentWithdrawnEvent]
+pub(crate) const CONSENT_WITHDRAWN_SCHEMA: &str =
+    "iglu:com.snowplowanalytics.snowplow/consent_withdrawn/jsonschema/1-0-0";
+
+/// Event to track a user withdrawing consent to documents, or to all documents.
+///
+/// It is a self-describing event with the schema "iglu:com.snowplowanalytics.snowplow/consent_withdrawn/jsonschema/1-0-0".
+/// The `documents` are attached as `consent_document` context entities.
+#[derive(Serialize, Deserialize, Builder, Debug, Clone)]
+#[builder(setter(into, strip_option))]
+#[builder(build_fn(error = "Error"))]
+pub struct ConsentWithdrawnEvent {
+    /// Whether consent is withdrawn from all documents
+    #[builder(default)]
+    pub all: bool,
+
+    /// The documents consent is withdrawn from
+    #[builder(default)]
+    pub documents: Vec<ConsentDocument>,
+
+    /// The [Subject] of the event, set with `.subject(subject)` on the builder.
+    ///
+    /// Its fields take priority over those of the tracker [Subject], for this event only.
+    #[builder(default)]
+    #[serde(skip_serializing)]
+    pub subject: Option<Subject>,
+
+    /// The true timestamp of the event
+    #[builder(default)]
+    #[serde(skip_serializing)]
+    pub true_tstamp: Option<DateTime<Utc>>,
+}
+
+impl ConsentWithdrawnEvent {
+    pub fn builder() -> ConsentWithdrawnEventBuilder {
+        ConsentWithdrawnEventBuilder::default()
+    }
+}
+
+impl PayloadAddable for ConsentWithdrawnEvent {
+    fn add_to_payload(self, payload_builder: PayloadBuilder) -> PayloadBuilder {
+        let event = SelfDescribingEvent {
+            schema: CONSENT_WITHDRAWN_SCHEMA.to_string(),
+            data: json!({ "all": self.all }),
+            subject: self.subject,
+            true_tstamp: self.true_tstamp,
+        };
+
+        let mut payload_builder = event.add_to_payload(payload_builder);
+        for document in &self.documents {
+            payload_builder.add_context(document.to_self_describing_json());
+        }
+        payload_builder
+    }
+
+    fn subject(&self) -> &Option<Subject> {
+        &self.subject
+    }
+
+    fn take_subject(&mut self) -> Option<Subject> {
+        self.subject.take()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::payload::Payload;
+    use crate::payload::{ContextData, Payload};
 
     use super::*;
 
@@ -391,6 +880,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn builds_payload_for_link_click_event() {
+        let event = LinkClickEvent::builder()
+            .target_url("https://example.com/pricing")
+            .element_id("pricing-link")
+            .element_classes(vec!["nav".to_string(), "primary".to_string()])
+            .element_target("_blank")
+            .build()
+            .unwrap();
+
+        let payload = event.add_to_payload(payload_builder()).build().unwrap();
+        let ue_pr = payload.ue_pr.unwrap();
+        assert_eq!(ue_pr.data.schema, LINK_CLICK_SCHEMA);
+        assert_eq!(
+            ue_pr.data.data,
+            json!({
+                "targetUrl": "https://example.com/pricing",
+                "elementId": "pricing-link",
+                "elementClasses": ["nav", "primary"],
+                "elementTarget": "_blank"
+            })
+        );
+    }
+
+    #[test]
+    fn link_click_event_requires_valid_target_url() {
+        let result = LinkClickEvent::builder().target_url("not a url").build();
+        assert!(matches!(result, Err(Error::BuilderError(_))));
+
+        let result = LinkClickEvent::builder().element_id("link").build();
+        assert!(matches!(result, Err(Error::BuilderError(_))));
+    }
+
     #[test]
     fn builds_payload_for_timing_event() {
         let event = TimingEvent::builder()
@@ -424,6 +946,87 @@ mod tests {
         assert_eq!(data.data, expected.data);
     }
 
+    #[test]
+    fn true_timestamp_serializes_as_milliseconds_string() {
+        let true_tstamp = DateTime::from_timestamp_millis(1_672_531_200_123).unwrap();
+        let event = StructuredEvent::builder()
+            .category("shop")
+            .action("add-to-basket")
+            .true_tstamp(true_tstamp)
+            .build()
+            .unwrap();
+
+        let payload = event.add_to_payload(payload_builder()).build().unwrap();
+        let serialized = serde_json::to_value(payload).unwrap();
+
+        assert_eq!(serialized["ttm"], json!("1672531200123"));
+    }
+
+    #[test]
+    fn true_timestamp_omitted_when_not_set() {
+        let event = TimingEvent::builder()
+            .category("fetch_resource")
+            .variable("map_loaded")
+            .timing(1423)
+            .build()
+            .unwrap();
+
+        let payload = event.add_to_payload(payload_builder()).build().unwrap();
+        let serialized = serde_json::to_value(payload).unwrap();
+
+        assert!(serialized.get("ttm").is_none());
+    }
+
+    #[test]
+    fn structured_event_value_serializes_with_numeric_encoding() {
+        let event = StructuredEvent::builder()
+            .category("shop")
+            .action("add-to-basket")
+            .value(2.5)
+            .build()
+            .unwrap();
+
+        let as_string = serde_json::to_value(&event).unwrap();
+        let as_number =
+            serde_json::to_value(EncodedStructuredEvent(&event, NumericEncoding::Number)).unwrap();
+
+        assert_eq!(as_string["se_va"], json!("2.5"));
+        assert_eq!(as_number["se_va"], json!(2.5));
+        assert_eq!(as_string["se_ca"], as_number["se_ca"]);
+    }
+
+    #[test]
+    fn structured_event_value_serializes_as_shortest_string() {
+        let serialized_value = |value: Option<f64>| {
+            let mut builder = StructuredEvent::builder();
+            builder.category("shop").action("add-to-basket");
+            if let Some(value) = value {
+                builder.value(value);
+            }
+            let serialized = serde_json::to_value(builder.build().unwrap()).unwrap();
+            serialized.get("se_va").cloned()
+        };
+
+        assert_eq!(serialized_value(Some(-3.25)), Some(json!("-3.25")));
+        assert_eq!(serialized_value(Some(0.0)), Some(json!("0")));
+        assert_eq!(serialized_value(Some(12.0)), Some(json!("12")));
+        assert_eq!(serialized_value(Some(0.1)), Some(json!("0.1")));
+        assert_eq!(serialized_value(None), None);
+    }
+
+    #[test]
+    fn structured_event_value_must_be_finite() {
+        for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let result = StructuredEvent::builder()
+                .category("shop")
+                .action("add-to-basket")
+                .value(value)
+                .build();
+
+            assert!(matches!(result, Err(Error::BuilderError(_))));
+        }
+    }
+
     fn payload_builder() -> PayloadBuilder {
         Payload::builder()
             .p("platform".to_string())
@@ -445,4 +1048,124 @@ mod tests {
             .unwrap_err();
         assert_eq!(event.to_string(), "Field not initialized: action");
     }
+
+    #[test]
+    fn builds_payload_for_page_ping() {
+        let event = PagePingEvent::builder()
+            .min_x_offset(0)
+            .max_x_offset(120)
+            .min_y_offset(-10)
+            .max_y_offset(800)
+            .build()
+            .unwrap();
+
+        let payload = event.add_to_payload(payload_builder()).build().unwrap();
+        let payload = serde_json::to_value(payload).unwrap();
+
+        assert_eq!(payload["e"], "pp");
+        assert_eq!(payload["pp_mix"], "0");
+        assert_eq!(payload["pp_max"], "120");
+        assert_eq!(payload["pp_miy"], "-10");
+        assert_eq!(payload["pp_may"], "800");
+    }
+
+    #[test]
+    fn unset_page_ping_offsets_are_omitted() {
+        let event = PagePingEvent::builder().max_y_offset(400).build().unwrap();
+
+        let payload = event.add_to_payload(payload_builder()).build().unwrap();
+        let payload = serde_json::to_value(payload).unwrap();
+
+        assert_eq!(payload["e"], "pp");
+        assert_eq!(payload["pp_may"], "400");
+        for key in ["pp_mix", "pp_max", "pp_miy"] {
+            assert!(payload.get(key).is_none(), "{key} should be omitted");
+        }
+    }
+
+    fn consent_document(id: &str) -> ConsentDocument {
+        ConsentDocument::builder()
+            .id(id)
+            .version("1.0")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn builds_payload_for_consent_granted() {
+        let expiry = Utc::now();
+        let event = ConsentGrantedEvent::builder()
+            .document_id("privacy-policy")
+            .version("2.1")
+            .name("Privacy policy")
+            .expiry(expiry)
+            .documents(vec![consent_document("cookie-policy")])
+            .build()
+            .unwrap();
+
+        let payload = event.add_to_payload(payload_builder()).build().unwrap();
+
+        let ue_pr = payload.ue_pr.unwrap();
+        assert_eq!(ue_pr.data.schema, CONSENT_GRANTED_SCHEMA);
+        assert_eq!(ue_pr.data.data, json!({ "expiry": expiry }));
+
+        let contexts = payload.co.unwrap().data;
+        assert_eq!(contexts.len(), 2);
+        assert!(contexts
+            .iter()
+            .all(|context| context.schema == CONSENT_DOCUMENT_SCHEMA));
+        assert_eq!(
+            contexts[0].data,
+            json!({ "id": "privacy-policy", "version": "2.1", "name": "Privacy policy" })
+        );
+        assert_eq!(contexts[1].data["id"], "cookie-policy");
+    }
+
+    #[test]
+    fn builds_payload_for_consent_withdrawn() {
+        let event = ConsentWithdrawnEvent::builder()
+            .all(true)
+            .documents(vec![consent_document("privacy-policy")])
+            .build()
+            .unwrap();
+
+        let payload = event.add_to_payload(payload_builder()).build().unwrap();
+
+        let ue_pr = payload.ue_pr.unwrap();
+        assert_eq!(ue_pr.data.schema, CONSENT_WITHDRAWN_SCHEMA);
+        assert_eq!(ue_pr.data.data, json!({ "all": true }));
+
+        let contexts = payload.co.unwrap().data;
+        assert_eq!(contexts.len(), 1);
+        assert_eq!(contexts[0].schema, CONSENT_DOCUMENT_SCHEMA);
+        assert_eq!(contexts[0].data["id"], "privacy-policy");
+    }
+
+    #[test]
+    fn consent_documents_are_appended_to_existing_contexts() {
+        let tracked_context =
+            SelfDescribingJson::new("iglu:com.acme/ctx/jsonschema/1-0-0", json!({}));
+        let payload_builder = payload_builder().co(ContextData::new(vec![tracked_context]));
+
+        let event = ConsentWithdrawnEvent::builder()
+            .documents(vec![consent_document("privacy-policy")])
+            .build()
+            .unwrap();
+        let payload = event.add_to_payload(payload_builder).build().unwrap();
+
+        let schemas: Vec<String> = payload
+            .co
+            .unwrap()
+            .data
+            .into_iter()
+            .map(|context| context.schema)
+            .collect();
+        assert_eq!(
+            schemas,
+            vec![
+                "iglu:com.acme/ctx/jsonschema/1-0-0".to_string(),
+                CONSENT_DOCUMENT_SCHEMA.to_string()
+            ]
+        );
+    }
 }
@@ -11,12 +11,13 @@
 
 use std::time::Duration;
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use rand::Rng;
 use serde_json::json;
 use uuid::Uuid;
 
-use crate::{emitter::RetryPolicy, payload::Payload, Error, SelfDescribingJson};
+use crate::emitter::{Jitter, RetryPolicy};
+use crate::{payload::Payload, Error, SelfDescribingJson};
 
 const PAYLOAD_DATA_SCHEMA: &str =
     "iglu:com.snowplowanalytics.snowplow/payload_data/jsonschema/1-0-4";
@@ -48,6 +49,97 @@ impl EventBatch {
         }
     }
 
+    /// Splits the batch into batches sent in requests of at most `byte_limit` bytes.
+    ///
+    /// The batch is returned as-is if it is within the limit. Otherwise, each split batch keeps
+    /// the ID and retry state of this batch, so the event store is cleaned up with the ID it gave out.
+    /// An event too large to be sent within the limit on its own is put in a batch of its own.
+    pub fn split_by_size(self, byte_limit: usize) -> Vec<EventBatch> {
+        let event_sizes: Vec<usize> = self.events.iter().map(Self::event_size).collect();
+        if Self::request_size(&event_sizes) <= byte_limit {
+            return vec![self];
+        }
+
+        let (id, delay, retry_attempts) = (self.id, self.delay, self.retry_attempts);
+        let split_batch = |events: Vec<Payload>| EventBatch {
+            id,
+            events,
+            delay,
+            retry_attempts,
+        };
+
+        let mut batches = Vec::new();
+        let mut events = Vec::new();
+        let mut size = Self::request_size(&[]);
+        for (event, event_size) in self.events.into_iter().zip(event_sizes) {
+            // Events after the first are separated by a comma
+            if !events.is_empty() && size + event_size + 1 > byte_limit {
+                batches.push(split_batch(std::mem::take(&mut events)));
+                size = Self::request_size(&[]);
+            }
+
+            size += event_size + usize::from(!events.is_empty());
+            events.push(event);
+        }
+        if !events.is_empty() {
+            batches.push(split_batch(events));
+        }
+
+        log::debug!("Split batch {} into {} batches", self.id, batches.len());
+        batches
+    }
+
+    /// Splits the batch into batches whose events all have the same `key`, e.g. the same app ID.
+    ///
+    /// Batches are in the order their key first appears, with events keeping their order.
+    /// The batch is returned as-is if all its events have the same key. Otherwise, each split batch
+    /// keeps the ID and retry state of this batch, as with [EventBatch::split_by_size].
+    pub fn partition_by<K: PartialEq>(mut self, key: impl Fn(&Payload) -> K) -> Vec<EventBatch> {
+        let mut partitions: Vec<(K, Vec<Payload>)> = Vec::new();
+        for event in std::mem::take(&mut self.events) {
+            let event_key = key(&event);
+            match partitions.iter_mut().find(|(key, _)| *key == event_key) {
+                Some((_, events)) => events.push(event),
+                None => partitions.push((event_key, vec![event])),
+            }
+        }
+
+        if partitions.len() <= 1 {
+            self.events = partitions
+                .pop()
+                .map(|(_, events)| events)
+                .unwrap_or_default();
+            return vec![self];
+        }
+
+        log::debug!(
+            "Partitioned batch {} into {} batches",
+            self.id,
+            partitions.len()
+        );
+        partitions
+            .into_iter()
+            .map(|(_, events)| EventBatch {
+                id: self.id,
+                events,
+                delay: self.delay,
+                retry_attempts: self.retry_attempts,
+            })
+            .collect()
+    }
+
+    /// The size in bytes of a request sending events of the given serialized sizes.
+    pub(crate) fn request_size(event_sizes: &[usize]) -> usize {
+        // The events are sent as `{"data":[...],"schema":"..."}`, separated by commas
+        let envelope = r#"{"data":[],"schema":""}"#.len() + PAYLOAD_DATA_SCHEMA.len();
+        envelope + event_sizes.iter().sum::<usize>() + event_sizes.len().saturating_sub(1)
+    }
+
+    /// The size in bytes of the serialized event.
+    pub(crate) fn event_size(event: &Payload) -> usize {
+        serde_json::to_vec(event).map_or(0, |bytes| bytes.len())
+    }
+
     /// Whether the batch has any retries remaining.
     pub fn has_retry(&self, retry_policy: RetryPolicy) -> bool {
         match retry_policy {
@@ -59,28 +151,37 @@ impl EventBatch {
 
     /// Updates the events `stm` field in batch with the current time.
     pub fn update_event_stm(&mut self) -> Result<(), Error> {
+        self.set_event_stm(Utc::now());
+        Ok(())
+    }
+
+    /// Sets the events `stm` field in batch to the given time.
+    pub(crate) fn set_event_stm(&mut self, stm: DateTime<Utc>) {
         for event in self.events.iter_mut() {
-            event.stm = Utc::now();
+            event.stm = stm;
         }
-
-        Ok(())
     }
 
     /// Updates the delay until another sending attempt is made.
     pub fn update_for_retry(&mut self) {
+        self.update_for_retry_with(Jitter::default(), &mut rand::thread_rng());
+    }
+
+    /// Updates the delay until another sending attempt is made, randomized within the backoff window by `jitter`.
+    pub(crate) fn update_for_retry_with(&mut self, jitter: Jitter, rng: &mut impl Rng) {
         let max_event_delay_time = Duration::from_secs(600_000);
 
         self.retry_attempts += 1;
 
-        self.delay = match self.delay {
-            Some(delay) => {
-                // 2 +- random number between 0 and 1
-                let delay_mul = rand::thread_rng().gen_range(1.0..=3.0);
+        // The backoff window doubles with each attempt, starting at 1 second
+        let backoff = 2_u32
+            .checked_pow(self.retry_attempts - 1)
+            .and_then(|factor| Duration::from_secs(1).checked_mul(factor))
+            .map_or(max_event_delay_time, |backoff| {
+                backoff.min(max_event_delay_time)
+            });
 
-                Some(delay.mul_f32(delay_mul).min(max_event_delay_time))
-            }
-            None => Some(Duration::from_secs(1)),
-        }
+        self.delay = Some(jitter.apply(backoff, rng));
     }
 }
 
@@ -91,7 +192,7 @@ mod tests {
     use chrono::Utc;
     use uuid::Uuid;
 
-    use crate::emitter::RetryPolicy;
+    use crate::emitter::{Jitter, RetryPolicy};
     use crate::PayloadBuilder;
     use crate::{event_batch::EventBatch, payload::Payload};
 
@@ -147,6 +248,25 @@ mod tests {
         assert!(batch.delay.unwrap() > Duration::from_secs(0));
     }
 
+    #[test]
+    fn retry_delay_is_jittered_within_backoff_window() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut batch = EventBatch::new(Uuid::new_v4(), vec![]);
+        let mut rng = StdRng::seed_from_u64(1);
+
+        // The third attempt has a backoff window of 4 seconds
+        for _ in 0..3 {
+            batch.update_for_retry_with(Jitter::Equal, &mut rng);
+        }
+
+        let delay = batch.delay.unwrap();
+        assert!(delay >= Duration::from_secs(2) && delay <= Duration::from_secs(4));
+
+        batch.update_for_retry_with(Jitter::None, &mut rng);
+        assert_eq!(batch.delay, Some(Duration::from_secs(8)));
+    }
+
     #[test]
     fn no_retry_policy() {
         let batch = EventBatch::new(
@@ -179,4 +299,81 @@ mod tests {
 
         assert!(!batch.has_retry(policy));
     }
+
+    #[test]
+    fn split_by_size_keeps_requests_within_limit() {
+        let events: Vec<Payload> = create_payloads(10)
+            .into_iter()
+            .map(|p| p.aid("a".repeat(1000)).finalise_payload().unwrap())
+            .collect();
+        let event_ids: Vec<_> = events.iter().map(|e| e.eid).collect();
+        let batch_id = Uuid::new_v4();
+        let batch = EventBatch::new(batch_id, events);
+
+        let batches = batch.split_by_size(3000);
+
+        assert!(batches.len() > 1);
+        for batch in batches.iter() {
+            let body = serde_json::to_vec(&batch.as_payload()).unwrap();
+            assert!(body.len() <= 3000);
+            assert_eq!(batch.id, batch_id);
+        }
+        let split_event_ids: Vec<_> = batches
+            .iter()
+            .flat_map(|batch| batch.events.iter().map(|e| e.eid))
+            .collect();
+        assert_eq!(split_event_ids, event_ids);
+    }
+
+    #[test]
+    fn partition_by_groups_events_with_the_same_key() {
+        let events: Vec<Payload> = ["a", "b", "a", "c", "b"]
+            .into_iter()
+            .zip(create_payloads(5))
+            .map(|(aid, p)| p.aid(aid.to_string()).finalise_payload().unwrap())
+            .collect();
+        let batch_id = Uuid::new_v4();
+        let batch = EventBatch::new(batch_id, events);
+
+        let batches = batch.partition_by(|event| event.app_id().to_string());
+
+        let app_ids: Vec<Vec<&str>> = batches
+            .iter()
+            .map(|batch| batch.events.iter().map(|e| e.app_id()).collect())
+            .collect();
+        assert_eq!(app_ids, vec![vec!["a", "a"], vec!["b", "b"], vec!["c"]]);
+        for batch in batches.iter() {
+            assert_eq!(batch.id, batch_id);
+        }
+    }
+
+    #[test]
+    fn split_by_size_keeps_batch_within_limit() {
+        let events: Vec<Payload> = create_payloads(3)
+            .into_iter()
+            .map(|p| p.finalise_payload().unwrap())
+            .collect();
+        let batch_id = Uuid::new_v4();
+        let batch = EventBatch::new(batch_id, events);
+
+        let batches = batch.split_by_size(1_000_000);
+
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].id, batch_id);
+        assert_eq!(batches[0].events.len(), 3);
+    }
+
+    #[test]
+    fn request_size_matches_serialized_batch() {
+        let events: Vec<Payload> = create_payloads(4)
+            .into_iter()
+            .map(|p| p.finalise_payload().unwrap())
+            .collect();
+        let sizes: Vec<_> = events.iter().map(EventBatch::event_size).collect();
+        let batch = EventBatch::new(Uuid::new_v4(), events);
+
+        let body = serde_json::to_vec(&batch.as_payload()).unwrap();
+
+        assert_eq!(EventBatch::request_size(&sizes), body.len());
+    }
 }
@@ -33,5 +33,6 @@ pub trait EventStore {
     /// Removes and returns the provided number of events from the EventStore as an [EventBatch]
     fn batch_of(&mut self, size: usize) -> Result<EventBatch, Error>;
     // A method to be called after attempts to send are finished, either successfully or unsuccessfully
+    // A batch sent in several requests keeps its ID, so this is called with it once per request
     fn cleanup_after_send_attempt(&mut self, batch_id: Uuid) -> Result<(), Error>;
 }
@@ -14,4 +14,5 @@ mod in_memory_event_store;
 
 pub use event_store::EventStore;
 pub use in_memory_event_store::InMemoryEventStore;
+#[cfg(not(target_arch = "wasm32"))]
 pub(crate) use in_memory_event_store::DEFAULT_EVENT_STORE_CAPACITY;
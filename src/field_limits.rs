@@ -0,0 +1,158 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::payload::PayloadBuilder;
+
+// A callback reporting a truncated field, with its protocol key, original length, and limit
+type TruncationCallback = Arc<dyn Fn(&str, usize, usize) + Send + Sync>;
+
+/// Maximum lengths of event fields, with longer values truncated before the event is sent
+///
+/// Fields are identified by their key in the [tracker protocol](https://docs.snowplow.io/docs/collecting-data/collecting-from-own-applications/snowplow-tracker-protocol),
+/// e.g. `se_la` for the label of a [StructuredEvent](crate::StructuredEvent), or `uid` for the user ID of a [Subject](crate::Subject).
+/// Lengths are in characters.
+///
+/// ## Example
+/// ```
+/// use snowplow_tracker::FieldLimits;
+///
+/// let field_limits = FieldLimits::new()
+///     .limit("se_la", 100)
+///     .on_truncate(|field, length, limit| println!("{field} truncated from {length} to {limit}"));
+/// ```
+#[derive(Clone, Default)]
+pub struct FieldLimits {
+    limits: HashMap<String, usize>,
+    on_truncate: Option<TruncationCallback>,
+}
+
+impl FieldLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum length of a field
+    pub fn limit(mut self, field: &str, max_length: usize) -> Self {
+        self.limits.insert(field.to_string(), max_length);
+        self
+    }
+
+    /// Set a callback called whenever a field is truncated, with the field's key, original length, and limit
+    pub fn on_truncate(
+        mut self,
+        on_truncate: impl Fn(&str, usize, usize) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_truncate = Some(Arc::new(on_truncate));
+        self
+    }
+
+    /// Truncates the fields of the structured event and subject of a payload to their limits
+    pub(crate) fn apply(&self, payload_builder: &mut PayloadBuilder) {
+        if let Some(Some(event)) = payload_builder.structured_event.as_mut() {
+            self.truncate("se_ca", &mut event.category);
+            self.truncate("se_ac", &mut event.action);
+            if let Some(property) = event.property.as_mut() {
+                self.truncate("se_pr", property);
+            }
+            if let Some(label) = event.label.as_mut() {
+                self.truncate("se_la", label);
+            }
+        }
+
+        if let Some(Some(subject)) = payload_builder.subject.as_mut() {
+            let fields = [
+                ("uid", &mut subject.user_id),
+                ("tz", &mut subject.timezone),
+                ("lang", &mut subject.language),
+                ("ip", &mut subject.ip_address),
+                ("ua", &mut subject.user_agent),
+            ];
+            for (field, value) in fields {
+                if let Some(value) = value.as_mut() {
+                    self.truncate(field, value);
+                }
+            }
+        }
+    }
+
+    fn truncate(&self, field: &str, value: &mut String) {
+        let limit = match self.limits.get(field) {
+            Some(limit) => *limit,
+            None => return,
+        };
+
+        let length = value.chars().count();
+        if length <= limit {
+            return;
+        }
+
+        // Truncate on a character boundary, so multi-byte characters aren't split
+        if let Some((index, _)) = value.char_indices().nth(limit) {
+            value.truncate(index);
+        }
+
+        log::debug!("Truncated {field} from {length} to {limit} characters");
+        if let Some(on_truncate) = &self.on_truncate {
+            on_truncate(field, length, limit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use crate::payload::Payload;
+    use crate::{StructuredEvent, Subject};
+
+    use super::*;
+
+    #[test]
+    fn truncates_fields_to_their_limits() {
+        let truncated = Arc::new(Mutex::new(Vec::new()));
+        let truncated_fields = truncated.clone();
+        let field_limits = FieldLimits::new()
+            .limit("se_la", 5)
+            .limit("se_ac", 100)
+            .limit("uid", 3)
+            .on_truncate(move |field, length, limit| {
+                truncated_fields
+                    .lock()
+                    .unwrap()
+                    .push((field.to_string(), length, limit))
+            });
+
+        let event = StructuredEvent::builder()
+            .category("shop")
+            .action("add-to-basket")
+            .label("a very long label")
+            .build()
+            .unwrap();
+        let mut payload_builder = Payload::builder()
+            .structured_event(event)
+            .subject(Subject::builder().user_id("üsér_1").build().unwrap());
+
+        field_limits.apply(&mut payload_builder);
+
+        let event = payload_builder.structured_event.unwrap().unwrap();
+        let subject = payload_builder.subject.unwrap().unwrap();
+        assert_eq!(event.label.unwrap(), "a ver");
+        assert_eq!(event.action, "add-to-basket");
+        assert_eq!(subject.user_id.unwrap(), "üsé");
+        assert_eq!(
+            *truncated.lock().unwrap(),
+            vec![("se_la".to_string(), 17, 5), ("uid".to_string(), 6, 3)]
+        );
+    }
+}
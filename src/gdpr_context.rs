@@ -0,0 +1,135 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use derive_builder::Builder;
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::payload::SelfDescribingJson;
+
+/// The schema of the GDPR context entity
+pub(crate) const GDPR_SCHEMA: &str = "iglu:com.snowplowanalytics.snowplow/gdpr/jsonschema/1-0-0";
+
+// The legal bases for processing personal data under GDPR, as allowed by the schema
+const BASES_FOR_PROCESSING: [&str; 6] = [
+    "consent",
+    "contract",
+    "legal_obligation",
+    "vital_interests",
+    "public_task",
+    "legitimate_interests",
+];
+
+/// A context entity describing the legal basis for processing personal data under GDPR
+///
+/// Attach it to every event with [Tracker::enable_gdpr_context](crate::Tracker::enable_gdpr_context).
+///
+/// ## Example
+/// ```
+/// use snowplow_tracker::GdprContext;
+///
+/// let gdpr_context = GdprContext::builder()
+///     .basis_for_processing("consent")
+///     .document_id("privacy-policy")
+///     .document_version("2.1")
+///     .build()
+///     .unwrap();
+///
+/// assert!(GdprContext::builder().basis_for_processing("because").build().is_err());
+/// ```
+#[derive(Serialize, Builder, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[builder(setter(into, strip_option))]
+#[builder(build_fn(validate = "Self::validate", error = "Error"))]
+pub struct GdprContext {
+    /// The legal basis for processing personal data.
+    ///
+    /// One of `consent`, `contract`, `legal_obligation`, `vital_interests`, `public_task`, or `legitimate_interests`.
+    pub basis_for_processing: String,
+
+    /// The ID of the document describing the basis, such as a privacy policy.
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_id: Option<String>,
+
+    /// The version of the document.
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_version: Option<String>,
+
+    /// A description of the document.
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_description: Option<String>,
+}
+
+impl GdprContext {
+    pub fn builder() -> GdprContextBuilder {
+        GdprContextBuilder::default()
+    }
+
+    pub(crate) fn to_self_describing_json(&self) -> SelfDescribingJson {
+        // Serializing a struct of strings can't fail
+        SelfDescribingJson::new(GDPR_SCHEMA, serde_json::to_value(self).unwrap_or_default())
+    }
+}
+
+impl GdprContextBuilder {
+    fn validate(&self) -> Result<(), Error> {
+        match &self.basis_for_processing {
+            Some(basis) if !BASES_FOR_PROCESSING.contains(&basis.as_str()) => {
+                Err(Error::BuilderError(format!(
+                    "Invalid basis for processing: {basis}, expected one of {}",
+                    BASES_FOR_PROCESSING.join(", ")
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_to_gdpr_entity() {
+        let gdpr_context = GdprContext::builder()
+            .basis_for_processing("legitimate_interests")
+            .document_id("policy")
+            .build()
+            .unwrap();
+
+        let entity = gdpr_context.to_self_describing_json();
+
+        assert_eq!(entity.schema, GDPR_SCHEMA);
+        assert_eq!(entity.data["basisForProcessing"], "legitimate_interests");
+        assert_eq!(entity.data["documentId"], "policy");
+        assert!(entity.data.get("documentVersion").is_none());
+    }
+
+    #[test]
+    fn invalid_basis_for_processing_errors() {
+        let result = GdprContext::builder()
+            .basis_for_processing("marketing")
+            .build();
+
+        assert!(matches!(result, Err(Error::BuilderError(_))));
+    }
+
+    #[test]
+    fn basis_for_processing_is_required() {
+        assert!(GdprContext::builder()
+            .document_id("policy")
+            .build()
+            .is_err());
+    }
+}
@@ -0,0 +1,146 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use derive_builder::Builder;
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::payload::SelfDescribingJson;
+
+/// The schema of the geolocation context entity
+pub(crate) const GEOLOCATION_CONTEXT_SCHEMA: &str =
+    "iglu:com.snowplowanalytics.snowplow/geolocation_context/jsonschema/1-1-0";
+
+/// A context entity describing the location of the device
+///
+/// Attach it to every event with [Tracker::set_geolocation_context](crate::Tracker::set_geolocation_context).
+///
+/// ## Example
+/// ```
+/// use snowplow_tracker::GeolocationContext;
+///
+/// let geolocation_context = GeolocationContext::builder()
+///     .latitude(59.33)
+///     .longitude(18.07)
+///     .build()
+///     .unwrap();
+///
+/// assert!(GeolocationContext::builder().latitude(91.0).longitude(0.0).build().is_err());
+/// ```
+#[derive(Serialize, Builder, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[builder(setter(into, strip_option))]
+#[builder(build_fn(validate = "Self::validate", error = "Error"))]
+pub struct GeolocationContext {
+    /// The latitude of the device, between -90 and 90 degrees.
+    pub latitude: f64,
+
+    /// The longitude of the device, between -180 and 180 degrees.
+    pub longitude: f64,
+
+    /// The accuracy of the latitude and longitude, in meters.
+    #[builder(default)]
+    #[serde(rename = "latitudeLongitudeAccuracy")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accuracy: Option<f64>,
+
+    /// The altitude of the device, in meters.
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub altitude: Option<f64>,
+
+    /// The accuracy of the altitude, in meters.
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub altitude_accuracy: Option<f64>,
+
+    /// The direction the device is moving in, in degrees clockwise from true north.
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bearing: Option<f64>,
+
+    /// The speed of the device, in meters per second.
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f64>,
+}
+
+impl GeolocationContext {
+    pub fn builder() -> GeolocationContextBuilder {
+        GeolocationContextBuilder::default()
+    }
+
+    pub(crate) fn to_self_describing_json(&self) -> SelfDescribingJson {
+        // Serializing a struct of numbers can't fail
+        SelfDescribingJson::new(
+            GEOLOCATION_CONTEXT_SCHEMA,
+            serde_json::to_value(self).unwrap_or_default(),
+        )
+    }
+}
+
+impl GeolocationContextBuilder {
+    fn validate(&self) -> Result<(), Error> {
+        if let Some(latitude) = self.latitude {
+            if !(-90.0..=90.0).contains(&latitude) {
+                return Err(Error::BuilderError(format!(
+                    "Invalid latitude: {latitude}, expected between -90 and 90"
+                )));
+            }
+        }
+
+        if let Some(longitude) = self.longitude {
+            if !(-180.0..=180.0).contains(&longitude) {
+                return Err(Error::BuilderError(format!(
+                    "Invalid longitude: {longitude}, expected between -180 and 180"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn serializes_to_geolocation_entity() {
+        let geolocation_context = GeolocationContext::builder()
+            .latitude(59.33)
+            .longitude(18.07)
+            .accuracy(12.5)
+            .build()
+            .unwrap();
+
+        let entity = geolocation_context.to_self_describing_json();
+
+        assert_eq!(entity.schema, GEOLOCATION_CONTEXT_SCHEMA);
+        assert_eq!(
+            entity.data,
+            json!({ "latitude": 59.33, "longitude": 18.07, "latitudeLongitudeAccuracy": 12.5 })
+        );
+    }
+
+    #[test]
+    fn out_of_range_coordinates_error() {
+        for (latitude, longitude) in [(-90.5, 0.0), (0.0, 180.5)] {
+            let result = GeolocationContext::builder()
+                .latitude(latitude)
+                .longitude(longitude)
+                .build();
+            assert!(matches!(result, Err(Error::BuilderError(_))));
+        }
+    }
+}
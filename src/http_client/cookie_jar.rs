@@ -0,0 +1,109 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use std::sync::{Arc, Mutex};
+
+use uuid::Uuid;
+
+// The name of the cookie the Snowplow collector stores the network user ID in
+const DEFAULT_COOKIE_NAME: &str = "sp";
+
+/// Keeps the network user ID the collector sets in a cookie, so it can be attached to later events
+///
+/// The cookie is captured from the `Set-Cookie` header of collector responses, and sent back with
+/// later requests, so the collector keeps the same network user ID. The jar is shared between its clones.
+#[derive(Clone, Debug)]
+pub struct CookieJar {
+    cookie_name: String,
+    network_user_id: Arc<Mutex<Option<Uuid>>>,
+}
+
+impl Default for CookieJar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CookieJar {
+    /// Create an empty jar, capturing the collector's `sp` cookie
+    pub fn new() -> Self {
+        Self {
+            cookie_name: DEFAULT_COOKIE_NAME.to_string(),
+            network_user_id: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Capture the cookie with the given name, for collectors configured with a custom cookie name
+    pub fn cookie_name(mut self, cookie_name: &str) -> Self {
+        self.cookie_name = cookie_name.to_string();
+        self
+    }
+
+    /// The network user ID set by the collector, once a response has set the cookie
+    pub fn network_user_id(&self) -> Option<Uuid> {
+        match self.network_user_id.lock() {
+            Ok(network_user_id) => *network_user_id,
+            Err(e) => {
+                log::warn!("Failed to read network user ID: {e}");
+                None
+            }
+        }
+    }
+
+    /// The `Cookie` header value sending the cookie back to the collector, once it has been set
+    pub(crate) fn cookie_header(&self) -> Option<String> {
+        self.network_user_id()
+            .map(|network_user_id| format!("{}={network_user_id}", self.cookie_name))
+    }
+
+    /// Store the network user ID from a `Set-Cookie` header value, if it sets the cookie
+    pub(crate) fn capture(&self, set_cookie: &str) {
+        // The cookie's attributes, such as its expiry, follow the first `;`
+        let cookie = set_cookie.split(';').next().unwrap_or_default();
+        let Some((name, value)) = cookie.split_once('=') else {
+            return;
+        };
+        if name.trim() != self.cookie_name {
+            return;
+        }
+
+        match Uuid::parse_str(value.trim()) {
+            Ok(network_user_id) => match self.network_user_id.lock() {
+                Ok(mut stored) => *stored = Some(network_user_id),
+                Err(e) => log::warn!("Failed to store network user ID: {e}"),
+            },
+            Err(e) => log::warn!("Collector set an invalid network user ID cookie: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captures_only_the_named_cookie() {
+        let jar = CookieJar::new();
+        let network_user_id = Uuid::new_v4();
+
+        jar.capture("other=value; Path=/");
+        assert_eq!(jar.network_user_id(), None);
+
+        jar.capture(&format!(
+            "sp={network_user_id}; Expires=Thu, 01 Jan 2099 00:00:00 GMT; Path=/"
+        ));
+        assert_eq!(jar.network_user_id(), Some(network_user_id));
+        assert_eq!(jar.cookie_header(), Some(format!("sp={network_user_id}")));
+
+        // Shared between clones
+        assert_eq!(jar.clone().network_user_id(), Some(network_user_id));
+    }
+}
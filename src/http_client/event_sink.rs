@@ -0,0 +1,138 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
+use serde_json::Value;
+
+use crate::{Error, HttpClient, ReqwestClient, SelfDescribingJson};
+
+const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// A [HttpClient] implementation sending events to a generic HTTP endpoint as newline-delimited JSON,
+/// rather than to a Snowplow Collector.
+///
+/// Each batch is POSTed with one event payload per line, without the `payload_data` envelope,
+/// e.g. for direct-to-lake ingestion via a logging endpoint.
+///
+/// ## Example
+/// ```
+/// use snowplow_tracker::{BatchEmitter, Emitter, EventSink};
+///
+/// let mut emitter = BatchEmitter::builder()
+///     .collector_url("https://logs.example.com/events")
+///     .http_client(EventSink::new("https://logs.example.com/events"))
+///     .build()
+///     .unwrap();
+///
+/// // Close the emitter thread
+/// emitter.close().unwrap();
+/// ```
+pub struct EventSink {
+    pub client: reqwest::Client,
+    /// The URL events are POSTed to
+    pub endpoint: String,
+}
+
+impl EventSink {
+    pub fn new(endpoint: &str) -> EventSink {
+        EventSink {
+            client: Client::new(),
+            endpoint: endpoint.to_string(),
+        }
+    }
+
+    // Builds the POST request sending the events in the payload as NDJSON
+    fn post_request(&self, payload: &SelfDescribingJson) -> Result<RequestBuilder, Error> {
+        let events = match &payload.data {
+            Value::Array(events) => events,
+            _ => {
+                return Err(Error::EmitterError(
+                    "Payload data is not an array of events".to_string(),
+                ))
+            }
+        };
+
+        let mut body = String::new();
+        for event in events {
+            body.push_str(&serde_json::to_string(event)?);
+            body.push('\n');
+        }
+
+        Ok(self
+            .client
+            .post(&self.endpoint)
+            .header(reqwest::header::CONTENT_TYPE, NDJSON_CONTENT_TYPE)
+            .body(body))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl HttpClient for EventSink {
+    async fn post(&self, payload: SelfDescribingJson) -> Result<u16, Error> {
+        ReqwestClient::handle_response(self.post_request(&payload)?.send().await?).await
+    }
+
+    fn clone(&self) -> Box<dyn HttpClient + Send + Sync> {
+        Box::new(EventSink {
+            client: self.client.clone(),
+            endpoint: self.endpoint.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use crate::event_batch::EventBatch;
+    use crate::http_client::mock_collector::mock_collector;
+    use crate::payload::Payload;
+
+    use super::*;
+
+    fn event(aid: &str) -> Payload {
+        Payload::builder()
+            .p("pc".to_string())
+            .tv("tv".to_string())
+            .eid(Uuid::new_v4())
+            .dtm(Utc::now())
+            .aid(aid.to_string())
+            .finalise_payload()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn posts_one_line_per_event() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let sink = EventSink::new(&format!("{url}/logs"));
+        let batch = EventBatch::new(Uuid::new_v4(), vec![event("first"), event("second")]);
+
+        let status = sink.post(batch.as_payload()).await.unwrap();
+
+        assert_eq!(status, 200);
+        let request = requests.try_recv().unwrap();
+        assert_eq!(request.path, "/logs");
+        assert_eq!(request.headers["content-type"], NDJSON_CONTENT_TYPE);
+
+        let lines: Vec<Value> = request
+            .body
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["aid"], "first");
+        assert_eq!(lines[1]["aid"], "second");
+        assert!(lines[0].get("schema").is_none());
+    }
+}
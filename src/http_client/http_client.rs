@@ -9,20 +9,106 @@
 // "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
 
+use std::collections::HashMap;
+
 use async_trait::async_trait;
 
-use crate::payload::SelfDescribingJson;
+use crate::emitter::{CollectorVendor, EmitFormat, ProtocolVersion};
+use crate::http_client::{BeforeSend, CookieJar, HttpResponse, RequestSigner};
+use crate::payload::{Payload, SelfDescribingJson};
 use crate::Error;
 
 /// A HttpClient is responsible for sending events to the collector.
 ///
 /// This is an async trait, using the [async_trait crate](https://crates.io/crates/async-trait).
+/// On `wasm32` targets, its futures don't need to be `Send`, as requests are made with the browser's `fetch`.
 ///
 /// Implement this trait to use your own HttpClient implementation on an [Emitter](crate::Emitter).
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 pub trait HttpClient {
     /// Send a [SelfDescribingJson] to the collector via POST
+    ///
+    /// Returns the status code of the response. Implementations may instead return
+    /// [Error::CollectorResponse] for non-2xx responses, to include the response body.
     async fn post(&self, payload: SelfDescribingJson) -> Result<u16, Error>;
+    /// Send a [SelfDescribingJson] to the collector via POST, with additional request headers
+    ///
+    /// By default, the headers are ignored and the payload is sent using [HttpClient::post]
+    async fn post_with_headers(
+        &self,
+        payload: SelfDescribingJson,
+        _headers: &HashMap<String, String>,
+    ) -> Result<u16, Error> {
+        self.post(payload).await
+    }
+    /// Send a [SelfDescribingJson] to the collector via POST, with additional request headers,
+    /// returning the status code and body of a successful response
+    ///
+    /// By default, the body is empty and the payload is sent using [HttpClient::post_with_headers]
+    async fn post_with_response(
+        &self,
+        payload: SelfDescribingJson,
+        headers: &HashMap<String, String>,
+    ) -> Result<HttpResponse, Error> {
+        let status = self.post_with_headers(payload, headers).await?;
+        Ok(HttpResponse {
+            status,
+            body: String::new(),
+        })
+    }
+    /// Send a single [Payload] to the collector via GET, with the event as query parameters
+    ///
+    /// By default, GET requests are not supported and an error is returned
+    async fn get(
+        &self,
+        _payload: &Payload,
+        _headers: &HashMap<String, String>,
+    ) -> Result<u16, Error> {
+        Err(Error::EmitterError(
+            "GET requests are not supported by this HttpClient".to_string(),
+        ))
+    }
+    /// Send a GET request to the health endpoint of the collector at `path`, returning the status code
+    ///
+    /// By default, health checks are not supported and an error is returned
+    async fn health_check(&self, _path: &str) -> Result<u16, Error> {
+        Err(Error::EmitterError(
+            "Health checks are not supported by this HttpClient".to_string(),
+        ))
+    }
+    /// Set a hook called with the parts of every request before it is sent
+    ///
+    /// By default, the hook is ignored
+    fn set_before_send(&mut self, _before_send: BeforeSend) {}
+    /// Set whether the body of POST requests is compressed with gzip
+    ///
+    /// By default, bodies are never compressed
+    fn set_compression(&mut self, _compress: bool) {}
+    /// Set a signer adding an HMAC signature of the body of each request as a header
+    ///
+    /// By default, requests are not signed
+    fn set_signer(&mut self, _signer: RequestSigner) {}
+    /// Set the vendor path segment of the endpoint events are POSTed to
+    ///
+    /// By default, the vendor is ignored
+    fn set_vendor(&mut self, _vendor: &CollectorVendor) {}
+    /// Set the protocol version path segment of the endpoint events are POSTed to
+    ///
+    /// By default, the version is ignored
+    fn set_protocol_version(&mut self, _protocol_version: &ProtocolVersion) {}
+    /// Set the shape of the body of POST requests
+    ///
+    /// By default, the format is ignored and bodies are sent as [EmitFormat::Tp2Envelope]
+    fn set_emit_format(&mut self, _emit_format: EmitFormat) {}
+    /// Set the URL of the collector events are sent to, replacing the one the client was created with
+    ///
+    /// This is used to fail over to fallback collectors. By default, the URL is ignored
+    fn set_collector_url(&mut self, _collector_url: &str) {}
+    /// Set a jar capturing the network user ID cookie set by the collector, and sending it back with later requests
+    ///
+    /// By default, cookies are ignored
+    fn set_cookie_jar(&mut self, _cookie_jar: CookieJar) {}
     /// Duplicate the HttpClient
     fn clone(&self) -> Box<dyn HttpClient + Send + Sync>;
 }
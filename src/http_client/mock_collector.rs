@@ -0,0 +1,117 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+//! A minimal HTTP server standing in for a collector in tests
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver};
+
+/// A request received by the mock collector
+#[derive(Debug)]
+pub(crate) struct MockRequest {
+    pub method: String,
+    /// The path of the request, including any query string
+    pub path: String,
+    /// Header names are lowercase
+    pub headers: HashMap<String, String>,
+    pub body: String,
+    /// The body as received, e.g. when it is compressed
+    pub raw_body: Vec<u8>,
+}
+
+/// Starts a mock collector answering each request with the status code and body returned by `respond`
+///
+/// Returns the URL of the collector and a receiver of every request it answers.
+pub(crate) fn mock_collector(
+    respond: impl Fn(&MockRequest) -> (u16, String) + Send + 'static,
+) -> (String, Receiver<MockRequest>) {
+    mock_collector_with_headers(move |request| {
+        let (status, body) = respond(request);
+        (status, Vec::new(), body)
+    })
+}
+
+/// Starts a mock collector answering each request with the status code, headers and body returned by `respond`
+pub(crate) fn mock_collector_with_headers(
+    respond: impl Fn(&MockRequest) -> (u16, Vec<(String, String)>, String) + Send + 'static,
+) -> (String, Receiver<MockRequest>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { break };
+            let Some(request) = read_request(&stream) else {
+                continue;
+            };
+
+            let (status, headers, body) = respond(&request);
+            let headers: String = headers
+                .iter()
+                .map(|(name, value)| format!("{name}: {value}\r\n"))
+                .collect();
+            let response = format!(
+                "HTTP/1.1 {status} Mock\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+
+            // Record the request before responding, so it's received once the client has a response
+            if tx.send(request).is_err() {
+                break;
+            }
+
+            stream.write_all(response.as_bytes()).ok();
+        }
+    });
+
+    (url, rx)
+}
+
+fn read_request(stream: &TcpStream) -> Option<MockRequest> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).ok()?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let content_length = headers
+        .get("content-length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).ok()?;
+
+    Some(MockRequest {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+        raw_body: body,
+    })
+}
@@ -9,8 +9,22 @@
 // "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
 
+mod cookie_jar;
+mod event_sink;
 mod http_client;
+mod http_response;
+#[cfg(test)]
+pub(crate) mod mock_collector;
+mod request_parts;
+mod request_signer;
 mod reqwest_client;
 
+pub use cookie_jar::CookieJar;
+pub use event_sink::EventSink;
 pub use http_client::HttpClient;
+pub use http_response::HttpResponse;
+pub use request_parts::{BeforeSend, RequestParts};
+pub use request_signer::RequestSigner;
 pub use reqwest_client::ReqwestClient;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use reqwest_client::DEFAULT_USER_AGENT;
@@ -0,0 +1,29 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A hook called with each request before it is sent, set on the builder returned by [BatchEmitter::builder](crate::BatchEmitter::builder)
+pub type BeforeSend = Arc<dyn Fn(&mut RequestParts) + Send + Sync>;
+
+/// The parts of a HTTP request to the collector, which can be inspected or changed before it is sent
+#[derive(Clone, Debug)]
+pub struct RequestParts {
+    /// The HTTP method, e.g. `POST`
+    pub method: String,
+    /// The full URL, including any query parameters
+    pub url: String,
+    /// The request headers
+    pub headers: HashMap<String, String>,
+    /// The request body, if any
+    pub body: Option<String>,
+}
@@ -9,43 +9,493 @@
 // "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
 
+use std::collections::HashMap;
+use std::io::Write;
+
 use async_trait::async_trait;
-use reqwest::Client;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use reqwest::{Client, Method, RequestBuilder, Url};
+
+use crate::emitter::{CollectorVendor, EmitFormat, ProtocolVersion};
+use crate::http_client::{BeforeSend, CookieJar, HttpResponse, RequestParts, RequestSigner};
+use crate::{Error, HttpClient, Payload, SelfDescribingJson};
 
-use crate::{Error, HttpClient, SelfDescribingJson};
+const POST_PATH: &str = "/com.snowplowanalytics.snowplow/tp2";
+const GET_PATH: &str = "/i";
 
-const POST_PATH: &str = "com.snowplowanalytics.snowplow/tp2";
+/// The `User-Agent` header sent by default, identifying the tracker and its version
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) const DEFAULT_USER_AGENT: &str =
+    concat!("snowplow-rust-tracker/", env!("CARGO_PKG_VERSION"));
 
 /// A [HttpClient] implementation useing the reqwest crate to send events to the collector.
 pub struct ReqwestClient {
     pub client: reqwest::Client,
     pub collector_url: String,
+    /// The path events are POSTed to, appended to the collector URL
+    pub post_path: String,
+    /// The path events are sent to via GET, appended to the collector URL
+    pub get_path: String,
+    /// A hook called with every request before it is sent, if set
+    pub before_send: Option<BeforeSend>,
+    /// Whether the body of POST requests is compressed with gzip
+    pub compress: bool,
+    /// Signs the body of requests, after compression, if set
+    pub signer: Option<RequestSigner>,
+    /// The shape of the body of POST requests
+    pub emit_format: EmitFormat,
+    /// Captures the network user ID cookie set by the collector, and sends it back, if set
+    pub cookie_jar: Option<CookieJar>,
 }
 
 impl ReqwestClient {
+    /// Create a client sending events with the default `User-Agent` header
     pub fn new(collector_url: &str) -> Box<ReqwestClient> {
+        Self::with_client(collector_url, Self::default_client())
+    }
+
+    // A client identifying the tracker in the `User-Agent` header, which browsers set themselves
+    fn default_client() -> Client {
+        let client = Client::builder();
+        #[cfg(not(target_arch = "wasm32"))]
+        let client = client.user_agent(DEFAULT_USER_AGENT);
+
+        client.build().unwrap_or_else(|e| {
+            log::warn!("Failed to build HTTP client, using the default one: {e}");
+            Client::new()
+        })
+    }
+
+    /// Create a client sending events using the provided [reqwest::Client]
+    ///
+    /// Useful to share a client configured with TLS settings, proxies, or connection pooling.
+    pub fn with_client(collector_url: &str, client: reqwest::Client) -> Box<ReqwestClient> {
         Box::new(ReqwestClient {
-            client: Client::new(),
+            client,
             collector_url: collector_url.to_string(),
+            post_path: POST_PATH.to_string(),
+            get_path: GET_PATH.to_string(),
+            before_send: None,
+            compress: false,
+            signer: None,
+            emit_format: EmitFormat::default(),
+            cookie_jar: None,
+        })
+    }
+
+    /// Create a client sending events to custom paths on the collector, e.g. when it sits behind a proxy
+    ///
+    /// Both paths must start with `/`.
+    pub fn with_paths(
+        collector_url: &str,
+        post_path: &str,
+        get_path: &str,
+    ) -> Result<Box<ReqwestClient>, Error> {
+        for path in [post_path, get_path] {
+            if !path.starts_with('/') {
+                return Err(Error::EmitterError(format!(
+                    "Path must start with '/': {path}"
+                )));
+            }
+        }
+
+        Ok(Box::new(ReqwestClient {
+            client: Self::default_client(),
+            collector_url: collector_url.to_string(),
+            post_path: post_path.to_string(),
+            get_path: get_path.to_string(),
+            before_send: None,
+            compress: false,
+            signer: None,
+            emit_format: EmitFormat::default(),
+            cookie_jar: None,
+        }))
+    }
+
+    // Builds a request from its parts, after passing them to the `before_send` hook
+    fn request(&self, mut parts: RequestParts) -> Result<RequestBuilder, Error> {
+        if let Some(before_send) = &self.before_send {
+            before_send(&mut parts);
+        }
+
+        let method = match Method::from_bytes(parts.method.as_bytes()) {
+            Ok(method) => method,
+            Err(e) => return Err(Error::EmitterError(format!("Invalid HTTP method: {e}"))),
+        };
+
+        if let Some(cookie) = self.cookie_jar.as_ref().and_then(CookieJar::cookie_header) {
+            parts.headers.entry("Cookie".to_string()).or_insert(cookie);
+        }
+
+        let request = parts.headers.iter().fold(
+            self.client.request(method, &parts.url),
+            |request, (name, value)| request.header(name, value),
+        );
+
+        let body = match parts.body {
+            // Compressed after the hook, so it sees the body as JSON
+            Some(body) if self.compress => Self::gzip(body.as_bytes())?,
+            Some(body) => body.into_bytes(),
+            None => return Ok(request),
+        };
+        let request = if self.compress {
+            request.header("Content-Encoding", "gzip")
+        } else {
+            request
+        };
+
+        // Signed last, over the bytes actually sent
+        Ok(match &self.signer {
+            Some(signer) => request
+                .header(signer.header(), signer.sign(&body))
+                .body(body),
+            None => request.body(body),
+        })
+    }
+
+    fn gzip(body: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        match encoder.write_all(body).and_then(|_| encoder.finish()) {
+            Ok(compressed) => Ok(compressed),
+            Err(e) => Err(Error::EmitterError(format!(
+                "Failed to compress request body: {e}"
+            ))),
+        }
+    }
+
+    // Builds the POST request sending the payload to the collector
+    fn post_request(
+        &self,
+        payload: &SelfDescribingJson,
+        headers: &HashMap<String, String>,
+    ) -> Result<RequestBuilder, Error> {
+        let body = match self.emit_format {
+            EmitFormat::Tp2Envelope => serde_json::to_string(payload)?,
+            EmitFormat::RawJsonArray => serde_json::to_string(&payload.data)?,
+        };
+
+        let mut headers = headers.clone();
+        headers.insert("Content-Type".to_string(), "application/json".to_string());
+
+        self.request(RequestParts {
+            method: "POST".to_string(),
+            url: format!("{}{}", self.collector_url, self.post_path),
+            headers,
+            body: Some(body),
+        })
+    }
+
+    // Builds the GET request sending a single event to the collector as query parameters
+    fn get_request(
+        &self,
+        payload: &Payload,
+        headers: &HashMap<String, String>,
+    ) -> Result<RequestBuilder, Error> {
+        let collector_url = format!("{}{}", self.collector_url, self.get_path);
+
+        let url = match Url::parse_with_params(&collector_url, payload.query_pairs()?) {
+            Ok(url) => url,
+            Err(e) => return Err(Error::EmitterError(format!("Invalid collector URL: {e}"))),
+        };
+
+        self.request(RequestParts {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: headers.clone(),
+            body: None,
+        })
+    }
+
+    // Stores the network user ID from any cookie the collector set in the response
+    fn capture_cookies(&self, resp: &reqwest::Response) {
+        let Some(cookie_jar) = &self.cookie_jar else {
+            return;
+        };
+
+        for set_cookie in resp.headers().get_all(reqwest::header::SET_COOKIE) {
+            match set_cookie.to_str() {
+                Ok(set_cookie) => cookie_jar.capture(set_cookie),
+                Err(e) => log::warn!("Failed to read Set-Cookie header: {e}"),
+            }
+        }
+    }
+
+    // Turns a response into its status code, or a collector response error if unsuccessful
+    pub(crate) async fn handle_response(resp: reqwest::Response) -> Result<u16, Error> {
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(status.as_u16());
+        }
+
+        // Keep the response body, as it often explains why the collector rejected the events
+        let body = resp.text().await.unwrap_or_else(|e| {
+            log::warn!("Failed to read collector response body: {e}");
+            String::new()
+        });
+
+        Err(Error::CollectorResponse {
+            status: status.as_u16(),
+            body,
         })
     }
 }
 
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 impl HttpClient for ReqwestClient {
     async fn post(&self, payload: SelfDescribingJson) -> Result<u16, Error> {
-        let collector_url = format!("{}/{}", self.collector_url, POST_PATH);
+        self.post_with_headers(payload, &HashMap::new()).await
+    }
 
-        match self.client.post(&collector_url).json(&payload).send().await {
-            Ok(resp) => Ok(resp.status().as_u16()),
-            Err(e) => Err(Error::EmitterError(format!("POST request failed: {e}"))),
+    async fn post_with_headers(
+        &self,
+        payload: SelfDescribingJson,
+        headers: &HashMap<String, String>,
+    ) -> Result<u16, Error> {
+        let resp = self.post_request(&payload, headers)?.send().await?;
+        self.capture_cookies(&resp);
+        Self::handle_response(resp).await
+    }
+
+    async fn post_with_response(
+        &self,
+        payload: SelfDescribingJson,
+        headers: &HashMap<String, String>,
+    ) -> Result<HttpResponse, Error> {
+        let resp = self.post_request(&payload, headers)?.send().await?;
+        self.capture_cookies(&resp);
+        if !resp.status().is_success() {
+            return Self::handle_response(resp)
+                .await
+                .map(|status| HttpResponse {
+                    status,
+                    body: String::new(),
+                });
         }
+
+        let status = resp.status().as_u16();
+        let body = resp.text().await.unwrap_or_else(|e| {
+            log::warn!("Failed to read collector response body: {e}");
+            String::new()
+        });
+
+        Ok(HttpResponse { status, body })
+    }
+
+    async fn get(
+        &self,
+        payload: &Payload,
+        headers: &HashMap<String, String>,
+    ) -> Result<u16, Error> {
+        let resp = self.get_request(payload, headers)?.send().await?;
+        self.capture_cookies(&resp);
+        Self::handle_response(resp).await
+    }
+
+    async fn health_check(&self, path: &str) -> Result<u16, Error> {
+        let request = self.request(RequestParts {
+            method: "GET".to_string(),
+            url: format!("{}{}", self.collector_url, path),
+            headers: HashMap::new(),
+            body: None,
+        })?;
+
+        Self::handle_response(request.send().await?).await
+    }
+
+    fn set_before_send(&mut self, before_send: BeforeSend) {
+        self.before_send = Some(before_send);
+    }
+
+    fn set_compression(&mut self, compress: bool) {
+        self.compress = compress;
+    }
+
+    fn set_signer(&mut self, signer: RequestSigner) {
+        self.signer = Some(signer);
+    }
+
+    // The POST path is `/{vendor}/{version}`, so each setter keeps the other segment
+    fn set_vendor(&mut self, vendor: &CollectorVendor) {
+        let version = match self.post_path.rsplit_once('/') {
+            Some((_, version)) => version,
+            None => ProtocolVersion::Tp2.as_str(),
+        };
+        self.post_path = format!("/{}/{version}", vendor.as_str());
+    }
+
+    fn set_protocol_version(&mut self, protocol_version: &ProtocolVersion) {
+        let prefix = match self.post_path.rsplit_once('/') {
+            Some((prefix, _)) => prefix,
+            None => "",
+        };
+        self.post_path = format!("{prefix}/{}", protocol_version.as_str());
+    }
+
+    fn set_emit_format(&mut self, emit_format: EmitFormat) {
+        self.emit_format = emit_format;
+    }
+
+    fn set_cookie_jar(&mut self, cookie_jar: CookieJar) {
+        self.cookie_jar = Some(cookie_jar);
+    }
+
+    fn set_collector_url(&mut self, collector_url: &str) {
+        self.collector_url = collector_url.to_string();
     }
 
     fn clone(&self) -> Box<dyn HttpClient + Send + Sync> {
         Box::new(ReqwestClient {
             client: self.client.clone(),
             collector_url: self.collector_url.clone(),
+            post_path: self.post_path.clone(),
+            get_path: self.get_path.clone(),
+            before_send: self.before_send.clone(),
+            compress: self.compress,
+            signer: self.signer.clone(),
+            emit_format: self.emit_format,
+            cookie_jar: self.cookie_jar.clone(),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use crate::event_batch::EventBatch;
+    use crate::http_client::mock_collector::mock_collector;
+
+    use super::*;
+
+    #[test]
+    fn post_request_includes_headers() {
+        let client = ReqwestClient::new("http://example.com");
+        let headers = HashMap::from([("X-SP-Namespace".to_string(), "ns".to_string())]);
+
+        let request = client
+            .post_request(&SelfDescribingJson::new("schema", json!({})), &headers)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            request.url().as_str(),
+            "http://example.com/com.snowplowanalytics.snowplow/tp2"
+        );
+        assert_eq!(request.headers()["X-SP-Namespace"], "ns");
+    }
+
+    #[test]
+    fn post_body_follows_emit_format() {
+        let events: Vec<Payload> = ["first", "second"]
+            .into_iter()
+            .map(|app_id| {
+                Payload::builder()
+                    .p("pc".to_string())
+                    .tv("tv".to_string())
+                    .eid(Uuid::new_v4())
+                    .dtm(Utc::now())
+                    .aid(app_id.to_string())
+                    .finalise_payload()
+                    .unwrap()
+            })
+            .collect();
+        let batch = EventBatch::new(Uuid::new_v4(), events);
+
+        let body = |emit_format| {
+            let mut client = ReqwestClient::new("http://example.com");
+            client.set_emit_format(emit_format);
+            let request = client
+                .post_request(&batch.as_payload(), &HashMap::new())
+                .unwrap()
+                .build()
+                .unwrap();
+            let body = request.body().unwrap().as_bytes().unwrap();
+            serde_json::from_slice::<serde_json::Value>(body).unwrap()
+        };
+
+        let envelope = body(EmitFormat::Tp2Envelope);
+        assert_eq!(
+            envelope["schema"],
+            "iglu:com.snowplowanalytics.snowplow/payload_data/jsonschema/1-0-4"
+        );
+        assert_eq!(envelope["data"][0]["aid"], "first");
+        assert_eq!(envelope["data"][1]["aid"], "second");
+
+        let array = body(EmitFormat::RawJsonArray);
+        assert_eq!(array, envelope["data"]);
+        assert_eq!(array.as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn get_request_sends_payload_as_query_parameters() {
+        let client = ReqwestClient::new("http://example.com");
+        let event_id = Uuid::new_v4();
+        let payload = Payload::builder()
+            .p("pc".to_string())
+            .tv("tv".to_string())
+            .eid(event_id)
+            .dtm(Utc::now())
+            .aid("app id".to_string())
+            .finalise_payload()
+            .unwrap();
+
+        let request = client
+            .get_request(&payload, &HashMap::new())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(request.url().path(), "/i");
+        let query: HashMap<_, _> = request.url().query_pairs().into_owned().collect();
+        assert_eq!(query["eid"], event_id.to_string());
+        assert_eq!(query["aid"], "app id");
+    }
+
+    #[test]
+    fn with_paths_requires_leading_slash() {
+        assert!(ReqwestClient::with_paths("http://example.com", "/tp2", "/i").is_ok());
+        assert!(matches!(
+            ReqwestClient::with_paths("http://example.com", "tp2", "/i"),
+            Err(Error::EmitterError(_))
+        ));
+        assert!(matches!(
+            ReqwestClient::with_paths("http://example.com", "/tp2", "i"),
+            Err(Error::EmitterError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn non_successful_response_returns_collector_response_error() {
+        let (url, _requests) = mock_collector(|_| (400, "Malformed payload".to_string()));
+        let client = ReqwestClient::new(&url);
+
+        let result = client
+            .post(SelfDescribingJson::new("schema", json!({})))
+            .await;
+
+        match result {
+            Err(Error::CollectorResponse { status, body }) => {
+                assert_eq!(status, 400);
+                assert_eq!(body, "Malformed payload");
+            }
+            other => panic!("Expected a collector response error, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn successful_response_returns_status_code() {
+        let (url, _requests) = mock_collector(|_| (200, String::new()));
+        let client = ReqwestClient::new(&url);
+
+        let result = client
+            .post(SelfDescribingJson::new("schema", json!({})))
+            .await;
+
+        assert_eq!(result.unwrap(), 200);
+    }
+}
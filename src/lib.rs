@@ -59,24 +59,74 @@
 //! }
 //! ```
 
+mod application_context;
+mod clock;
+mod coalescer;
+mod deduplicator;
+mod desktop_context;
 mod emitter;
 mod error;
 mod event;
 mod event_batch;
 mod event_store;
+mod field_limits;
+mod gdpr_context;
+mod geolocation_context;
 mod http_client;
+mod macros;
+mod mobile_context;
 mod payload;
+mod pii;
+mod platform_contexts;
+#[cfg(feature = "schema-validation")]
+mod schema_resolver;
+mod schema_version;
+mod session_tracker;
 mod snowplow;
 mod subject;
 mod timestamp;
 mod tracker;
 
-pub use emitter::{BatchEmitter, Emitter, RetryPolicy};
+pub use application_context::ApplicationContext;
+pub use clock::{Clock, FixedClock, SystemClock};
+pub use desktop_context::DesktopContext;
+#[cfg(feature = "blocking")]
+pub use emitter::BlockingEmitter;
+#[cfg(target_arch = "wasm32")]
+pub use emitter::WasmEmitter;
+pub use emitter::{
+    normalize_collector_url, BufferOption, CollectorVendor, DeadLetter, EmitFormat, Emitter,
+    EmitterStats, EventFilter, HttpMethod, Jitter, MockEmitter, ProtocolVersion, RetryPolicy,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use emitter::{BatchEmitter, DeliveryReport, FlushPolicy, OnSuccess, Sink};
 pub use error::Error;
-pub use event::{ScreenViewEvent, SelfDescribingEvent, StructuredEvent, TimingEvent};
+pub use event::{
+    ConsentDocument, ConsentGrantedEvent, ConsentWithdrawnEvent, EventBuildable, EventWithContext,
+    LinkClickEvent, PagePingEvent, PayloadAddable, ScreenViewEvent, SelfDescribingEvent,
+    StructuredEvent, TimingEvent,
+};
 pub use event_store::{EventStore, InMemoryEventStore};
-pub use http_client::{HttpClient, ReqwestClient};
-pub use payload::{Payload, PayloadBuilder, SelfDescribingJson};
+pub use field_limits::FieldLimits;
+pub use gdpr_context::GdprContext;
+pub use geolocation_context::GeolocationContext;
+pub use http_client::{
+    BeforeSend, CookieJar, EventSink, HttpClient, HttpResponse, RequestParts, RequestSigner,
+    ReqwestClient,
+};
+#[doc(hidden)]
+pub use macros::__add_self_describing_data;
+pub use mobile_context::MobileContext;
+pub use payload::{
+    ContextDeduplication, ContextEncoding, EventType, NumericEncoding, Payload, PayloadBuilder,
+    SelfDescribingEventData, SelfDescribingJson,
+};
+pub use pii::PiiField;
+pub use platform_contexts::PlatformContexts;
+#[cfg(feature = "schema-validation")]
+pub use schema_resolver::SchemaResolver;
+pub use schema_version::{SchemaUri, SchemaVersion};
+pub use session_tracker::SessionTracker;
 pub use snowplow::Snowplow;
 pub use subject::Subject;
-pub use tracker::Tracker;
+pub use tracker::{Enricher, TrackedEvent, Tracker, TrackerBuilder, TrackerConfig};
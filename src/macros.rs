@@ -0,0 +1,139 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use serde::Serialize;
+
+use crate::event::{PayloadAddable, SelfDescribingEvent};
+use crate::payload::{PayloadBuilder, SelfDescribingJson};
+
+/// Implements [PayloadAddable](crate::PayloadAddable) for a serializable struct, tracking it as a
+/// self-describing event with the given schema
+///
+/// The struct is serialized with serde as the data of the event, so it must serialize to a JSON object
+/// conforming to the schema. The event has no [Subject](crate::Subject) of its own.
+///
+/// ## Example
+/// ```
+/// use serde::Serialize;
+/// use snowplow_tracker::{define_self_describing_event, MockEmitter, Tracker};
+///
+/// #[derive(Serialize)]
+/// struct ButtonClick {
+///     id: String,
+/// }
+///
+/// define_self_describing_event!(ButtonClick, "iglu:com.acme/button_click/jsonschema/1-0-0");
+///
+/// let emitter = MockEmitter::new();
+/// let mut tracker = Tracker::new("ns", "app_id", emitter.clone(), None);
+/// tracker.track(ButtonClick { id: "buy".to_string() }, None).unwrap();
+///
+/// assert_eq!(emitter.payloads().len(), 1);
+/// ```
+#[macro_export]
+macro_rules! define_self_describing_event {
+    ($event:ty, $schema:expr) => {
+        impl $crate::PayloadAddable for $event {
+            fn add_to_payload(
+                self,
+                payload_builder: $crate::PayloadBuilder,
+            ) -> $crate::PayloadBuilder {
+                $crate::__add_self_describing_data($schema, &self, payload_builder)
+            }
+
+            fn subject(&self) -> &::std::option::Option<$crate::Subject> {
+                &::std::option::Option::None
+            }
+        }
+    };
+}
+
+// Adds the data to the payload as a self-describing event, for the impls generated by `define_self_describing_event`
+#[doc(hidden)]
+pub fn __add_self_describing_data<T: Serialize>(
+    schema: &str,
+    data: &T,
+    payload_builder: PayloadBuilder,
+) -> PayloadBuilder {
+    let data = match SelfDescribingJson::from_serializable(schema, data) {
+        Ok(json) => json.data,
+        Err(e) => {
+            log::error!("Failed to serialize the data of {schema}: {e}");
+            serde_json::Value::Null
+        }
+    };
+
+    let event = SelfDescribingEvent {
+        schema: schema.to_string(),
+        data,
+        subject: None,
+        true_tstamp: None,
+    };
+    event.add_to_payload(payload_builder)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use crate::{EventWithContext, MockEmitter, Tracker};
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct AddToBasket {
+        product_id: String,
+        quantity: u32,
+    }
+
+    define_self_describing_event!(AddToBasket, "iglu:com.acme/add_to_basket/jsonschema/1-0-0");
+
+    #[test]
+    fn defined_event_is_tracked_as_self_describing_event() {
+        let emitter = MockEmitter::new();
+        let mut tracker = Tracker::new("ns", "app_id", emitter.clone(), None);
+
+        let event = AddToBasket {
+            product_id: "sku-1".to_string(),
+            quantity: 2,
+        };
+        tracker.track(event, None).unwrap();
+        let boxed: Vec<EventWithContext> = vec![(
+            Box::new(AddToBasket {
+                product_id: "sku-2".to_string(),
+                quantity: 1,
+            }),
+            None,
+        )];
+        tracker.track_all(boxed).unwrap();
+
+        let payloads = emitter.payloads();
+        assert_eq!(payloads.len(), 2);
+        let events: Vec<serde_json::Value> = payloads
+            .iter()
+            .map(|payload| {
+                let payload = serde_json::to_value(payload).unwrap();
+                assert_eq!(payload["e"], "ue");
+                let ue_pr: serde_json::Value =
+                    serde_json::from_str(payload["ue_pr"].as_str().unwrap()).unwrap();
+                ue_pr["data"].clone()
+            })
+            .collect();
+        assert_eq!(
+            events[0]["schema"],
+            "iglu:com.acme/add_to_basket/jsonschema/1-0-0"
+        );
+        assert_eq!(
+            events[0]["data"],
+            serde_json::json!({ "productId": "sku-1", "quantity": 2 })
+        );
+        assert_eq!(events[1]["data"]["productId"], "sku-2");
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use derive_builder::Builder;
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::payload::SelfDescribingJson;
+
+/// The schema of the mobile context entity
+pub(crate) const MOBILE_CONTEXT_SCHEMA: &str =
+    "iglu:com.snowplowanalytics.snowplow/mobile_context/jsonschema/1-0-2";
+
+/// A context entity describing the operating system, device and network of a mobile app
+///
+/// Attach it to every event with [Tracker::set_mobile_context](crate::Tracker::set_mobile_context).
+///
+/// ## Example
+/// ```
+/// use snowplow_tracker::MobileContext;
+///
+/// let mobile_context = MobileContext::builder()
+///     .os_type("android")
+///     .os_version("14")
+///     .device_manufacturer("Acme")
+///     .device_model("Phone 3")
+///     .network_type("wifi")
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Serialize, Builder, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[builder(setter(into, strip_option))]
+#[builder(build_fn(error = "Error"))]
+pub struct MobileContext {
+    /// The type of operating system, e.g. `ios` or `android`.
+    pub os_type: String,
+
+    /// The version of the operating system.
+    pub os_version: String,
+
+    /// The manufacturer of the device.
+    pub device_manufacturer: String,
+
+    /// The model of the device.
+    pub device_model: String,
+
+    /// The mobile network carrier.
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub carrier: Option<String>,
+
+    /// The type of network, one of `mobile`, `wifi` or `offline`.
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_type: Option<String>,
+
+    /// The technology of the mobile network, e.g. `LTE`.
+    #[builder(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_technology: Option<String>,
+}
+
+impl MobileContext {
+    pub fn builder() -> MobileContextBuilder {
+        MobileContextBuilder::default()
+    }
+
+    pub(crate) fn to_self_describing_json(&self) -> SelfDescribingJson {
+        // Serializing a struct of strings can't fail
+        SelfDescribingJson::new(
+            MOBILE_CONTEXT_SCHEMA,
+            serde_json::to_value(self).unwrap_or_default(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn unset_fields_are_omitted() {
+        let mobile_context = MobileContext::builder()
+            .os_type("ios")
+            .os_version("17.1")
+            .device_manufacturer("Apple Inc.")
+            .device_model("iPhone15,2")
+            .carrier("Acme Mobile")
+            .build()
+            .unwrap();
+
+        let entity = mobile_context.to_self_describing_json();
+
+        assert_eq!(entity.schema, MOBILE_CONTEXT_SCHEMA);
+        assert_eq!(
+            entity.data,
+            json!({
+                "osType": "ios",
+                "osVersion": "17.1",
+                "deviceManufacturer": "Apple Inc.",
+                "deviceModel": "iPhone15,2",
+                "carrier": "Acme Mobile",
+            })
+        );
+    }
+}
@@ -11,30 +11,95 @@
 
 use chrono::{DateTime, Utc};
 use derive_builder::Builder;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use serde_json::json;
 use serde_json::Value;
 use uuid::Uuid;
 
+use crate::event::EncodedStructuredEvent;
 use crate::timestamp::{ts_milliseconds_string, ts_milliseconds_string_option};
 use crate::Error;
-use crate::StructuredEvent;
 use crate::Subject;
+use crate::{PagePingEvent, SchemaUri, SchemaVersion, StructuredEvent};
 
+/// The type of an event, sent as the `e` field of the payload
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum EventType {
-    #[serde(rename(serialize = "se"))]
+    /// A [StructuredEvent], whose fields are set with [PayloadBuilder::structured_event]
+    #[serde(rename(serialize = "se"), alias = "se")]
     StructuredEvent,
-    #[serde(rename(serialize = "ue"))]
+    /// A self-describing event, whose data is set with [PayloadBuilder::ue_pr]
+    #[serde(rename(serialize = "ue"), alias = "ue")]
     SelfDescribingEvent,
+    /// A [PagePingEvent], whose fields are set with [PayloadBuilder::page_ping]
+    #[serde(rename(serialize = "pp"), alias = "pp")]
+    PagePing,
 }
 
-#[derive(Builder, Serialize, Deserialize, Default, Clone, Debug)]
+/// How numeric fields, such as the value of a [StructuredEvent], are serialized
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NumericEncoding {
+    /// As JSON strings, as expected by the tracker protocol
+    #[default]
+    String,
+    /// As JSON numbers, for downstream consumers expecting numbers
+    Number,
+}
+
+/// How the context entities of an event (`co`) are serialized
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ContextEncoding {
+    /// As a string of JSON, as expected by the tracker protocol
+    #[default]
+    String,
+    /// As a JSON object, for debugging or collectors accepting unencoded contexts
+    ///
+    /// When sent via GET, the object is still encoded as a string in the query parameter.
+    Object,
+}
+
+/// Which context entity is kept when an event has several with the same schema
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ContextDeduplication {
+    /// All of them, so several entities with the same schema, such as products, are all sent
+    #[default]
+    KeepAll,
+    /// The first one, so the entities of the event take priority over those added by the tracker
+    FirstWins,
+    /// The last one, so the entities added by the tracker take priority over those of the event
+    LastWins,
+}
+
+impl ContextDeduplication {
+    // Removes the entities whose schema appears more than once, keeping the winner where it was
+    pub(crate) fn apply(self, contexts: Vec<SelfDescribingJson>) -> Vec<SelfDescribingJson> {
+        let mut seen = std::collections::HashSet::new();
+        match self {
+            ContextDeduplication::KeepAll => contexts,
+            ContextDeduplication::FirstWins => contexts
+                .into_iter()
+                .filter(|context| seen.insert(context.schema.clone()))
+                .collect(),
+            ContextDeduplication::LastWins => {
+                let mut contexts: Vec<_> = contexts
+                    .into_iter()
+                    .rev()
+                    .filter(|context| seen.insert(context.schema.clone()))
+                    .collect();
+                contexts.reverse();
+                contexts
+            }
+        }
+    }
+}
+
+#[derive(Builder, Deserialize, Default, Clone, Debug)]
+#[serde(remote = "Self")]
 #[builder(field(public))]
 #[builder(pattern = "owned")]
 #[builder(setter(strip_option))]
 #[builder(build_fn(error = "Error"))]
-#[builder(derive(Clone))]
+#[builder(derive(Clone, Debug))]
 /// The final payload that is sent to the collector
 ///
 /// For more information, see the [Snowplow Tracker Protocol](https://docs.snowplow.io/docs/collecting-data/collecting-from-own-applications/snowplow-tracker-protocol)
@@ -42,8 +107,12 @@ pub struct Payload {
     p: String,
     tv: String,
     pub(crate) eid: Uuid,
+
+    /// The device created timestamp, set when the event is tracked
     #[serde(with = "ts_milliseconds_string")]
-    dtm: DateTime<Utc>,
+    pub(crate) dtm: DateTime<Utc>,
+
+    /// The device sent timestamp, set again each time the event is sent, e.g. when retried
     #[serde(with = "ts_milliseconds_string")]
     pub(crate) stm: DateTime<Utc>,
 
@@ -63,7 +132,7 @@ pub struct Payload {
 
     #[builder(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
-    co: Option<ContextData>,
+    pub(crate) co: Option<ContextData>,
 
     // Structured Event
     #[builder(default)]
@@ -71,26 +140,264 @@ pub struct Payload {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) structured_event: Option<StructuredEvent>,
 
+    // Page Ping
+    #[builder(default)]
+    #[serde(flatten)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) page_ping: Option<PagePingEvent>,
+
     // Subject
     #[builder(default)]
     #[serde(flatten)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) subject: Option<Subject>,
+
+    /// How numeric fields are serialized, set by the tracker from its config
+    #[builder(default)]
+    #[serde(skip)]
+    pub(crate) numeric_encoding: NumericEncoding,
+
+    /// How the context data is serialized, set by the tracker from its config
+    #[builder(default)]
+    #[serde(skip)]
+    pub(crate) context_encoding: ContextEncoding,
+}
+
+// The payload is serialized through this view, so that its fields are encoded as set on the payload
+#[derive(Serialize)]
+struct PayloadView<'a> {
+    p: &'a str,
+    tv: &'a str,
+    eid: Uuid,
+
+    #[serde(with = "ts_milliseconds_string")]
+    dtm: DateTime<Utc>,
+
+    #[serde(with = "ts_milliseconds_string")]
+    stm: DateTime<Utc>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(with = "ts_milliseconds_string_option")]
+    ttm: Option<DateTime<Utc>>,
+
+    e: Option<&'a EventType>,
+    aid: &'a str,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ue_pr: Option<&'a SelfDescribingEventData>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    co: Option<EncodedContextData<'a>>,
+
+    #[serde(flatten)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    structured_event: Option<EncodedStructuredEvent<'a>>,
+
+    #[serde(flatten)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page_ping: Option<&'a PagePingEvent>,
+
+    #[serde(flatten)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subject: Option<&'a Subject>,
+}
+
+impl Serialize for Payload {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        PayloadView {
+            p: &self.p,
+            tv: &self.tv,
+            eid: self.eid,
+            dtm: self.dtm,
+            stm: self.stm,
+            ttm: self.ttm,
+            e: self.e.as_ref(),
+            aid: &self.aid,
+            ue_pr: self.ue_pr.as_ref(),
+            co: self
+                .co
+                .as_ref()
+                .map(|context| EncodedContextData(context, self.context_encoding)),
+            structured_event: self
+                .structured_event
+                .as_ref()
+                .map(|event| EncodedStructuredEvent(event, self.numeric_encoding)),
+            page_ping: self.page_ping.as_ref(),
+            subject: self.subject.as_ref(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Payload {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let fields = Value::deserialize(deserializer)?;
+
+        // The context data keeps the encoding it was serialized with
+        let context_encoding = match fields.get("co") {
+            Some(Value::Object(_)) => ContextEncoding::Object,
+            _ => ContextEncoding::String,
+        };
+
+        let mut payload = Payload::deserialize(fields).map_err(de::Error::custom)?;
+        payload.context_encoding = context_encoding;
+        Ok(payload)
+    }
 }
 
 impl Payload {
     pub fn builder() -> PayloadBuilder {
         PayloadBuilder::default()
     }
+
+    /// The ID of the event
+    pub fn event_id(&self) -> Uuid {
+        self.eid
+    }
+
+    /// The application ID of the tracker that tracked the event
+    pub fn app_id(&self) -> &str {
+        &self.aid
+    }
+
+    /// The platform the event was tracked on
+    pub fn platform(&self) -> &str {
+        &self.p
+    }
+
+    /// The payload as the URL-encoded query string of a GET request, as sent to the collector
+    ///
+    /// Useful for logging events, or sending them with your own transport.
+    ///
+    /// ## Example
+    /// ```
+    /// use chrono::Utc;
+    /// use snowplow_tracker::Payload;
+    /// use uuid::Uuid;
+    ///
+    /// let payload = Payload::builder()
+    ///     .p("srv".to_string())
+    ///     .tv("rust-0.2.0".to_string())
+    ///     .eid(Uuid::new_v4())
+    ///     .dtm(Utc::now())
+    ///     .aid("my app".to_string())
+    ///     .finalise_payload()
+    ///     .unwrap();
+    ///
+    /// assert!(payload.to_query_string().unwrap().contains("aid=my+app"));
+    /// ```
+    pub fn to_query_string(&self) -> Result<String, Error> {
+        match reqwest::Url::parse_with_params("http://localhost/", self.query_pairs()?) {
+            Ok(url) => Ok(url.query().unwrap_or_default().to_string()),
+            Err(e) => Err(Error::EmitterError(format!(
+                "Failed to encode payload as a query string: {e}"
+            ))),
+        }
+    }
+
+    // Checks that the fields required by the tracker protocol are set, for payloads built outside the tracker
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        let missing = |field: &str| Err(Error::BuilderError(format!("`{field}` must be set")));
+
+        if self.p.is_empty() {
+            return missing("p");
+        }
+
+        match self.e {
+            None => missing("e"),
+            Some(EventType::StructuredEvent) if self.structured_event.is_none() => {
+                missing("structured_event")
+            }
+            Some(EventType::SelfDescribingEvent) if self.ue_pr.is_none() => missing("ue_pr"),
+            Some(EventType::PagePing) if self.page_ping.is_none() => missing("page_ping"),
+            Some(_) => Ok(()),
+        }
+    }
+
+    // The payload as the query parameters of a GET request, with non-string fields as JSON
+    pub(crate) fn query_pairs(&self) -> Result<Vec<(String, String)>, Error> {
+        match serde_json::to_value(self)? {
+            Value::Object(fields) => Ok(fields
+                .into_iter()
+                .map(|(name, value)| match value {
+                    Value::String(value) => (name, value),
+                    value => (name, value.to_string()),
+                })
+                .collect()),
+            _ => Err(Error::EmitterError("Payload is not an object".to_string())),
+        }
+    }
+}
+
+// Lets a built payload be passed to an [Emitter](crate::Emitter), e.g. with [Tracker::track_payload](crate::Tracker::track_payload)
+impl From<Payload> for PayloadBuilder {
+    fn from(payload: Payload) -> Self {
+        PayloadBuilder {
+            p: Some(payload.p),
+            tv: Some(payload.tv),
+            eid: Some(payload.eid),
+            dtm: Some(payload.dtm),
+            stm: Some(payload.stm),
+            ttm: Some(payload.ttm),
+            e: Some(payload.e),
+            aid: Some(payload.aid),
+            ue_pr: Some(payload.ue_pr),
+            co: Some(payload.co),
+            structured_event: Some(payload.structured_event),
+            page_ping: Some(payload.page_ping),
+            subject: Some(payload.subject),
+            numeric_encoding: Some(payload.numeric_encoding),
+            context_encoding: Some(payload.context_encoding),
+        }
+    }
 }
 
 impl PayloadBuilder {
     pub fn finalise_payload(self) -> Result<Payload, Error> {
-        self.stm(Utc::now()).build()
+        self.finalise_payload_at(Utc::now())
+    }
+
+    /// Appends a context entity to those already set on the payload, e.g. from an enricher set with [Tracker::set_enricher](crate::Tracker::set_enricher)
+    pub fn add_context(&mut self, context: SelfDescribingJson) {
+        match self.co.as_mut() {
+            Some(Some(context_data)) => context_data.data.push(context),
+            _ => self.co = Some(Some(ContextData::new(vec![context]))),
+        }
+    }
+
+    // Builds the payload with the given sent timestamp
+    pub(crate) fn finalise_payload_at(mut self, stm: DateTime<Utc>) -> Result<Payload, Error> {
+        // An empty subject is treated as absent, e.g. when merging subjects left no fields set
+        if let Some(Some(subject)) = &self.subject {
+            if subject.is_empty() {
+                self.subject = None;
+            }
+        }
+
+        self.stm(stm).build()
+    }
+
+    // Events are identical if everything except the event ID and timestamps match
+    pub(crate) fn content_key(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string(&(
+            &self.e,
+            &self.ue_pr,
+            &self.structured_event,
+            &self.page_ping,
+            &self.co,
+            &self.subject,
+        ))?)
     }
 }
 
-#[derive(Deserialize, Clone, Debug)]
+/// The data of a self-describing event, sent as the `ue_pr` field of the payload
+#[derive(Clone, Debug)]
 pub struct SelfDescribingEventData {
     pub schema: String,
     pub data: SelfDescribingJson,
@@ -124,6 +431,46 @@ impl Serialize for SelfDescribingEventData {
     }
 }
 
+impl<'de> Deserialize<'de> for SelfDescribingEventData {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let fields = deserialize_string_or_object::<D, SelfDescribingJson>(deserializer)?;
+        Ok(SelfDescribingEventData {
+            schema: fields.schema,
+            data: fields.data,
+        })
+    }
+}
+
+// The fields shared by `SelfDescribingEventData` and `ContextData`
+#[derive(Deserialize)]
+struct SchemaAndData<T> {
+    schema: String,
+    data: T,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOrObject<T> {
+    String(String),
+    Object(SchemaAndData<T>),
+}
+
+// Reverses the manual serialization above, accepting the fields either as stringified JSON,
+// as sent to the collector, or as a plain object
+fn deserialize_string_or_object<'de, D, T>(deserializer: D) -> Result<SchemaAndData<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: de::DeserializeOwned,
+{
+    match StringOrObject::<T>::deserialize(deserializer)? {
+        StringOrObject::String(json) => serde_json::from_str(&json).map_err(de::Error::custom),
+        StringOrObject::Object(fields) => Ok(fields),
+    }
+}
+
 /// Self-describing JSON to be used mainly when creating context entities.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct SelfDescribingJson {
@@ -145,9 +492,62 @@ impl SelfDescribingJson {
             data: data,
         }
     }
+
+    /// Create self-describing JSON with the data serialized from a typed value
+    ///
+    /// Fails if the value can't be serialized to JSON, e.g. a map with non-string keys.
+    ///
+    /// ## Example
+    /// ```
+    /// use serde::Serialize;
+    /// use snowplow_tracker::SelfDescribingJson;
+    ///
+    /// #[derive(Serialize)]
+    /// struct ButtonClick {
+    ///     id: String,
+    /// }
+    ///
+    /// let click = ButtonClick { id: "buy".to_string() };
+    /// let json = SelfDescribingJson::from_serializable("iglu:com.acme/button_click/jsonschema/1-0-0", &click)
+    ///     .unwrap();
+    /// assert_eq!(json.data["id"], "buy");
+    /// ```
+    pub fn from_serializable<T: Serialize>(
+        schema: &str,
+        data: &T,
+    ) -> Result<SelfDescribingJson, Error> {
+        Ok(SelfDescribingJson::new(schema, serde_json::to_value(data)?))
+    }
+
+    /// Create self-describing JSON from a schema URI without its version, `iglu:{vendor}/{name}/{format}`, and the version
+    ///
+    /// Fails if the schema URI is malformed.
+    ///
+    /// ## Example
+    /// ```
+    /// use serde_json::json;
+    /// use snowplow_tracker::SelfDescribingJson;
+    ///
+    /// let json = SelfDescribingJson::with_version("iglu:com.acme/button_click/jsonschema", (1, 0, 1), json!({}))
+    ///     .unwrap();
+    /// assert_eq!(json.schema, "iglu:com.acme/button_click/jsonschema/1-0-1");
+    /// ```
+    pub fn with_version(
+        base: &str,
+        version: impl Into<SchemaVersion>,
+        data: Value,
+    ) -> Result<SelfDescribingJson, Error> {
+        let schema = SchemaUri::from_base(base, version)?;
+        Ok(SelfDescribingJson::new(&schema.to_string(), data))
+    }
+
+    /// The schema URI split into its parts, failing if it is malformed
+    pub fn schema_uri(&self) -> Result<SchemaUri, Error> {
+        self.schema.parse()
+    }
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct ContextData {
     pub schema: String,
     pub data: Vec<SelfDescribingJson>,
@@ -163,18 +563,187 @@ impl ContextData {
 }
 
 // The collector expects the `data` field of the `SelfDescribingEventData` to be an object,
-// but the SelfDescribingEventData to be a string, so we have to manually serialize SelfDescribingEventData
+// but the SelfDescribingEventData to be a string, so we have to manually serialize SelfDescribingEventData.
 impl Serialize for ContextData {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(
-            &json!({
-                "schema": self.schema,
-                "data": self.data,
-            })
-            .to_string(),
+        EncodedContextData(self, ContextEncoding::String).serialize(serializer)
+    }
+}
+
+// Context data serialized with the context encoding of the payload it is part of.
+// With `ContextEncoding::Object`, the whole context data is left as an object instead.
+struct EncodedContextData<'a>(&'a ContextData, ContextEncoding);
+
+impl Serialize for EncodedContextData<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let EncodedContextData(context, encoding) = self;
+        let context_data = json!({
+            "schema": context.schema,
+            "data": context.data,
+        });
+
+        match encoding {
+            ContextEncoding::String => serializer.serialize_str(&context_data.to_string()),
+            ContextEncoding::Object => context_data.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ContextData {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let fields = deserialize_string_or_object::<D, Vec<SelfDescribingJson>>(deserializer)?;
+        Ok(ContextData {
+            schema: fields.schema,
+            data: fields.data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Product {
+        sku: String,
+        unit_price: f64,
+        tags: Vec<String>,
+    }
+
+    #[test]
+    fn from_serializable_serializes_data() {
+        let product = Product {
+            sku: "sku-1".to_string(),
+            unit_price: 9.99,
+            tags: vec!["sale".to_string()],
+        };
+
+        let json = SelfDescribingJson::from_serializable(
+            "iglu:com.acme/product/jsonschema/1-0-0",
+            &product,
         )
+        .unwrap();
+
+        assert_eq!(json.schema, "iglu:com.acme/product/jsonschema/1-0-0");
+        assert_eq!(
+            json.data,
+            json!({ "sku": "sku-1", "unitPrice": 9.99, "tags": ["sale"] })
+        );
+    }
+
+    #[test]
+    fn from_serializable_fails_on_unserializable_data() {
+        let data = HashMap::from([((1, 2), "non-string key")]);
+
+        let result =
+            SelfDescribingJson::from_serializable("iglu:com.acme/map/jsonschema/1-0-0", &data);
+
+        assert!(matches!(result, Err(Error::Serialization(_))));
+    }
+
+    #[test]
+    fn query_string_round_trips_key_fields() {
+        let eid = Uuid::new_v4();
+        let dtm = Utc::now();
+        let payload = Payload::builder()
+            .p("srv".to_string())
+            .tv("rust-0.2.0".to_string())
+            .eid(eid)
+            .dtm(dtm)
+            .aid("my app".to_string())
+            .e(EventType::SelfDescribingEvent)
+            .ue_pr(SelfDescribingEventData::new(SelfDescribingJson::new(
+                "iglu:com.acme/click/jsonschema/1-0-0",
+                json!({ "target": "a&b=c" }),
+            )))
+            .finalise_payload()
+            .unwrap();
+
+        let query_string = payload.to_query_string().unwrap();
+        let url = reqwest::Url::parse(&format!("http://localhost/i?{query_string}")).unwrap();
+        let fields: HashMap<_, _> = url.query_pairs().into_owned().collect();
+
+        assert_eq!(fields["eid"], eid.to_string());
+        assert_eq!(fields["aid"], "my app");
+        assert_eq!(fields["p"], "srv");
+        assert_eq!(fields["e"], "ue");
+        assert_eq!(fields["dtm"], dtm.timestamp_millis().to_string());
+        let ue_pr: Value = serde_json::from_str(&fields["ue_pr"]).unwrap();
+        assert_eq!(ue_pr["data"]["data"]["target"], "a&b=c");
+    }
+
+    fn payload_with_contexts(encoding: ContextEncoding) -> Payload {
+        let context = ContextData::new(vec![
+            SelfDescribingJson::new("iglu:com.acme/user/jsonschema/1-0-0", json!({ "id": 1 })),
+            SelfDescribingJson::new(
+                "iglu:com.acme/page/jsonschema/1-0-0",
+                json!({ "path": "/" }),
+            ),
+        ]);
+
+        Payload::builder()
+            .p("srv".to_string())
+            .tv("rust-0.2.0".to_string())
+            .eid(Uuid::new_v4())
+            .dtm(Utc::now())
+            .ttm(Utc::now())
+            .aid("my app".to_string())
+            .e(EventType::SelfDescribingEvent)
+            .ue_pr(SelfDescribingEventData::new(SelfDescribingJson::new(
+                "iglu:com.acme/click/jsonschema/1-0-0",
+                json!({ "target": "button" }),
+            )))
+            .co(context)
+            .context_encoding(encoding)
+            .finalise_payload()
+            .unwrap()
+    }
+
+    #[test]
+    fn payload_round_trips_through_deserialize() {
+        for encoding in [ContextEncoding::String, ContextEncoding::Object] {
+            let payload = payload_with_contexts(encoding);
+            let serialized = serde_json::to_string(&payload).unwrap();
+
+            let deserialized: Payload = serde_json::from_str(&serialized).unwrap();
+
+            assert_eq!(deserialized.event_id(), payload.event_id());
+            assert_eq!(
+                deserialized.dtm.timestamp_millis(),
+                payload.dtm.timestamp_millis()
+            );
+            assert!(matches!(
+                deserialized.e,
+                Some(EventType::SelfDescribingEvent)
+            ));
+            assert_eq!(deserialized.context_encoding, encoding);
+            let co = deserialized.co.as_ref().unwrap();
+            assert_eq!(co.data.len(), 2);
+            assert_eq!(co.data[1].data["path"], "/");
+            let ue_pr = deserialized.ue_pr.as_ref().unwrap();
+            assert_eq!(ue_pr.data.schema, "iglu:com.acme/click/jsonschema/1-0-0");
+            assert_eq!(serde_json::to_string(&deserialized).unwrap(), serialized);
+        }
+    }
+
+    #[test]
+    fn context_data_fails_to_deserialize_malformed_string() {
+        let result = serde_json::from_value::<ContextData>(json!("{\"schema\": 1}"));
+
+        assert!(result.is_err());
     }
 }
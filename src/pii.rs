@@ -0,0 +1,102 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use std::collections::HashSet;
+
+use sha2::{Digest, Sha256};
+
+use crate::subject::Subject;
+
+/// A field of the [Subject] identifying the user, which can be hashed with [Tracker::set_hash_pii](crate::Tracker::set_hash_pii)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PiiField {
+    /// [Subject::user_id]
+    UserId,
+    /// [Subject::ip_address]
+    IpAddress,
+}
+
+/// The subject fields hashed before events are sent, and the salt they are hashed with
+#[derive(Debug, Clone)]
+pub(crate) struct PiiHashing {
+    pub(crate) fields: HashSet<PiiField>,
+    pub(crate) salt: String,
+}
+
+impl PiiHashing {
+    /// Replaces the configured fields of the subject with their salted SHA-256 hash
+    pub(crate) fn apply(&self, subject: &mut Subject) {
+        for field in &self.fields {
+            let value = match field {
+                PiiField::UserId => &mut subject.user_id,
+                PiiField::IpAddress => &mut subject.ip_address,
+            };
+            if let Some(value) = value.as_mut() {
+                *value = self.hash(value);
+            }
+        }
+    }
+
+    // The lowercase hex SHA-256 hash of the salt followed by the value
+    fn hash(&self, value: &str) -> String {
+        let digest = Sha256::new()
+            .chain_update(self.salt.as_bytes())
+            .chain_update(value.as_bytes())
+            .finalize();
+
+        digest.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pii_hashing(fields: &[PiiField]) -> PiiHashing {
+        PiiHashing {
+            fields: fields.iter().copied().collect(),
+            salt: "pepper".to_string(),
+        }
+    }
+
+    #[test]
+    fn configured_fields_are_replaced_by_salted_hash() {
+        let mut subject = Subject::builder()
+            .user_id("user_1")
+            .ip_address("203.0.113.7")
+            .language("en")
+            .build()
+            .unwrap();
+
+        pii_hashing(&[PiiField::UserId]).apply(&mut subject);
+
+        // sha256("pepperuser_1")
+        assert_eq!(
+            subject.user_id.unwrap(),
+            "c86cf08a7f47f5e09dd929a59156807e557b2e66965f145a65bf520633302343"
+        );
+        assert_eq!(subject.ip_address.unwrap(), "203.0.113.7");
+        assert_eq!(subject.language.unwrap(), "en");
+    }
+
+    #[test]
+    fn hash_depends_on_salt() {
+        let hashing = pii_hashing(&[]);
+        let other_salt = PiiHashing {
+            salt: "salt".to_string(),
+            ..hashing.clone()
+        };
+
+        assert_eq!(hashing.hash("user_1").len(), 64);
+        assert_eq!(hashing.hash("user_1"), hashing.hash("user_1"));
+        assert_ne!(hashing.hash("user_1"), other_salt.hash("user_1"));
+    }
+}
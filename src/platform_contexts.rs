@@ -0,0 +1,79 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use crate::desktop_context::DesktopContext;
+use crate::geolocation_context::GeolocationContext;
+use crate::mobile_context::MobileContext;
+use crate::session_tracker::SessionTracker;
+
+/// The common automatic context of an app, enabled together with [Tracker::with_platform_contexts](crate::Tracker::with_platform_contexts)
+///
+/// Each part is optional, and is the same as enabling it on the tracker on its own.
+///
+/// ## Example
+/// ```
+/// use snowplow_tracker::{
+///     GeolocationContext, MobileContext, MockEmitter, PlatformContexts, SessionTracker, Tracker,
+/// };
+///
+/// let platform_contexts = PlatformContexts::new()
+///     .session(SessionTracker::new())
+///     .mobile(
+///         MobileContext::builder()
+///             .os_type("android")
+///             .os_version("14")
+///             .device_manufacturer("Acme")
+///             .device_model("Phone 3")
+///             .build()
+///             .unwrap(),
+///     )
+///     .geolocation(GeolocationContext::builder().latitude(59.33).longitude(18.07).build().unwrap());
+///
+/// let tracker = Tracker::new("ns", "app_id", MockEmitter::new(), None)
+///     .with_platform_contexts(platform_contexts);
+/// ```
+#[derive(Default)]
+pub struct PlatformContexts {
+    pub(crate) session: Option<SessionTracker>,
+    pub(crate) mobile: Option<MobileContext>,
+    pub(crate) desktop: Option<DesktopContext>,
+    pub(crate) geolocation: Option<GeolocationContext>,
+}
+
+impl PlatformContexts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the session ID and index of every event, as with [Tracker::set_session_tracker](crate::Tracker::set_session_tracker)
+    pub fn session(mut self, session_tracker: SessionTracker) -> Self {
+        self.session = Some(session_tracker);
+        self
+    }
+
+    /// Attach a [MobileContext] to every event, as with [Tracker::set_mobile_context](crate::Tracker::set_mobile_context)
+    pub fn mobile(mut self, mobile_context: MobileContext) -> Self {
+        self.mobile = Some(mobile_context);
+        self
+    }
+
+    /// Attach a [DesktopContext] to every event, as with [Tracker::set_desktop_context](crate::Tracker::set_desktop_context)
+    pub fn desktop(mut self, desktop_context: DesktopContext) -> Self {
+        self.desktop = Some(desktop_context);
+        self
+    }
+
+    /// Attach a [GeolocationContext] to every event, as with [Tracker::set_geolocation_context](crate::Tracker::set_geolocation_context)
+    pub fn geolocation(mut self, geolocation_context: GeolocationContext) -> Self {
+        self.geolocation = Some(geolocation_context);
+        self
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use jsonschema::{Draft, JSONSchema};
+use serde_json::Value;
+
+use crate::error::Error;
+use crate::payload::SelfDescribingJson;
+
+/// Resolves the Iglu schemas of events and context entities, so they can be validated before being tracked.
+///
+/// Schemas are fetched once and cached in memory for the lifetime of the resolver.
+pub struct SchemaResolver {
+    base_url: String,
+    cache: Mutex<HashMap<String, Arc<JSONSchema>>>,
+}
+
+impl SchemaResolver {
+    /// Creates a resolver fetching schemas over HTTP from a remote Iglu repository
+    ///
+    /// A schema `iglu:{vendor}/{name}/{format}/{version}` is fetched from `{base_url}/schemas/{vendor}/{name}/{format}/{version}`,
+    /// e.g. with a `base_url` of `http://iglucentral.com` for a static repository, or `https://iglu.example.com/api` for an Iglu Server.
+    pub fn remote(base_url: &str) -> SchemaResolver {
+        SchemaResolver {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Validates the data of a [SelfDescribingJson] against its schema
+    ///
+    /// Returns [Error::ValidationError] if the data doesn't conform to the schema,
+    /// or if the schema couldn't be resolved.
+    pub fn validate(&self, json: &SelfDescribingJson) -> Result<(), Error> {
+        let schema = self.resolve(&json.schema)?;
+
+        if let Err(errors) = schema.validate(&json.data) {
+            let errors: Vec<String> = errors.map(|e| e.to_string()).collect();
+            return Err(Error::ValidationError(format!(
+                "Data does not conform to {}: {}",
+                json.schema,
+                errors.join(", ")
+            )));
+        }
+
+        Ok(())
+    }
+
+    // Returns the compiled schema from the cache, fetching it if it hasn't been resolved before
+    fn resolve(&self, schema_uri: &str) -> Result<Arc<JSONSchema>, Error> {
+        if let Some(schema) = self.lock_cache()?.get(schema_uri) {
+            return Ok(schema.clone());
+        }
+
+        // The lock isn't held while fetching, so a schema may occasionally be fetched more than once
+        let schema = self.fetch(schema_uri)?;
+        let compiled = JSONSchema::options()
+            .with_draft(Draft::Draft4)
+            .compile(&schema)
+            .map_err(|e| Error::ValidationError(format!("Invalid schema {schema_uri}: {e}")))?;
+
+        let compiled = Arc::new(compiled);
+        self.lock_cache()?
+            .insert(schema_uri.to_string(), compiled.clone());
+        Ok(compiled)
+    }
+
+    fn lock_cache(
+        &self,
+    ) -> Result<std::sync::MutexGuard<'_, HashMap<String, Arc<JSONSchema>>>, Error> {
+        self.cache
+            .lock()
+            .map_err(|e| Error::ValidationError(format!("Failed to lock schema cache: {e}")))
+    }
+
+    fn fetch(&self, schema_uri: &str) -> Result<Value, Error> {
+        let path = match schema_uri.strip_prefix("iglu:") {
+            Some(path) => path,
+            None => {
+                return Err(Error::ValidationError(format!(
+                    "Not an Iglu schema URI: {schema_uri}"
+                )))
+            }
+        };
+        let url = format!("{}/schemas/{path}", self.base_url);
+
+        // Tracking is synchronous, and may happen inside or outside of a tokio runtime,
+        // so the request is made on a separate thread with its own runtime
+        let request = std::thread::spawn(move || -> Result<Value, String> {
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(|e| e.to_string())?;
+
+            rt.block_on(async {
+                let resp = reqwest::get(&url).await.map_err(|e| e.to_string())?;
+                let resp = resp.error_for_status().map_err(|e| e.to_string())?;
+                resp.json::<Value>().await.map_err(|e| e.to_string())
+            })
+        });
+
+        match request.join() {
+            Ok(Ok(schema)) => Ok(schema),
+            Ok(Err(e)) => Err(Error::ValidationError(format!(
+                "Failed to fetch schema {schema_uri}: {e}"
+            ))),
+            Err(_) => Err(Error::ValidationError(format!(
+                "Failed to fetch schema {schema_uri}"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use std::sync::mpsc::Receiver;
+
+    use crate::http_client::mock_collector::{mock_collector, MockRequest};
+
+    use super::*;
+
+    const SCHEMA: &str = "iglu:com.acme/button_click/jsonschema/1-0-0";
+
+    fn mock_iglu_server() -> (String, Receiver<MockRequest>) {
+        mock_collector(|request| {
+            match request.path.as_str() {
+            "/schemas/com.acme/button_click/jsonschema/1-0-0" => (
+                200,
+                json!({
+                    "$schema": "http://iglucentral.com/schemas/com.snowplowanalytics.self-desc/schema/jsonschema/1-0-0#",
+                    "self": {
+                        "vendor": "com.acme",
+                        "name": "button_click",
+                        "format": "jsonschema",
+                        "version": "1-0-0"
+                    },
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string" }
+                    },
+                    "required": ["id"],
+                    "additionalProperties": false
+                })
+                .to_string(),
+            ),
+            _ => (404, String::new()),
+        }
+        })
+    }
+
+    #[test]
+    fn validates_against_fetched_schema_and_caches_it() {
+        let (url, requests) = mock_iglu_server();
+        let resolver = SchemaResolver::remote(&url);
+
+        let valid = SelfDescribingJson::new(SCHEMA, json!({ "id": "buy" }));
+        let invalid = SelfDescribingJson::new(SCHEMA, json!({ "label": "buy" }));
+
+        assert!(resolver.validate(&valid).is_ok());
+        assert!(matches!(
+            resolver.validate(&invalid),
+            Err(Error::ValidationError(_))
+        ));
+
+        let requests: Vec<_> = requests.try_iter().collect();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].path,
+            "/schemas/com.acme/button_click/jsonschema/1-0-0"
+        );
+    }
+
+    #[test]
+    fn unresolvable_schema_fails_validation() {
+        let (url, _requests) = mock_iglu_server();
+        let resolver = SchemaResolver::remote(&url);
+
+        let json = SelfDescribingJson::new("iglu:com.acme/unknown/jsonschema/1-0-0", json!({}));
+
+        assert!(matches!(
+            resolver.validate(&json),
+            Err(Error::ValidationError(_))
+        ));
+    }
+}
@@ -0,0 +1,257 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::Error;
+
+/// The version of an Iglu schema, `{model}-{revision}-{addition}` in [SchemaVer](https://docs.snowplow.io/docs/pipeline-components-and-applications/iglu/common-architecture/schemaver/)
+///
+/// ## Example
+/// ```
+/// use snowplow_tracker::SchemaVersion;
+///
+/// let version: SchemaVersion = "1-0-2".parse().unwrap();
+/// assert_eq!(version.bump_revision().to_string(), "1-1-0");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SchemaVersion {
+    /// Incremented for breaking changes, which prevent interacting with data of previous versions
+    pub model: u32,
+    /// Incremented for changes which may prevent interacting with some data of previous versions
+    pub revision: u32,
+    /// Incremented for changes compatible with all data of previous versions
+    pub addition: u32,
+}
+
+impl SchemaVersion {
+    pub fn new(model: u32, revision: u32, addition: u32) -> Self {
+        Self {
+            model,
+            revision,
+            addition,
+        }
+    }
+
+    /// The next model version, e.g. `2-0-0` after `1-2-3`
+    pub fn bump_model(self) -> Self {
+        Self::new(self.model + 1, 0, 0)
+    }
+
+    /// The next revision, e.g. `1-3-0` after `1-2-3`
+    pub fn bump_revision(self) -> Self {
+        Self::new(self.model, self.revision + 1, 0)
+    }
+
+    /// The next addition, e.g. `1-2-4` after `1-2-3`
+    pub fn bump_addition(self) -> Self {
+        Self::new(self.model, self.revision, self.addition + 1)
+    }
+}
+
+impl From<(u32, u32, u32)> for SchemaVersion {
+    fn from((model, revision, addition): (u32, u32, u32)) -> Self {
+        Self::new(model, revision, addition)
+    }
+}
+
+impl Display for SchemaVersion {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}-{}-{}", self.model, self.revision, self.addition)
+    }
+}
+
+impl FromStr for SchemaVersion {
+    type Err = Error;
+
+    /// Parses a version such as `1-0-2`, failing unless it has exactly three numeric parts
+    fn from_str(version: &str) -> Result<Self, Error> {
+        let invalid = || Error::BuilderError(format!("Invalid schema version: {version}"));
+
+        let parts = version
+            .split('-')
+            .map(|part| match part.bytes().all(|b| b.is_ascii_digit()) {
+                true => part.parse::<u32>().map_err(|_| invalid()),
+                false => Err(invalid()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match parts[..] {
+            [model, revision, addition] => Ok(Self::new(model, revision, addition)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// An Iglu schema URI, `iglu:{vendor}/{name}/{format}/{version}`, split into its parts
+///
+/// ## Example
+/// ```
+/// use snowplow_tracker::SchemaUri;
+///
+/// let uri: SchemaUri = "iglu:com.acme/button_click/jsonschema/1-0-0".parse().unwrap();
+/// assert_eq!(uri.name, "button_click");
+///
+/// let next = uri.with_version(uri.version.bump_addition());
+/// assert_eq!(next.to_string(), "iglu:com.acme/button_click/jsonschema/1-0-1");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SchemaUri {
+    /// The organization owning the schema, e.g. `com.acme`
+    pub vendor: String,
+    /// The name of the schema, e.g. `button_click`
+    pub name: String,
+    /// The format of the schema, usually `jsonschema`
+    pub format: String,
+    pub version: SchemaVersion,
+}
+
+impl SchemaUri {
+    /// The same schema, at another version
+    pub fn with_version(&self, version: impl Into<SchemaVersion>) -> Self {
+        Self {
+            version: version.into(),
+            ..self.clone()
+        }
+    }
+
+    /// Parses a schema URI without its version, `iglu:{vendor}/{name}/{format}`, and adds the version
+    pub fn from_base(base: &str, version: impl Into<SchemaVersion>) -> Result<Self, Error> {
+        let [vendor, name, format] = Self::parts(base)?;
+        Ok(Self {
+            vendor,
+            name,
+            format,
+            version: version.into(),
+        })
+    }
+
+    // Splits the slash-separated parts after the `iglu:` prefix, failing unless there are exactly N non-empty ones
+    fn parts<const N: usize>(uri: &str) -> Result<[String; N], Error> {
+        let invalid =
+            |reason: &str| Error::BuilderError(format!("Invalid schema URI {uri}: {reason}"));
+
+        let path = uri
+            .strip_prefix("iglu:")
+            .ok_or_else(|| invalid("must start with 'iglu:'"))?;
+        let parts: Vec<String> = path.split('/').map(str::to_string).collect();
+        if parts.iter().any(String::is_empty) {
+            return Err(invalid("parts must not be empty"));
+        }
+
+        parts
+            .try_into()
+            .map_err(|_| invalid(&format!("expected {N} parts separated by '/'")))
+    }
+}
+
+impl Display for SchemaUri {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "iglu:{}/{}/{}/{}",
+            self.vendor, self.name, self.format, self.version
+        )
+    }
+}
+
+impl FromStr for SchemaUri {
+    type Err = Error;
+
+    fn from_str(uri: &str) -> Result<Self, Error> {
+        let [vendor, name, format, version] = Self::parts(uri)?;
+        Ok(Self {
+            vendor,
+            name,
+            format,
+            version: version.parse()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::SelfDescribingJson;
+
+    use super::*;
+
+    #[test]
+    fn parses_and_rebuilds_schema_uri() {
+        let uri: SchemaUri = "iglu:com.acme/button_click/jsonschema/1-2-3"
+            .parse()
+            .unwrap();
+
+        assert_eq!(uri.vendor, "com.acme");
+        assert_eq!(uri.name, "button_click");
+        assert_eq!(uri.format, "jsonschema");
+        assert_eq!(uri.version, SchemaVersion::new(1, 2, 3));
+        assert_eq!(
+            uri.to_string(),
+            "iglu:com.acme/button_click/jsonschema/1-2-3"
+        );
+    }
+
+    #[test]
+    fn bumps_schema_version() {
+        let version = SchemaVersion::new(1, 2, 3);
+
+        assert_eq!(version.bump_model(), SchemaVersion::new(2, 0, 0));
+        assert_eq!(version.bump_revision(), SchemaVersion::new(1, 3, 0));
+        assert_eq!(version.bump_addition(), SchemaVersion::new(1, 2, 4));
+        assert!(version < version.bump_addition());
+    }
+
+    #[test]
+    fn rejects_malformed_versions() {
+        for version in ["1-0", "1-0-0-0", "1-a-0", "1--0", "-1-0-0", "+1-0-0", ""] {
+            assert!(version.parse::<SchemaVersion>().is_err(), "{version}");
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_schema_uris() {
+        for uri in [
+            "com.acme/button_click/jsonschema/1-0-0",
+            "iglu:com.acme/button_click/1-0-0",
+            "iglu:com.acme//jsonschema/1-0-0",
+            "iglu:com.acme/button_click/jsonschema/1-0",
+            "iglu:com.acme/button_click/jsonschema/1-0-0/extra",
+        ] {
+            assert!(uri.parse::<SchemaUri>().is_err(), "{uri}");
+        }
+    }
+
+    #[test]
+    fn self_describing_json_with_version() {
+        let json = SelfDescribingJson::with_version(
+            "iglu:com.acme/button_click/jsonschema",
+            (1, 0, 1),
+            json!({ "id": "buy" }),
+        )
+        .unwrap();
+
+        assert_eq!(json.schema, "iglu:com.acme/button_click/jsonschema/1-0-1");
+        assert_eq!(
+            json.schema_uri().unwrap().version,
+            SchemaVersion::new(1, 0, 1)
+        );
+
+        let result = SelfDescribingJson::with_version(
+            "iglu:com.acme/button_click/jsonschema/1-0-0",
+            (1, 0, 1),
+            json!({}),
+        );
+        assert!(matches!(result, Err(Error::BuilderError(_))));
+    }
+}
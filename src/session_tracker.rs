@@ -0,0 +1,179 @@
+// Copyright (c) 2022 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0,
+// and you may not use this file except in compliance with the Apache License Version 2.0.
+// You may obtain a copy of the Apache License Version 2.0 at http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the Apache License Version 2.0 is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// Keeps track of the current session, setting the session ID and index on the subject of every event
+///
+/// A new session starts with a new session ID and an incremented index, either when
+/// [SessionTracker::new_session] is called, when the maximum number of events per session is reached,
+/// or when the app returns to the foreground after longer than the background timeout.
+/// Set it on a tracker with [Tracker::set_session_tracker](crate::Tracker::set_session_tracker).
+///
+/// ## Example
+/// ```
+/// use snowplow_tracker::SessionTracker;
+///
+/// let session_tracker = SessionTracker::new().max_events_per_session(1000);
+///
+/// assert_eq!(session_tracker.session_index(), 1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SessionTracker {
+    session_id: Uuid,
+    session_index: u32,
+    event_count: u32,
+    max_events_per_session: Option<u32>,
+    background_timeout: Option<Duration>,
+    backgrounded_at: Option<DateTime<Utc>>,
+}
+
+impl Default for SessionTracker {
+    fn default() -> Self {
+        Self {
+            session_id: Uuid::new_v4(),
+            session_index: 1,
+            event_count: 0,
+            max_events_per_session: None,
+            background_timeout: None,
+            backgrounded_at: None,
+        }
+    }
+}
+
+impl SessionTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of events in a session, after which a new session is started
+    ///
+    /// This bounds the size of sessions produced by runaway instrumentation.
+    pub fn max_events_per_session(mut self, max_events_per_session: u32) -> Self {
+        self.max_events_per_session = Some(max_events_per_session);
+        self
+    }
+
+    /// Set how long the app can be in the background before a new session is started when it returns to the foreground
+    ///
+    /// See [Tracker::on_background](crate::Tracker::on_background). Without a timeout, sessions continue across the background.
+    pub fn background_timeout(mut self, background_timeout: Duration) -> Self {
+        self.background_timeout = Some(background_timeout);
+        self
+    }
+
+    /// Whether the app is in the background, between [Tracker::on_background](crate::Tracker::on_background)
+    /// and [Tracker::on_foreground](crate::Tracker::on_foreground)
+    pub fn is_in_background(&self) -> bool {
+        self.backgrounded_at.is_some()
+    }
+
+    /// The ID of the current session, sent as `sid`
+    pub fn session_id(&self) -> Uuid {
+        self.session_id
+    }
+
+    /// The index of the current session, starting at 1 and sent as `vid`
+    pub fn session_index(&self) -> u32 {
+        self.session_index
+    }
+
+    /// Starts a new session, with a new session ID and an incremented index
+    pub fn new_session(&mut self) {
+        self.session_id = Uuid::new_v4();
+        self.session_index += 1;
+        self.event_count = 0;
+    }
+
+    // Marks the app as in the background since `now`, unless it already was
+    pub(crate) fn background(&mut self, now: DateTime<Utc>) {
+        self.backgrounded_at.get_or_insert(now);
+    }
+
+    // Marks the app as back in the foreground at `now`, starting a new session if the background timeout passed
+    pub(crate) fn foreground(&mut self, now: DateTime<Utc>) {
+        let (Some(backgrounded_at), Some(background_timeout)) =
+            (self.backgrounded_at.take(), self.background_timeout)
+        else {
+            return;
+        };
+
+        let in_background = (now - backgrounded_at).to_std().unwrap_or_default();
+        if in_background > background_timeout {
+            log::debug!("App was in the background for {in_background:?}, starting a new session");
+            self.new_session();
+        }
+    }
+
+    // Counts an event, returning the ID and index of the session it belongs to
+    pub(crate) fn next_event(&mut self) -> (Uuid, u32) {
+        if let Some(max_events) = self.max_events_per_session {
+            if self.event_count >= max_events {
+                log::debug!("Session reached {max_events} events, starting a new session");
+                self.new_session();
+            }
+        }
+
+        self.event_count += 1;
+        (self.session_id, self.session_index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_share_a_session_until_the_limit() {
+        let mut session_tracker = SessionTracker::new().max_events_per_session(2);
+
+        let (first_id, first_index) = session_tracker.next_event();
+        let (second_id, second_index) = session_tracker.next_event();
+        let (third_id, third_index) = session_tracker.next_event();
+
+        assert_eq!(first_id, second_id);
+        assert_eq!((first_index, second_index), (1, 1));
+        assert_ne!(third_id, first_id);
+        assert_eq!(third_index, 2);
+    }
+
+    #[test]
+    fn sessions_are_unbounded_by_default() {
+        let mut session_tracker = SessionTracker::new();
+
+        let (session_id, _) = session_tracker.next_event();
+        for _ in 0..100 {
+            session_tracker.next_event();
+        }
+
+        assert_eq!(session_tracker.session_id(), session_id);
+        assert_eq!(session_tracker.session_index(), 1);
+    }
+
+    #[test]
+    fn returning_after_the_background_timeout_starts_a_new_session() {
+        let mut session_tracker = SessionTracker::new().background_timeout(Duration::from_secs(60));
+        let start = Utc::now();
+
+        session_tracker.background(start);
+        assert!(session_tracker.is_in_background());
+        session_tracker.foreground(start + chrono::Duration::seconds(30));
+        assert!(!session_tracker.is_in_background());
+        assert_eq!(session_tracker.session_index(), 1);
+
+        session_tracker.background(start);
+        session_tracker.foreground(start + chrono::Duration::seconds(90));
+        assert_eq!(session_tracker.session_index(), 2);
+    }
+}
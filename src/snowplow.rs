@@ -9,22 +9,121 @@
 // "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
 
+#[cfg(not(target_arch = "wasm32"))]
 use crate::emitter::BatchEmitter;
+#[cfg(target_arch = "wasm32")]
+use crate::emitter::WasmEmitter;
+use crate::error::Error;
 use crate::subject::Subject;
 use crate::tracker::Tracker;
 
+const COLLECTOR_URL_VAR: &str = "SNOWPLOW_COLLECTOR_URL";
+const APP_ID_VAR: &str = "SNOWPLOW_APP_ID";
+const NAMESPACE_VAR: &str = "SNOWPLOW_NAMESPACE";
+const PLATFORM_VAR: &str = "SNOWPLOW_PLATFORM";
+
 /// Main interface for the package, used to initialize trackers.
 pub struct Snowplow;
 
 impl Snowplow {
     /// Creates a new [Tracker] instance
+    ///
+    /// Events are sent with a `BatchEmitter`, or a `WasmEmitter` on `wasm32` targets.
     pub fn create_tracker(
         namespace: &str,
         app_id: &str,
         collector_url: &str,
         subject: Option<Subject>,
     ) -> Tracker {
+        #[cfg(not(target_arch = "wasm32"))]
         let emitter = BatchEmitter::new(collector_url);
+        #[cfg(target_arch = "wasm32")]
+        let emitter = WasmEmitter::new(collector_url);
         Tracker::new(namespace, app_id, emitter, subject)
     }
+
+    /// Creates a new [Tracker] instance configured from environment variables
+    ///
+    /// The following variables are read:
+    /// - `SNOWPLOW_COLLECTOR_URL` (required)
+    /// - `SNOWPLOW_APP_ID` (required)
+    /// - `SNOWPLOW_NAMESPACE` (required)
+    /// - `SNOWPLOW_PLATFORM` (optional, defaults to `pc`)
+    ///
+    /// Returns an [Error::EmitterError] listing every required variable that is not set.
+    pub fn from_env() -> Result<Tracker, Error> {
+        let read_var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+
+        let collector_url = read_var(COLLECTOR_URL_VAR);
+        let app_id = read_var(APP_ID_VAR);
+        let namespace = read_var(NAMESPACE_VAR);
+
+        match (collector_url, app_id, namespace) {
+            (Some(collector_url), Some(app_id), Some(namespace)) => {
+                let mut tracker =
+                    Snowplow::create_tracker(&namespace, &app_id, &collector_url, None);
+                if let Some(platform) = read_var(PLATFORM_VAR) {
+                    tracker.set_platform(&platform);
+                }
+                Ok(tracker)
+            }
+            (collector_url, app_id, namespace) => {
+                let missing: Vec<&str> = [
+                    (COLLECTOR_URL_VAR, collector_url.is_none()),
+                    (APP_ID_VAR, app_id.is_none()),
+                    (NAMESPACE_VAR, namespace.is_none()),
+                ]
+                .iter()
+                .filter(|(_, is_missing)| *is_missing)
+                .map(|(name, _)| *name)
+                .collect();
+
+                Err(Error::EmitterError(format!(
+                    "Missing environment variables: {}",
+                    missing.join(", ")
+                )))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Environment variables are shared by the whole test process, so all cases
+    // are run sequentially in a single test
+    #[test]
+    fn create_tracker_from_env() {
+        std::env::remove_var(COLLECTOR_URL_VAR);
+        std::env::remove_var(APP_ID_VAR);
+        std::env::remove_var(NAMESPACE_VAR);
+        std::env::remove_var(PLATFORM_VAR);
+
+        std::env::set_var(APP_ID_VAR, "env_app_id");
+        let err = Snowplow::from_env().err().unwrap();
+        assert!(matches!(err, Error::EmitterError(_)));
+        assert_eq!(
+            err.to_string(),
+            "Missing environment variables: SNOWPLOW_COLLECTOR_URL, SNOWPLOW_NAMESPACE"
+        );
+
+        std::env::set_var(COLLECTOR_URL_VAR, "http://example.com");
+        std::env::set_var(NAMESPACE_VAR, "env_ns");
+        std::env::set_var(PLATFORM_VAR, "srv");
+
+        let mut tracker = Snowplow::from_env().unwrap();
+
+        assert_eq!(tracker.namespace(), "env_ns");
+        assert_eq!(tracker.app_id(), "env_app_id");
+        assert_eq!(tracker.emitter().collector_url(), "http://example.com");
+        assert_eq!(tracker.platform(), "srv");
+
+        tracker.close_emitter().unwrap();
+
+        std::env::remove_var(COLLECTOR_URL_VAR);
+        std::env::remove_var(APP_ID_VAR);
+        std::env::remove_var(NAMESPACE_VAR);
+        std::env::remove_var(PLATFORM_VAR);
+    }
 }
@@ -9,10 +9,15 @@
 // "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
 
+use std::net::IpAddr;
+
+use chrono_tz::Tz;
 use derive_builder::Builder;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use uuid::Uuid;
 
+use crate::Error;
+
 /// Subject allows you to attach additional information about your application's environment.
 ///
 /// A Subject can be attached to:
@@ -20,29 +25,32 @@ use uuid::Uuid;
 /// - An Event itself, with the Event-level Subject fields taking priority over Tracker-level (if present)
 #[derive(Serialize, Deserialize, Builder, Default, Clone, Debug)]
 #[builder(setter(into, strip_option), default)]
+#[builder(build_fn(validate = "Self::validate", error = "Error"))]
 pub struct Subject {
     /// Unique identifier for user
     #[serde(rename(serialize = "uid"))]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user_id: Option<String>,
 
-    /// The timezone label.
+    /// The timezone label, an IANA time zone database name such as `Europe/London`.
     ///
-    /// Populates the `os_timezone` field.
+    /// Populates the `os_timezone` field. Building the subject fails for unknown names.
     #[serde(rename(serialize = "tz"))]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub timezone: Option<String>,
 
-    /// The language set on the device.
+    /// The language set on the device, as a BCP 47 language tag such as `en-GB`.
     ///
-    /// Populates the `lang` field.
+    /// Populates the `lang` field. The builder normalizes the tag, so `en_gb` is stored as `en-GB`,
+    /// and building the subject fails for malformed tags.
+    #[builder(setter(custom))]
     #[serde(rename(serialize = "lang"))]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub language: Option<String>,
 
     /// Custom IP address. It overrides the IP address used by default.
     ///
-    /// Populates the `user_ipaddress` field.
+    /// Populates the `user_ipaddress` field. Building the subject fails unless it is a valid IPv4 or IPv6 address.
     #[serde(rename(serialize = "ip"))]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ip_address: Option<String>,
@@ -77,6 +85,136 @@ pub struct Subject {
     #[serde(rename(serialize = "sid"))]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub session_user_id: Option<Uuid>,
+
+    /// Domain session index, the number of the current visit of the user.
+    ///
+    /// Populates the `domain_sessionidx` field.
+    /// Typically used with `session_user_id` to link native tracking to events tracked using the JavaScript Tracker.
+    #[serde(rename(serialize = "vid"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "optional_u32_to_string")]
+    pub domain_session_index: Option<u32>,
+
+    /// Screen resolution of the device, as `(width, height)` in pixels.
+    ///
+    /// Populates the `dvce_screenwidth` and `dvce_screenheight` fields.
+    #[serde(rename(serialize = "res"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "optional_dimensions_to_string")]
+    pub screen_resolution: Option<(u32, u32)>,
+
+    /// Viewport dimensions of the browser or app window, as `(width, height)` in pixels.
+    ///
+    /// Populates the `br_viewwidth` and `br_viewheight` fields.
+    #[serde(rename(serialize = "vp"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "optional_dimensions_to_string")]
+    pub viewport: Option<(u32, u32)>,
+
+    /// Color depth of the screen, in bits per pixel.
+    ///
+    /// Populates the `br_colordepth` field.
+    #[serde(rename(serialize = "cd"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "optional_u32_to_string")]
+    pub color_depth: Option<u32>,
+}
+
+// Serializer to convert the optional u32 to the JSON `String` type
+// expected by the collector, rather than the default JSON `Number`
+fn optional_u32_to_string<S>(num: &Option<u32>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    if let Some(num) = num {
+        serializer.serialize_str(&num.to_string())
+    } else {
+        serializer.serialize_none()
+    }
+}
+
+// Serializer to convert optional `(width, height)` dimensions to the `WxH` format expected by the collector
+fn optional_dimensions_to_string<S>(
+    dimensions: &Option<(u32, u32)>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    if let Some((width, height)) = dimensions {
+        serializer.serialize_str(&format!("{width}x{height}"))
+    } else {
+        serializer.serialize_none()
+    }
+}
+
+// Normalizes the case and separators of a language tag to the conventional BCP 47 form:
+// the language is lowercase, a 4-letter script is titlecase, a 2-letter region is uppercase,
+// and subtags are separated by `-`, e.g. `zh_hant_tw` becomes `zh-Hant-TW`
+fn normalize_language_tag(tag: &str) -> String {
+    tag.trim()
+        .split(['-', '_'])
+        .enumerate()
+        .map(|(i, subtag)| match subtag.len() {
+            _ if i == 0 => subtag.to_ascii_lowercase(),
+            2 => subtag.to_ascii_uppercase(),
+            4 if subtag.chars().all(|c| c.is_ascii_alphabetic()) => {
+                let (first, rest) = subtag.split_at(1);
+                first.to_ascii_uppercase() + &rest.to_ascii_lowercase()
+            }
+            _ => subtag.to_ascii_lowercase(),
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+// Whether the tag is well-formed: a language of 2 to 8 letters, then subtags of 1 to 8 letters or digits
+fn is_valid_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    let language_is_valid = subtags.next().is_some_and(|language| {
+        (2..=8).contains(&language.len()) && language.chars().all(|c| c.is_ascii_alphabetic())
+    });
+
+    language_is_valid
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+impl SubjectBuilder {
+    /// The language set on the device, normalized to BCP 47 form, e.g. `en_gb` becomes `en-GB`
+    pub fn language<VALUE: Into<String>>(&mut self, value: VALUE) -> &mut Self {
+        self.language = Some(Some(normalize_language_tag(&value.into())));
+        self
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if let Some(Some(language)) = &self.language {
+            if !is_valid_language_tag(language) {
+                return Err(Error::BuilderError(format!(
+                    "Invalid language: {language}, expected a BCP 47 language tag"
+                )));
+            }
+        }
+
+        if let Some(Some(ip_address)) = &self.ip_address {
+            if ip_address.parse::<IpAddr>().is_err() {
+                return Err(Error::BuilderError(format!(
+                    "Invalid IP address: {ip_address}"
+                )));
+            }
+        }
+
+        if let Some(Some(timezone)) = &self.timezone {
+            if timezone.parse::<Tz>().is_err() {
+                return Err(Error::BuilderError(format!(
+                    "Invalid timezone: {timezone}, expected an IANA time zone name"
+                )));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl Subject {
@@ -86,6 +224,10 @@ impl Subject {
 
     /// Merges another instance of [Subject], with self taking priority
     ///
+    /// Each field set on `self` is kept, and each field not set on `self` is taken from `other`.
+    /// Fields are merged independently, so the result can combine fields of both subjects.
+    /// This is how an event's subject is merged with the tracker's subject, with the event's fields winning.
+    ///
     /// Also useful in conjunction with [Tracker.subject_mut](crate::Tracker::subject_mut) to update the subject field, without replacing
     ///
     /// ## Example
@@ -98,7 +240,7 @@ impl Subject {
     /// let merged_subject = priority_subject.merge(subject_to_merge);
     ///
     /// assert_eq!(merged_subject.user_id, Some("user_1".to_string()));
-    /// assert_eq!(merged_subject.language, Some("en-gb".to_string()));
+    /// assert_eq!(merged_subject.language, Some("en-GB".to_string()));
     ///```
     pub fn merge(self, other: Subject) -> Self {
         Self {
@@ -110,6 +252,58 @@ impl Subject {
             domain_user_id: self.domain_user_id.or(other.domain_user_id),
             network_user_id: self.network_user_id.or(other.network_user_id),
             session_user_id: self.session_user_id.or(other.session_user_id),
+            domain_session_index: self.domain_session_index.or(other.domain_session_index),
+            screen_resolution: self.screen_resolution.or(other.screen_resolution),
+            viewport: self.viewport.or(other.viewport),
+            color_depth: self.color_depth.or(other.color_depth),
+        }
+    }
+
+    /// Merges another instance of [Subject] the same as [Subject::merge], without consuming either subject
+    ///
+    /// ## Example
+    /// ```
+    /// use snowplow_tracker::Subject;
+    ///
+    /// let event_subject = Subject::builder().user_id("user_1").build().unwrap();
+    /// let tracker_subject = Subject::builder().user_id("user_2").language("en-gb").build().unwrap();
+    ///
+    /// let merged_subject = event_subject.merge_ref(&tracker_subject);
+    ///
+    /// assert_eq!(merged_subject.user_id, Some("user_1".to_string()));
+    /// assert_eq!(tracker_subject.user_id, Some("user_2".to_string()));
+    ///```
+    pub fn merge_ref(&self, other: &Subject) -> Subject {
+        self.clone().merge(other.clone())
+    }
+
+    /// Whether none of the fields are set, in which case the subject adds nothing to an event
+    pub fn is_empty(&self) -> bool {
+        self.user_id.is_none()
+            && self.timezone.is_none()
+            && self.language.is_none()
+            && self.ip_address.is_none()
+            && self.user_agent.is_none()
+            && self.domain_user_id.is_none()
+            && self.network_user_id.is_none()
+            && self.session_user_id.is_none()
+            && self.domain_session_index.is_none()
+            && self.screen_resolution.is_none()
+            && self.viewport.is_none()
+            && self.color_depth.is_none()
+    }
+
+    /// Removes the fields that identify the user, for anonymous tracking
+    ///
+    /// The user ID, IP address, and domain, network, and session user IDs are removed.
+    pub fn anonymised(self) -> Self {
+        Self {
+            user_id: None,
+            ip_address: None,
+            domain_user_id: None,
+            network_user_id: None,
+            session_user_id: None,
+            ..self
         }
     }
 }
@@ -132,6 +326,7 @@ mod test {
             .domain_user_id(domain_user_id)
             .network_user_id(network_user_id)
             .session_user_id(session_user_id)
+            .domain_session_index(3_u32)
             .build()
             .unwrap();
 
@@ -143,6 +338,7 @@ mod test {
         assert_eq!(domain_user_id, subject.domain_user_id.unwrap());
         assert_eq!(network_user_id, subject.network_user_id.unwrap());
         assert_eq!(session_user_id, subject.session_user_id.unwrap());
+        assert_eq!(3, subject.domain_session_index.unwrap());
     }
 
     #[test]
@@ -161,6 +357,21 @@ mod test {
         assert!(subject.domain_user_id.is_none());
         assert!(subject.network_user_id.is_none());
         assert!(subject.session_user_id.is_none());
+        assert!(subject.domain_session_index.is_none());
+    }
+
+    #[test]
+    fn user_agent_serializes_as_ua_only_when_set() {
+        let subject = Subject::builder()
+            .user_agent("Mozilla/5.0 (X11; Linux x86_64)")
+            .build()
+            .unwrap();
+
+        let with_user_agent = serde_json::to_value(&subject).unwrap();
+        let without_user_agent = serde_json::to_value(Subject::default()).unwrap();
+
+        assert_eq!(with_user_agent["ua"], "Mozilla/5.0 (X11; Linux x86_64)");
+        assert!(without_user_agent.get("ua").is_none());
     }
 
     #[test]
@@ -168,13 +379,257 @@ mod test {
         let sub_with_priority = Subject::builder().user_id("user_1").build().unwrap();
         let sub_to_merge = Subject::builder()
             .user_id("user_2")
-            .ip_address("999.999.999.999")
+            .ip_address("203.0.113.7")
             .build()
             .unwrap();
 
         let merged = sub_with_priority.merge(sub_to_merge);
 
         assert_eq!(merged.user_id.unwrap(), "user_1");
-        assert_eq!(merged.ip_address.unwrap(), "999.999.999.999");
+        assert_eq!(merged.ip_address.unwrap(), "203.0.113.7");
+    }
+
+    // Whether a field is set on the first subject, the second subject, or both
+    #[derive(Clone, Copy)]
+    enum Overlap {
+        Neither,
+        First,
+        Second,
+        Both,
+    }
+
+    // A subject with some fields set, to `value` in each
+    fn subject_with(user_id: bool, language: bool, color_depth: bool, value: u32) -> Subject {
+        Subject {
+            user_id: user_id.then(|| format!("user_{value}")),
+            language: language.then(|| format!("lang_{value}")),
+            color_depth: color_depth.then_some(value),
+            ..Subject::default()
+        }
+    }
+
+    #[test]
+    fn merge_keeps_set_fields_and_fills_unset_fields() {
+        use Overlap::*;
+
+        let cases = [
+            // All unset
+            [Neither, Neither, Neither],
+            // Partial overlaps
+            [First, Second, Neither],
+            [Both, First, Second],
+            [Second, Both, First],
+            // Full overlap
+            [Both, Both, Both],
+            // No overlap
+            [First, First, First],
+            [Second, Second, Second],
+        ];
+
+        for overlaps in cases {
+            let in_first = overlaps.map(|o| matches!(o, First | Both));
+            let in_second = overlaps.map(|o| matches!(o, Second | Both));
+            let first = subject_with(in_first[0], in_first[1], in_first[2], 1);
+            let second = subject_with(in_second[0], in_second[1], in_second[2], 2);
+
+            // The first subject wins where set, and the second fills the rest
+            let expected = overlaps.map(|o| match o {
+                Neither => None,
+                First | Both => Some(1),
+                Second => Some(2),
+            });
+
+            let by_ref = first.merge_ref(&second);
+            let merged = first.merge(second);
+
+            for subject in [&merged, &by_ref] {
+                assert_eq!(subject.user_id, expected[0].map(|v| format!("user_{v}")));
+                assert_eq!(subject.language, expected[1].map(|v| format!("lang_{v}")));
+                assert_eq!(subject.color_depth, expected[2]);
+                assert!(subject.ip_address.is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn merging_with_empty_subject_changes_nothing() {
+        let subject = subject_with(true, true, true, 1);
+
+        let merged = subject.merge_ref(&Subject::default());
+        let filled = Subject::default().merge_ref(&subject);
+
+        for merged in [merged, filled] {
+            assert_eq!(
+                serde_json::to_value(merged).unwrap(),
+                serde_json::to_value(&subject).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn valid_ip_addresses_are_accepted() {
+        for ip_address in ["203.0.113.7", "0.0.0.0", "2001:db8::1", "::1"] {
+            let subject = Subject::builder().ip_address(ip_address).build().unwrap();
+            assert_eq!(subject.ip_address.unwrap(), ip_address);
+        }
+    }
+
+    #[test]
+    fn invalid_ip_addresses_are_rejected() {
+        for ip_address in ["999.999.999.999", "1.2.3", "2001:db8::g", "localhost", ""] {
+            let result = Subject::builder().ip_address(ip_address).build();
+            assert!(matches!(result, Err(Error::BuilderError(_))));
+        }
+    }
+
+    #[test]
+    fn timezone_must_be_an_iana_name() {
+        let subject = Subject::builder()
+            .timezone("America/New_York")
+            .build()
+            .unwrap();
+        assert_eq!(subject.timezone.unwrap(), "America/New_York");
+
+        let result = Subject::builder().timezone("Mars/Olympus_Mons").build();
+        assert!(matches!(result, Err(Error::BuilderError(_))));
+    }
+
+    #[test]
+    fn unset_ip_address_and_timezone_are_omitted() {
+        let subject = Subject::builder().user_id("user_1").build().unwrap();
+
+        let serialized = serde_json::to_value(subject).unwrap();
+
+        assert!(serialized.get("ip").is_none());
+        assert!(serialized.get("tz").is_none());
+    }
+
+    #[test]
+    fn language_is_normalized_to_bcp_47() {
+        let cases = [
+            ("en", "en"),
+            ("EN", "en"),
+            ("en_GB", "en-GB"),
+            ("en-gb", "en-GB"),
+            ("En_gB", "en-GB"),
+            ("zh_hant_tw", "zh-Hant-TW"),
+            ("es-419", "es-419"),
+            (" fr-ca ", "fr-CA"),
+        ];
+
+        for (language, expected) in cases {
+            let subject = Subject::builder().language(language).build().unwrap();
+            assert_eq!(subject.language.unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn malformed_language_is_rejected() {
+        for language in [
+            "",
+            "e",
+            "en--gb",
+            "en-",
+            "en gb",
+            "en-toolongsubtag",
+            "12-GB",
+        ] {
+            let result = Subject::builder().language(language).build();
+            assert!(matches!(result, Err(Error::BuilderError(_))));
+        }
+    }
+
+    #[test]
+    fn normalized_language_serializes_as_lang() {
+        let subject = Subject::builder().language("pt_br").build().unwrap();
+
+        let serialized = serde_json::to_value(subject).unwrap();
+
+        assert_eq!(serialized["lang"], "pt-BR");
+    }
+
+    #[test]
+    fn anonymised_subject_removes_user_identifiers() {
+        let subject = Subject::builder()
+            .user_id("user_1")
+            .ip_address("0.0.0.0")
+            .language("en")
+            .domain_user_id(Uuid::new_v4())
+            .network_user_id(Uuid::new_v4())
+            .session_user_id(Uuid::new_v4())
+            .build()
+            .unwrap()
+            .anonymised();
+
+        assert!(subject.user_id.is_none());
+        assert!(subject.ip_address.is_none());
+        assert!(subject.domain_user_id.is_none());
+        assert!(subject.network_user_id.is_none());
+        assert!(subject.session_user_id.is_none());
+        assert_eq!(subject.language.unwrap(), "en");
+    }
+
+    #[test]
+    fn user_and_session_ids_serialize_to_protocol_keys() {
+        let domain_user_id = Uuid::new_v4();
+        let network_user_id = Uuid::new_v4();
+        let session_user_id = Uuid::new_v4();
+        let subject = Subject::builder()
+            .domain_user_id(domain_user_id)
+            .network_user_id(network_user_id)
+            .session_user_id(session_user_id)
+            .domain_session_index(3_u32)
+            .build()
+            .unwrap();
+
+        let serialized = serde_json::to_value(subject).unwrap();
+
+        assert_eq!(serialized["duid"], domain_user_id.to_string());
+        assert_eq!(serialized["tnuid"], network_user_id.to_string());
+        assert_eq!(serialized["sid"], session_user_id.to_string());
+        assert_eq!(serialized["vid"], "3");
+    }
+
+    #[test]
+    fn screen_dimensions_serialize_as_width_by_height() {
+        let subject = Subject::builder()
+            .screen_resolution((1920, 1080))
+            .viewport((1280, 720))
+            .color_depth(24_u32)
+            .build()
+            .unwrap();
+
+        let serialized = serde_json::to_value(subject).unwrap();
+
+        assert_eq!(serialized["res"], "1920x1080");
+        assert_eq!(serialized["vp"], "1280x720");
+        assert_eq!(serialized["cd"], "24");
+    }
+
+    #[test]
+    fn unset_screen_dimensions_are_omitted() {
+        let subject = Subject::builder().user_id("user_1").build().unwrap();
+
+        let serialized = serde_json::to_value(subject).unwrap();
+
+        assert!(serialized.get("res").is_none());
+        assert!(serialized.get("vp").is_none());
+        assert!(serialized.get("cd").is_none());
+    }
+
+    #[test]
+    fn is_empty_when_no_fields_are_set() {
+        assert!(Subject::default().is_empty());
+        assert!(!Subject::builder()
+            .color_depth(24_u32)
+            .build()
+            .unwrap()
+            .is_empty());
+        assert!(Subject::builder()
+            .user_id("user_1")
+            .build()
+            .unwrap()
+            .anonymised()
+            .is_empty());
     }
 }
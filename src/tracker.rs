@@ -9,19 +9,111 @@
 // "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the Apache License Version 2.0 for the specific language governing permissions and limitations there under.
 
-use chrono::Utc;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde_json::json;
 use uuid::Uuid;
 
+use crate::application_context::ApplicationContext;
+use crate::clock::{Clock, SystemClock};
+use crate::coalescer::EventCoalescer;
+use crate::deduplicator::EventDeduplicator;
+use crate::desktop_context::DesktopContext;
 use crate::emitter::Emitter;
 use crate::error::Error;
-use crate::event::PayloadAddable;
-use crate::payload::{ContextData, Payload, SelfDescribingJson};
+use crate::event::{EventWithContext, PayloadAddable};
+use crate::event::{SelfDescribingEvent, StructuredEvent};
+use crate::field_limits::FieldLimits;
+use crate::gdpr_context::GdprContext;
+use crate::geolocation_context::GeolocationContext;
+use crate::mobile_context::MobileContext;
+use crate::payload::{
+    ContextData, ContextDeduplication, ContextEncoding, NumericEncoding, Payload, PayloadBuilder,
+    SelfDescribingJson,
+};
+use crate::pii::{PiiField, PiiHashing};
+use crate::platform_contexts::PlatformContexts;
+#[cfg(feature = "schema-validation")]
+use crate::schema_resolver::SchemaResolver;
+use crate::session_tracker::SessionTracker;
 use crate::subject::Subject;
 
+/// The schema of the event tracked by [Tracker::on_background]
+const APPLICATION_BACKGROUND_SCHEMA: &str =
+    "iglu:com.snowplowanalytics.snowplow/application_background/jsonschema/1-0-0";
+
+/// The schema of the event tracked by [Tracker::on_foreground]
+const APPLICATION_FOREGROUND_SCHEMA: &str =
+    "iglu:com.snowplowanalytics.snowplow/application_foreground/jsonschema/1-0-0";
+
+/// The tracker version (`tv`) sent with events, unless set otherwise with [Tracker::set_tracker_version]
+const DEFAULT_TRACKER_VERSION: &str = concat!("rust-", env!("CARGO_PKG_VERSION"));
+
 pub struct TrackerConfig {
     pub platform: String,
     pub version: String,
     pub encode_base_64: bool,
+    pub numeric_encoding: NumericEncoding,
+    pub context_encoding: ContextEncoding,
+    pub context_deduplication: ContextDeduplication,
+    pub client_anonymisation: bool,
+    /// The environment context entity attached to every event, set with [Tracker::set_environment]
+    pub environment: Option<SelfDescribingJson>,
+    pub field_limits: Option<FieldLimits>,
+    pub gdpr_context: Option<GdprContext>,
+    pub desktop_context: Option<DesktopContext>,
+    pub mobile_context: Option<MobileContext>,
+    pub geolocation_context: Option<GeolocationContext>,
+    pub application_context: Option<ApplicationContext>,
+    pub global_contexts: Vec<SelfDescribingJson>,
+    /// The schema of the diagnostic context entity attached to every event, for debugging only
+    pub diagnostic_context: Option<String>,
+}
+
+impl Default for TrackerConfig {
+    fn default() -> Self {
+        Self {
+            platform: "pc".to_string(),
+            version: DEFAULT_TRACKER_VERSION.to_string(),
+            encode_base_64: false,
+            numeric_encoding: NumericEncoding::default(),
+            context_encoding: ContextEncoding::default(),
+            context_deduplication: ContextDeduplication::default(),
+            client_anonymisation: false,
+            environment: None,
+            field_limits: None,
+            gdpr_context: None,
+            desktop_context: None,
+            mobile_context: None,
+            application_context: None,
+            geolocation_context: None,
+            global_contexts: Vec::new(),
+            diagnostic_context: None,
+        }
+    }
+}
+
+// A user-supplied function producing the subject for each tracked event
+type SubjectProvider = Box<dyn Fn() -> Subject + Send + Sync>;
+
+/// A function called with the payload of every tracked event, set with [Tracker::set_enricher]
+pub type Enricher = Box<dyn Fn(&mut PayloadBuilder) + Send + Sync>;
+
+// A user-supplied function producing the true timestamp of events that don't set one
+type TrueTimestampSource = Box<dyn Fn() -> DateTime<Utc> + Send + Sync>;
+
+/// An event tracked with [Tracker::track_event], along with the payload it was sent as
+#[derive(Debug, Clone)]
+pub struct TrackedEvent {
+    /// The ID of the event, as returned by [Tracker::track]
+    pub event_id: Uuid,
+    /// The final payload, including the merged subject and context entities
+    ///
+    /// The sent timestamp (`stm`) is set again by the emitter when the event is sent.
+    pub payload: Payload,
 }
 
 /// The Snowplow tracker, used to track events
@@ -37,30 +129,196 @@ pub struct Tracker {
     /// The [Subject] that will be applied to all events
     /// An event-level subject will take priority over this
     subject: Subject,
+    /// Produces the subject applied to each event, taking priority over the tracker [Subject], if set
+    subject_provider: Option<SubjectProvider>,
+    /// Produces the true timestamp of events that don't set one, if set
+    true_timestamp_source: Option<TrueTimestampSource>,
+    /// Adds computed fields or context entities to every event, if set
+    enricher: Option<Enricher>,
+    /// Hashes the subject fields identifying the user, if set
+    pii_hashing: Option<PiiHashing>,
+    /// The source of the device created timestamp (`dtm`) of events
+    clock: Arc<dyn Clock>,
+    /// Sets the session ID and index of every event, if set
+    session_tracker: Option<SessionTracker>,
+    /// The number of times the app went to the background, see [Tracker::on_background]
+    background_index: u32,
+    /// The number of times the app returned to the foreground, see [Tracker::on_foreground]
+    foreground_index: u32,
+    /// Suppresses consecutive identical events, if enabled
+    coalescer: Option<EventCoalescer>,
+    /// Skips duplicate events tracked with [Tracker::track_deduped], if enabled
+    deduplicator: Option<EventDeduplicator>,
+    /// Validates events and context entities against their schemas, if set
+    #[cfg(feature = "schema-validation")]
+    schema_resolver: Option<SchemaResolver>,
+}
+
+/// A builder for the [Tracker] struct
+///
+/// The namespace, app ID and emitter are required, everything else is optional.
+///
+/// ## Example
+/// ```
+/// use serde_json::json;
+/// use snowplow_tracker::{MockEmitter, SelfDescribingJson, SessionTracker, Subject, Tracker};
+///
+/// let tracker = Tracker::builder()
+///     .namespace("ns")
+///     .app_id("app_id")
+///     .emitter(MockEmitter::new())
+///     .subject(Subject::builder().language("en-gb").build().unwrap())
+///     .enable_session(SessionTracker::new())
+///     .global_context(SelfDescribingJson::new(
+///         "iglu:com.acme/build/jsonschema/1-0-0",
+///         json!({ "commit": "4f2c1a9" }),
+///     ))
+///     .build()
+///     .unwrap();
+/// assert_eq!(tracker.namespace(), "ns");
+/// ```
+#[derive(Default)]
+pub struct TrackerBuilder {
+    namespace: Option<String>,
+    app_id: Option<String>,
+    emitter: Option<Box<dyn Emitter>>,
+    subject: Option<Subject>,
+    config: TrackerConfig,
+    global_contexts: Vec<SelfDescribingJson>,
+    session_tracker: Option<SessionTracker>,
+    clock: Option<Arc<dyn Clock>>,
+}
+
+impl TrackerBuilder {
+    /// Set the tracker namespace, which identifies the tracker within the app
+    pub fn namespace(mut self, namespace: &str) -> Self {
+        self.namespace = Some(namespace.to_string());
+        self
+    }
+
+    /// Set the application ID (`aid`) sent with every event
+    pub fn app_id(mut self, app_id: &str) -> Self {
+        self.app_id = Some(app_id.to_string());
+        self
+    }
+
+    /// Set the [Emitter] used to send events to the collector
+    pub fn emitter(mut self, emitter: impl Emitter + 'static) -> Self {
+        self.emitter = Some(Box::new(emitter));
+        self
+    }
+
+    /// Set the [Subject] applied to all events, which an event-level subject takes priority over
+    pub fn subject(mut self, subject: Subject) -> Self {
+        self.subject = Some(subject);
+        self
+    }
+
+    /// Set the [TrackerConfig], replacing the default one
+    ///
+    /// Global context entities set with [TrackerBuilder::global_context] are added to those of the config.
+    pub fn config(mut self, config: TrackerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Attach a context entity to every tracked event, unless the event opts out of default contexts
+    ///
+    /// Call this again to attach several context entities.
+    pub fn global_context(mut self, context: SelfDescribingJson) -> Self {
+        self.global_contexts.push(context);
+        self
+    }
+
+    /// Set a [SessionTracker], which sets the session ID and index on the subject of every event
+    ///
+    /// See [Tracker::set_session_tracker].
+    pub fn enable_session(mut self, session_tracker: SessionTracker) -> Self {
+        self.session_tracker = Some(session_tracker);
+        self
+    }
+
+    /// Set the [Clock] used for the device created timestamp (`dtm`) of events, by default a [SystemClock]
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Build the [Tracker], failing if the namespace, app ID or emitter isn't set
+    pub fn build(self) -> Result<Tracker, Error> {
+        let missing = |field: &str| Error::BuilderError(format!("Tracker {field} is required"));
+        let namespace = self.namespace.ok_or_else(|| missing("namespace"))?;
+        let app_id = self.app_id.ok_or_else(|| missing("app ID"))?;
+        let emitter = self.emitter.ok_or_else(|| missing("emitter"))?;
+
+        let mut config = self.config;
+        config.global_contexts.extend(self.global_contexts);
+
+        let mut tracker = Tracker::with_config(&namespace, &app_id, emitter, self.subject, config);
+        tracker.session_tracker = self.session_tracker;
+        if let Some(clock) = self.clock {
+            tracker.clock = clock;
+        }
+
+        Ok(tracker)
+    }
 }
 
 impl Tracker {
     /// Creates a new Tracker instance
+    ///
+    /// Use [Tracker::builder] to also set the config and automatic context of the tracker.
     pub fn new(
         namespace: &str,
         app_id: &str,
         emitter: impl Emitter + 'static,
         subject: Option<Subject>,
     ) -> Tracker {
+        Self::with_config(
+            namespace,
+            app_id,
+            Box::new(emitter),
+            subject,
+            TrackerConfig::default(),
+        )
+    }
+
+    /// Creates a [TrackerBuilder], to set up a tracker with its config and automatic context
+    pub fn builder() -> TrackerBuilder {
+        TrackerBuilder::default()
+    }
+
+    fn with_config(
+        namespace: &str,
+        app_id: &str,
+        mut emitter: Box<dyn Emitter>,
+        subject: Option<Subject>,
+        config: TrackerConfig,
+    ) -> Tracker {
+        emitter.set_namespace(namespace);
+
         Tracker {
             namespace: namespace.to_string(),
             app_id: app_id.to_string(),
-            emitter: Box::new(emitter),
+            emitter,
             // By providing a default subject, we can avoid having to unwrap the subject
             //
             // The default for Subject provides `None` for all fields, so will be skipped
             // when serializing
             subject: subject.unwrap_or(Subject::default()),
-            config: TrackerConfig {
-                platform: "pc".to_string(),
-                version: format!("rust-{}", env!("CARGO_PKG_VERSION")),
-                encode_base_64: false,
-            },
+            subject_provider: None,
+            enricher: None,
+            pii_hashing: None,
+            true_timestamp_source: None,
+            clock: Arc::new(SystemClock),
+            session_tracker: None,
+            background_index: 0,
+            foreground_index: 0,
+            config,
+            coalescer: None,
+            deduplicator: None,
+            #[cfg(feature = "schema-validation")]
+            schema_resolver: None,
         }
     }
 
@@ -80,16 +338,370 @@ impl Tracker {
         &self.subject
     }
 
+    pub fn platform(&self) -> &str {
+        &self.config.platform
+    }
+
+    pub(crate) fn set_platform(&mut self, platform: &str) {
+        self.config.platform = platform.to_string();
+    }
+
+    /// Sets the tracker version (`tv`) sent with every event, by default `rust-` followed by the crate version
+    ///
+    /// Useful for wrappers of this tracker, to tell their events apart when debugging.
+    pub fn set_tracker_version(&mut self, version: &str) {
+        self.config.version = version.to_string();
+    }
+
+    /// Sets how numeric fields, such as the value of a [StructuredEvent](crate::StructuredEvent), are serialized
+    ///
+    /// The tracker protocol expects strings, which is the default.
+    pub fn set_numeric_encoding(&mut self, numeric_encoding: NumericEncoding) {
+        self.config.numeric_encoding = numeric_encoding;
+    }
+
+    /// Sets how the context entities of every event (`co`) are serialized
+    ///
+    /// The tracker protocol expects a string of JSON, which is the default.
+    pub fn set_context_encoding(&mut self, context_encoding: ContextEncoding) {
+        self.config.context_encoding = context_encoding;
+    }
+
+    /// Sets which context entity is kept when an event has several with the same schema
+    ///
+    /// By default, all of them are kept. With [ContextDeduplication::FirstWins], the entities tracked
+    /// with an event take priority over those added by the tracker, such as [Tracker::set_environment].
+    pub fn set_context_deduplication(&mut self, context_deduplication: ContextDeduplication) {
+        self.config.context_deduplication = context_deduplication;
+    }
+
+    /// Sets whether fields identifying the user are removed from the subject of every event
+    ///
+    /// See [Subject::anonymised] for the fields that are removed. To also stop the collector
+    /// recording the IP address and network user ID, use server anonymisation on the emitter.
+    pub fn set_client_anonymisation(&mut self, client_anonymisation: bool) {
+        self.config.client_anonymisation = client_anonymisation;
+    }
+
+    /// Sets an environment label, such as `dev`, `staging` or `prod`, attached to every tracked event
+    ///
+    /// The label is sent as the `name` of a context entity with the given schema. Snowplow does not
+    /// publish an environment schema, so it must be hosted in your own Iglu registry and define
+    /// `name` as a string property, otherwise events fail validation.
+    pub fn set_environment(&mut self, environment: &str, schema: &str) {
+        self.config.environment = Some(SelfDescribingJson::new(
+            schema,
+            json!({ "name": environment }),
+        ));
+    }
+
+    /// Attaches a [GdprContext] to every tracked event, describing the legal basis for processing personal data
+    pub fn enable_gdpr_context(&mut self, gdpr_context: GdprContext) {
+        self.config.gdpr_context = Some(gdpr_context);
+    }
+
+    /// Stops attaching the [GdprContext] set with [Tracker::enable_gdpr_context]
+    pub fn disable_gdpr_context(&mut self) {
+        self.config.gdpr_context = None;
+    }
+
+    /// Attaches a diagnostic context entity with the given schema to every tracked event, to debug the tracker
+    ///
+    /// The entity reports the tracker namespace and version, and the number of events buffered by the emitter.
+    /// This is for debugging only, as it adds to the size of every event, so leave it disabled in production.
+    ///
+    /// Snowplow does not publish a schema for this entity, so it must be hosted in your own Iglu registry
+    /// and define the string properties `namespace` and `trackerVersion` and the integer property
+    /// `bufferedEvents`, otherwise events fail validation.
+    pub fn enable_diagnostic_context(&mut self, schema: &str) {
+        self.config.diagnostic_context = Some(schema.to_string());
+    }
+
+    /// Stops attaching the context entity enabled with [Tracker::enable_diagnostic_context]
+    pub fn disable_diagnostic_context(&mut self) {
+        self.config.diagnostic_context = None;
+    }
+
+    /// Attaches a [DesktopContext] to every tracked event, describing the operating system and device
+    pub fn set_desktop_context(&mut self, desktop_context: DesktopContext) {
+        self.config.desktop_context = Some(desktop_context);
+    }
+
+    /// Attaches a [MobileContext] to every tracked event, describing the operating system, device and network
+    pub fn set_mobile_context(&mut self, mobile_context: MobileContext) {
+        self.config.mobile_context = Some(mobile_context);
+    }
+
+    /// Attaches a [GeolocationContext] to every tracked event, describing the location of the device
+    ///
+    /// Call this again to update the location as the device moves.
+    pub fn set_geolocation_context(&mut self, geolocation_context: GeolocationContext) {
+        self.config.geolocation_context = Some(geolocation_context);
+    }
+
+    /// Attaches an [ApplicationContext] to every tracked event, describing the version and build of the app
+    pub fn set_application_context(&mut self, application_context: ApplicationContext) {
+        self.config.application_context = Some(application_context);
+    }
+
+    /// Enables the common automatic context of an app in one call, e.g. the session and mobile context
+    ///
+    /// Each part of the [PlatformContexts] that is set is enabled as with its own setter,
+    /// such as [Tracker::set_session_tracker] or [Tracker::set_mobile_context].
+    pub fn with_platform_contexts(mut self, platform_contexts: PlatformContexts) -> Self {
+        if let Some(session_tracker) = platform_contexts.session {
+            self.set_session_tracker(session_tracker);
+        }
+        if let Some(mobile_context) = platform_contexts.mobile {
+            self.set_mobile_context(mobile_context);
+        }
+        if let Some(desktop_context) = platform_contexts.desktop {
+            self.set_desktop_context(desktop_context);
+        }
+        if let Some(geolocation_context) = platform_contexts.geolocation {
+            self.set_geolocation_context(geolocation_context);
+        }
+        self
+    }
+
+    /// Sets the [Clock] used for the device created timestamp (`dtm`) of events, by default a [SystemClock]
+    ///
+    /// Useful for deterministic timestamps in tests, along with a [FixedClock](crate::FixedClock).
+    /// The sent timestamp (`stm`) is set by the emitter, which may take its own clock.
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Arc::new(clock);
+    }
+
+    /// Sets a function producing the subject of each tracked event, e.g. from the currently signed-in user
+    ///
+    /// The provider is called every time an event is tracked. Its subject takes priority over the
+    /// tracker [Subject], while an event-level subject still takes priority over both.
+    pub fn set_subject_provider(&mut self, provider: impl Fn() -> Subject + Send + Sync + 'static) {
+        self.subject_provider = Some(Box::new(provider));
+    }
+
+    /// Sets a function called with the payload of every tracked event, to add computed fields or context entities
+    ///
+    /// The enricher is called once the event has added its fields and the tracker its subject and
+    /// context entities, and before the tracker's encodings and field limits are applied.
+    ///
+    /// ## Example
+    /// ```
+    /// use snowplow_tracker::{MockEmitter, SelfDescribingJson, Tracker};
+    /// use serde_json::json;
+    ///
+    /// let mut tracker = Tracker::new("ns", "app_id", MockEmitter::new(), None);
+    /// tracker.set_enricher(Box::new(|payload| {
+    ///     payload.add_context(SelfDescribingJson::new(
+    ///         "iglu:com.acme/build/jsonschema/1-0-0",
+    ///         json!({ "commit": "4f2c1a9" }),
+    ///     ));
+    /// }));
+    /// ```
+    pub fn set_enricher(&mut self, enricher: Enricher) {
+        self.enricher = Some(enricher);
+    }
+
+    /// Sets the subject fields that are replaced by their salted SHA-256 hash before events are sent
+    ///
+    /// Each value is hashed with the `salt` prepended, and sent as the lowercase hex digest.
+    /// This pseudonymizes the user while still letting their events be joined. Keep the salt secret
+    /// and the same across trackers, so the same value always has the same hash. An empty set disables hashing.
+    pub fn set_hash_pii(&mut self, fields: HashSet<PiiField>, salt: &str) {
+        self.pii_hashing = match fields.is_empty() {
+            true => None,
+            false => Some(PiiHashing {
+                fields,
+                salt: salt.to_string(),
+            }),
+        };
+    }
+
+    /// Sets a function producing the true timestamp (`ttm`) of each tracked event, e.g. from a trusted clock
+    ///
+    /// The source is called every time an event is tracked without a true timestamp.
+    /// A true timestamp set on the event itself takes priority over the source.
+    pub fn set_true_timestamp_source(
+        &mut self,
+        source: impl Fn() -> DateTime<Utc> + Send + Sync + 'static,
+    ) {
+        self.true_timestamp_source = Some(Box::new(source));
+    }
+
+    /// Sets a [SessionTracker], which sets the session ID and index on the subject of every event
+    ///
+    /// These take priority over the session fields of other subjects. No session is set while
+    /// client anonymisation is enabled, see [Tracker::set_client_anonymisation].
+    pub fn set_session_tracker(&mut self, session_tracker: SessionTracker) {
+        self.session_tracker = Some(session_tracker);
+    }
+
+    /// Provides mutable access to the [SessionTracker], if set, e.g. to start a new session
+    pub fn session_tracker_mut(&mut self) -> Option<&mut SessionTracker> {
+        self.session_tracker.as_mut()
+    }
+
+    /// Tracks an `application_background` event, for when the app goes to the background
+    ///
+    /// The event is part of the current session. While the app is in the background, the
+    /// [SessionTracker] counts how long for, see [SessionTracker::background_timeout].
+    pub fn on_background(&mut self) -> Result<Uuid, Error> {
+        self.background_index += 1;
+        let event = SelfDescribingEvent::builder()
+            .schema(APPLICATION_BACKGROUND_SCHEMA)
+            .data(json!({ "backgroundIndex": self.background_index }))
+            .build()?;
+        let event_id = self.track(event, None)?;
+
+        let now = self.clock.now();
+        if let Some(session_tracker) = self.session_tracker.as_mut() {
+            session_tracker.background(now);
+        }
+
+        Ok(event_id)
+    }
+
+    /// Tracks an `application_foreground` event, for when the app returns to the foreground
+    ///
+    /// If the app was in the background for longer than the background timeout of the [SessionTracker],
+    /// a new session is started first, which the event is part of.
+    pub fn on_foreground(&mut self) -> Result<Uuid, Error> {
+        let now = self.clock.now();
+        if let Some(session_tracker) = self.session_tracker.as_mut() {
+            session_tracker.foreground(now);
+        }
+
+        self.foreground_index += 1;
+        let event = SelfDescribingEvent::builder()
+            .schema(APPLICATION_FOREGROUND_SCHEMA)
+            .data(json!({ "foregroundIndex": self.foreground_index }))
+            .build()?;
+        self.track(event, None)
+    }
+
+    /// Sets maximum lengths of event fields, truncating longer values before events are sent
+    pub fn set_field_limits(&mut self, field_limits: FieldLimits) {
+        self.config.field_limits = Some(field_limits);
+    }
+
     /// Attempts to send all events in the event store to the collector
     pub fn flush(&mut self) -> Result<(), Error> {
+        self.emit_coalesced()?;
         self.emitter.flush()
     }
 
+    /// Sends all events in the event store to the collector, waiting until they have been sent
+    ///
+    /// Returns [Error::CollectorResponse] if the collector rejected a batch, see [Emitter::flush_now].
+    pub fn flush_now(&mut self) -> Result<(), Error> {
+        self.emit_coalesced()?;
+        self.emitter.flush_now()
+    }
+
     /// Safely shuts down the Emitter
     pub fn close_emitter(&mut self) -> Result<(), Error> {
+        self.emit_coalesced()?;
         self.emitter.close()
     }
 
+    /// Stops the emitter sending events, while still accepting new events
+    pub fn pause_emitter(&mut self) -> Result<(), Error> {
+        self.emitter.pause()
+    }
+
+    /// Resumes sending events, including any queued while the emitter was paused
+    pub fn resume_emitter(&mut self) -> Result<(), Error> {
+        self.emitter.resume()
+    }
+
+    /// Resets the statistics kept by the emitter, see [Emitter::stats]
+    pub fn reset_emitter_stats(&mut self) {
+        self.emitter.reset_stats()
+    }
+
+    /// Sets a window in which consecutive identical events are coalesced into a single event
+    ///
+    /// Events are identical when everything but their event ID and timestamps match.
+    /// The first event of a run is held back until a different event is tracked, an identical event
+    /// is tracked after the window, or the tracker is flushed, closed or dropped. It is then sent once.
+    /// No timer sends a held event when the window ends, so flush the tracker if events are tracked rarely.
+    /// An event representing several is sent with a context entity of `count_schema`, whose data
+    /// is the `count` of events, e.g. `{"count": 5}`. The schema must be hosted in your Iglu registry,
+    /// with a required integer `count` property, or the events fail validation.
+    /// Tracking a coalesced duplicate returns the event ID of the event it was merged into.
+    ///
+    /// The window is measured with the tracker clock, see [Tracker::set_clock].
+    /// Passing `None` as the window disables coalescing, sending any held event.
+    pub fn set_coalescing_window(
+        &mut self,
+        window: Option<Duration>,
+        count_schema: &str,
+    ) -> Result<(), Error> {
+        self.emit_coalesced()?;
+        self.coalescer = window.map(|window| EventCoalescer::new(window, count_schema));
+        Ok(())
+    }
+
+    /// Sets a window in which events tracked with [Tracker::track_deduped] are skipped if they are duplicates
+    ///
+    /// Passing `None` disables deduplication, forgetting all previously tracked events.
+    pub fn set_dedup_window(&mut self, window: Option<Duration>) {
+        self.deduplicator = window.map(EventDeduplicator::new);
+    }
+
+    /// Sets a [SchemaResolver] used to validate self-describing events and context entities before they are tracked
+    ///
+    /// Tracking an event that fails validation returns [Error::ValidationError] and the event isn't sent.
+    /// Passing `None` disables validation.
+    #[cfg(feature = "schema-validation")]
+    pub fn set_schema_resolver(&mut self, schema_resolver: Option<SchemaResolver>) {
+        self.schema_resolver = schema_resolver;
+    }
+
+    // Validates the self-describing event and context entities of the payload, if a schema resolver is set
+    #[cfg(feature = "schema-validation")]
+    fn validate_payload(&self, payload_builder: &PayloadBuilder) -> Result<(), Error> {
+        let resolver = match &self.schema_resolver {
+            Some(resolver) => resolver,
+            None => return Ok(()),
+        };
+
+        if let Some(Some(ue_pr)) = &payload_builder.ue_pr {
+            resolver.validate(&ue_pr.data)?;
+        }
+        if let Some(Some(co)) = &payload_builder.co {
+            for context in co.data.iter() {
+                resolver.validate(context)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Sends the event currently held by the coalescer, if any
+    fn emit_coalesced(&mut self) -> Result<(), Error> {
+        if let Some(payload_builder) = self.coalescer.as_mut().and_then(|c| c.drain()) {
+            let payload_builder = self.finish_coalesced(payload_builder)?;
+            self.emitter.add(payload_builder)?;
+        }
+        Ok(())
+    }
+
+    // Applies the tracker config to the count context entity the coalescer added to a prepared payload
+    fn finish_coalesced(
+        &self,
+        mut payload_builder: PayloadBuilder,
+    ) -> Result<PayloadBuilder, Error> {
+        if let Some(field_limits) = &self.config.field_limits {
+            field_limits.apply(&mut payload_builder);
+        }
+
+        #[cfg(feature = "schema-validation")]
+        self.validate_payload(&payload_builder)?;
+
+        Ok(payload_builder)
+    }
+
     /// Provides mutable access to the `subject` field
     ///
     /// ## Example
@@ -106,7 +718,7 @@ impl Tracker {
     /// let mut tracker = Snowplow::create_tracker("ns", "app_id", "https://...", Some(tracker_subject));
     ///
     /// assert_eq!(tracker.subject().user_id, Some("user_1".to_string()));
-    /// assert_eq!(tracker.subject().language, Some("en-gb".to_string()));
+    /// assert_eq!(tracker.subject().language, Some("en-GB".to_string()));
     ///
     /// // Bulild a new Subject to replace the instance in `tracker`
     /// let new_tracker_subject = match Subject::builder().user_id("user_2").build() {
@@ -130,48 +742,736 @@ impl Tracker {
         &mut self.subject
     }
 
+    /// Merges the provided [Subject] into the tracker's subject
+    ///
+    /// Fields set on `subject` take priority, while fields it leaves unset keep their current value.
+    /// To replace the subject entirely, use [Tracker::subject_mut].
+    ///
+    /// ## Example
+    /// ```
+    /// use snowplow_tracker::{Snowplow, Subject};
+    ///
+    /// let tracker_subject = Subject::builder().user_id("user_1").language("en-gb").build().unwrap();
+    /// let mut tracker = Snowplow::create_tracker("ns", "app_id", "https://...", Some(tracker_subject));
+    ///
+    /// tracker.update_subject(Subject::builder().user_id("user_2").build().unwrap());
+    ///
+    /// assert_eq!(tracker.subject().user_id, Some("user_2".to_string()));
+    /// assert_eq!(tracker.subject().language, Some("en-GB".to_string()));
+    ///
+    /// tracker.close_emitter().unwrap();
+    /// ```
+    pub fn update_subject(&mut self, subject: Subject) {
+        self.subject = subject.merge(std::mem::take(&mut self.subject));
+    }
+
     /// Tracks a Snowplow event with optional context entities and sends it to the Snowplow collector.
+    ///
+    /// Use [Tracker::track_event] to also get the payload that was sent.
     pub fn track(
         &mut self,
         event: impl PayloadAddable,
         context: Option<Vec<SelfDescribingJson>>,
     ) -> Result<Uuid, Error> {
-        let event_id = Uuid::new_v4();
+        let payload_builder = self.build_payload(event, context);
+        self.emit(payload_builder, |emitter, payload| emitter.add(payload))
+    }
 
-        let mut payload_builder = Payload::builder()
-            .p(self.config.platform.clone())
-            .tv(self.config.version.clone())
-            .eid(event_id.clone())
-            .dtm(Utc::now())
-            .aid(self.app_id.clone());
+    /// Tracks a Snowplow event, the same as [Tracker::track], with context entities from any iterator
+    ///
+    /// This saves wrapping a single context entity in `Some(vec![...])`. An empty iterator attaches no context entities.
+    ///
+    /// ## Example
+    /// ```
+    /// use serde_json::json;
+    /// use snowplow_tracker::{MockEmitter, SelfDescribingJson, StructuredEvent, Tracker};
+    ///
+    /// let mut tracker = Tracker::new("ns", "app_id", MockEmitter::new(), None);
+    ///
+    /// let event = StructuredEvent::builder().category("shop").action("add").build().unwrap();
+    /// let user = SelfDescribingJson::new("iglu:com.acme/user/jsonschema/1-0-0", json!({ "id": 1 }));
+    /// tracker.track_with_contexts(event, [user]).unwrap();
+    /// ```
+    pub fn track_with_contexts(
+        &mut self,
+        event: impl PayloadAddable,
+        contexts: impl IntoIterator<Item = SelfDescribingJson>,
+    ) -> Result<Uuid, Error> {
+        let contexts: Vec<SelfDescribingJson> = contexts.into_iter().collect();
+        let context = (!contexts.is_empty()).then_some(contexts);
+        self.track(event, context)
+    }
 
-        if let Some(context) = context {
-            payload_builder = payload_builder.co(ContextData::new(context));
-        }
+    /// Tracks a Snowplow event, the same as [Tracker::track], with a subject for this call only
+    ///
+    /// The subject takes priority over both the event subject and the tracker [Subject], which is
+    /// handy for server-side tracking where each request has its own user.
+    ///
+    /// ## Example
+    /// ```
+    /// use snowplow_tracker::{MockEmitter, StructuredEvent, Subject, Tracker};
+    ///
+    /// let mut tracker = Tracker::new("ns", "app_id", MockEmitter::new(), None);
+    ///
+    /// let event = StructuredEvent::builder().category("shop").action("add").build().unwrap();
+    /// let request_subject = Subject::builder().user_id("user_1").build().unwrap();
+    /// tracker.track_with_subject(event, None, request_subject).unwrap();
+    /// ```
+    pub fn track_with_subject(
+        &mut self,
+        event: impl PayloadAddable,
+        context: Option<Vec<SelfDescribingJson>>,
+        subject: Subject,
+    ) -> Result<Uuid, Error> {
+        let payload_builder = self.build_payload_with_subject(event, context, Some(subject));
+        self.emit(payload_builder, |emitter, payload| emitter.add(payload))
+    }
+
+    /// Tracks a Snowplow event, the same as [Tracker::track], with a platform (`p`) for this call only
+    ///
+    /// The platform overrides [TrackerConfig::platform], which is handy when relaying events on behalf
+    /// of other platforms, e.g. from mobile apps. Valid platforms include `"mob"`, `"web"` and `"srv"`.
+    ///
+    /// ## Example
+    /// ```
+    /// use snowplow_tracker::{MockEmitter, StructuredEvent, Tracker};
+    ///
+    /// let mut tracker = Tracker::new("ns", "app_id", MockEmitter::new(), None);
+    ///
+    /// let event = StructuredEvent::builder().category("shop").action("add").build().unwrap();
+    /// tracker.track_with_platform(event, None, "mob").unwrap();
+    /// ```
+    pub fn track_with_platform(
+        &mut self,
+        event: impl PayloadAddable,
+        context: Option<Vec<SelfDescribingJson>>,
+        platform: &str,
+    ) -> Result<Uuid, Error> {
+        let payload_builder = self.build_payload(event, context).p(platform.to_string());
+        self.emit(payload_builder, |emitter, payload| emitter.add(payload))
+    }
 
-        // Event Subject gets priority over Tracker Subject
-        if let Some(event_subject) = event.subject() {
-            payload_builder =
-                payload_builder.subject(event_subject.clone().merge(self.subject.clone()));
+    /// Tracks a Snowplow event, the same as [Tracker::track], with the provided event ID rather than a new one
+    ///
+    /// Useful for idempotent replays, or to correlate the event with other systems. Any UUID is accepted
+    /// except the nil UUID. Returns the provided ID, unless the event is coalesced into a previous one.
+    pub fn track_with_id(
+        &mut self,
+        event_id: Uuid,
+        event: impl PayloadAddable,
+        context: Option<Vec<SelfDescribingJson>>,
+    ) -> Result<Uuid, Error> {
+        if event_id.is_nil() {
+            return Err(Error::BuilderError(
+                "Event ID must not be the nil UUID".to_string(),
+            ));
         }
 
-        payload_builder = event.add_to_payload(payload_builder);
+        let payload_builder = self.build_payload(event, context).eid(event_id);
+        self.emit(payload_builder, |emitter, payload| emitter.add(payload))
+    }
 
-        let event_id = match payload_builder.eid {
-            Some(eid) => eid,
-            None => return Err(Error::BuilderError("Event ID not set".to_string())),
-        };
+    /// Tracks a Snowplow event, the same as [Tracker::track], returning both the event ID and its final payload
+    ///
+    /// This is useful to log or inspect what was sent, but costs a copy of every payload.
+    /// If the event is coalesced into a previous one, `event_id` is the ID of that previous event.
+    pub fn track_event(
+        &mut self,
+        event: impl PayloadAddable,
+        context: Option<Vec<SelfDescribingJson>>,
+    ) -> Result<TrackedEvent, Error> {
+        let payload_builder = self.prepare(self.build_payload(event, context))?;
+        let payload = payload_builder
+            .clone()
+            .finalise_payload_at(self.clock.now())?;
+        let event_id = self.dispatch(payload_builder, |emitter, payload| emitter.add(payload))?;
 
-        self.emitter.add(payload_builder)?;
-        Ok(event_id)
+        Ok(TrackedEvent { event_id, payload })
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::BatchEmitter;
-
-    use super::*;
+    /// Builds the payload of an event, the same as [Tracker::track], and returns it as pretty-printed JSON without tracking it
+    ///
+    /// This is handy during development to see exactly what would be sent, without a collector.
+    /// The session isn't updated, so the payload has no session fields.
+    ///
+    /// ## Example
+    /// ```
+    /// use snowplow_tracker::{MockEmitter, StructuredEvent, Tracker};
+    ///
+    /// let tracker = Tracker::new("ns", "app_id", MockEmitter::new(), None);
+    ///
+    /// let event = StructuredEvent::builder().category("shop").action("add").build().unwrap();
+    /// let preview = tracker.preview(event, None).unwrap();
+    /// assert!(preview.contains(r#""se_ac": "add""#));
+    /// ```
+    pub fn preview(
+        &self,
+        event: impl PayloadAddable,
+        context: Option<Vec<SelfDescribingJson>>,
+    ) -> Result<String, Error> {
+        let payload = self
+            .build_payload(event, context)
+            .finalise_payload_at(self.clock.now())?;
+        Ok(serde_json::to_string_pretty(&payload)?)
+    }
+
+    /// Tracks several related events together, each with optional context entities, returning their event IDs in order
+    ///
+    /// All payloads are prepared before any is added to the emitter, so if one fails, e.g. schema
+    /// validation, none are tracked. They are then added one after the other, so they are sent in
+    /// order, and in the same batch unless it fills up.
+    ///
+    /// ## Example
+    /// ```
+    /// use snowplow_tracker::{EventWithContext, MockEmitter, ScreenViewEvent, StructuredEvent, Tracker};
+    /// use uuid::Uuid;
+    ///
+    /// let mut tracker = Tracker::new("ns", "app_id", MockEmitter::new(), None);
+    ///
+    /// let screen_view = ScreenViewEvent::builder().id(Uuid::new_v4()).name("basket").build().unwrap();
+    /// let add_to_basket = StructuredEvent::builder().category("shop").action("add").build().unwrap();
+    ///
+    /// let events: Vec<EventWithContext> =
+    ///     vec![(Box::new(screen_view), None), (Box::new(add_to_basket), None)];
+    /// let event_ids = tracker.track_all(events).unwrap();
+    /// assert_eq!(event_ids.len(), 2);
+    /// ```
+    pub fn track_all(&mut self, events: Vec<EventWithContext>) -> Result<Vec<Uuid>, Error> {
+        let payload_builders = events
+            .into_iter()
+            .map(|(event, context)| self.prepare(self.build_payload(event, context)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        payload_builders
+            .into_iter()
+            .map(|payload_builder| {
+                self.dispatch(payload_builder, |emitter, payload| emitter.add(payload))
+            })
+            .collect()
+    }
+
+    /// Tracks a Snowplow event and waits until the emitter has sent it, for scripts and apps without an async runtime
+    ///
+    /// With a [BlockingEmitter](crate::BlockingEmitter), the event is sent right away.
+    /// With other emitters, this flushes all queued events with [Tracker::flush_now].
+    #[cfg(feature = "blocking")]
+    pub fn track_blocking(
+        &mut self,
+        event: impl PayloadAddable,
+        context: Option<Vec<SelfDescribingJson>>,
+    ) -> Result<Uuid, Error> {
+        let event_id = self.track(event, context)?;
+        self.flush_now()?;
+        Ok(event_id)
+    }
+
+    /// Tracks a [StructuredEvent], the same as [Tracker::track]
+    pub fn track_structured(
+        &mut self,
+        event: StructuredEvent,
+        context: Option<Vec<SelfDescribingJson>>,
+    ) -> Result<Uuid, Error> {
+        let payload_builder = self.build_payload(event, context);
+        self.emit(payload_builder, |emitter, payload| emitter.add(payload))
+    }
+
+    /// Tracks a [Payload] built outside the tracker, e.g. when replaying events or migrating from another tracker
+    ///
+    /// The payload is passed to the emitter as-is, which sets its sent timestamp (`stm`) when sending it.
+    /// None of the tracker's settings, such as its subject or context entities, are applied,
+    /// and the payload isn't coalesced. An event held for coalescing is sent first, so events keep their order.
+    /// Fails if the platform, or the event type and its matching event data, are not set.
+    pub fn track_payload(&mut self, payload: Payload) -> Result<Uuid, Error> {
+        payload.validate()?;
+        self.emit_coalesced()?;
+
+        let event_id = payload.eid;
+        self.emitter.add(payload.into())?;
+        Ok(event_id)
+    }
+
+    /// Tracks a Snowplow event without waiting for it to be queued by the emitter
+    ///
+    /// The event is handed to the emitter's background thread, which queues and sends it.
+    /// Returns the event ID immediately, or an error if the emitter can't accept more events right now.
+    /// Call [Tracker::flush] or [Tracker::close_emitter] to make sure all events are sent.
+    pub fn track_nonblocking(
+        &mut self,
+        event: impl PayloadAddable,
+        context: Option<Vec<SelfDescribingJson>>,
+    ) -> Result<Uuid, Error> {
+        let payload_builder = self.build_payload(event, context);
+        self.emit(payload_builder, |emitter, payload| {
+            emitter.add_nonblocking(payload)
+        })
+    }
+
+    /// Tracks a Snowplow event, unless a duplicate was tracked within the window set with [Tracker::set_dedup_window]
+    ///
+    /// Duplicates are identified by `key` if provided, and otherwise by the content of the event,
+    /// which is everything but its event ID and timestamps.
+    /// Tracking a duplicate returns the event ID of the event it duplicates, without tracking it again.
+    /// If no window is set, the event is always tracked.
+    pub fn track_deduped(
+        &mut self,
+        event: impl PayloadAddable,
+        context: Option<Vec<SelfDescribingJson>>,
+        key: Option<&str>,
+    ) -> Result<Uuid, Error> {
+        let payload_builder = self.build_payload(event, context);
+
+        if self.deduplicator.is_none() {
+            return self.emit(payload_builder, |emitter, payload| emitter.add(payload));
+        }
+
+        let key = match key {
+            Some(key) => key.to_string(),
+            None => payload_builder.content_key()?,
+        };
+
+        if let Some(event_id) = self
+            .deduplicator
+            .as_mut()
+            .and_then(|deduplicator| deduplicator.duplicate_of(&key))
+        {
+            return Ok(event_id);
+        }
+
+        let event_id = self.emit(payload_builder, |emitter, payload| emitter.add(payload))?;
+        if let Some(deduplicator) = self.deduplicator.as_mut() {
+            deduplicator.record(key, event_id);
+        }
+        Ok(event_id)
+    }
+
+    // Builds the payload of an event, adding the tracker config and subject
+    fn build_payload(
+        &self,
+        event: impl PayloadAddable,
+        context: Option<Vec<SelfDescribingJson>>,
+    ) -> PayloadBuilder {
+        self.build_payload_with_subject(event, context, None)
+    }
+
+    // Builds the payload of an event, the same as `build_payload`, with a subject taking priority over all others
+    fn build_payload_with_subject(
+        &self,
+        mut event: impl PayloadAddable,
+        context: Option<Vec<SelfDescribingJson>>,
+        call_subject: Option<Subject>,
+    ) -> PayloadBuilder {
+        let subject = self.resolve_subject(event.take_subject());
+        let subject = match call_subject {
+            Some(call_subject) => call_subject.merge(subject),
+            None => subject,
+        };
+        let base_payload = self.base_payload(context, subject, event.attach_default_contexts());
+        let payload_builder = event.add_to_payload(base_payload);
+        self.finish_payload(payload_builder)
+    }
+
+    // The subject of an event, with the event subject taking priority over the provided subject,
+    // which takes priority over the tracker subject
+    fn resolve_subject(&self, event_subject: Option<Subject>) -> Subject {
+        let subject = match &self.subject_provider {
+            Some(provider) => provider().merge(self.subject.clone()),
+            None => self.subject.clone(),
+        };
+        match event_subject {
+            Some(event_subject) => event_subject.merge(subject),
+            None => subject,
+        }
+    }
+
+    // The payload fields shared by all events, before the event adds its own fields
+    fn base_payload(
+        &self,
+        context: Option<Vec<SelfDescribingJson>>,
+        subject: Subject,
+        attach_default_contexts: bool,
+    ) -> PayloadBuilder {
+        let mut payload_builder = Payload::builder()
+            .p(self.config.platform.clone())
+            .tv(self.config.version.clone())
+            .eid(Uuid::new_v4())
+            .dtm(self.clock.now())
+            .aid(self.app_id.clone());
+
+        let mut context = context.unwrap_or_default();
+        if attach_default_contexts {
+            context.extend(self.default_contexts());
+        }
+        let context = self.config.context_deduplication.apply(context);
+        if !context.is_empty() {
+            payload_builder = payload_builder.co(ContextData::new(context));
+        }
+
+        payload_builder.subject(match self.config.client_anonymisation {
+            true => subject.anonymised(),
+            false => subject,
+        })
+    }
+
+    // Applies the tracker config to the payload, once the event has added its fields
+    fn finish_payload(&self, mut payload_builder: PayloadBuilder) -> PayloadBuilder {
+        // An event-level true timestamp takes priority over the tracker source
+        if let (None, Some(source)) = (&payload_builder.ttm, &self.true_timestamp_source) {
+            payload_builder = payload_builder.ttm(source());
+        }
+
+        if let Some(enricher) = &self.enricher {
+            enricher(&mut payload_builder);
+        }
+
+        if let (Some(pii_hashing), Some(Some(subject))) =
+            (&self.pii_hashing, payload_builder.subject.as_mut())
+        {
+            pii_hashing.apply(subject);
+        }
+
+        payload_builder = payload_builder
+            .numeric_encoding(self.config.numeric_encoding)
+            .context_encoding(self.config.context_encoding);
+
+        if let Some(field_limits) = &self.config.field_limits {
+            field_limits.apply(&mut payload_builder);
+        }
+
+        payload_builder
+    }
+
+    // The context entities the tracker attaches to every event
+    fn default_contexts(&self) -> Vec<SelfDescribingJson> {
+        let mut contexts = self.config.global_contexts.clone();
+
+        if let Some(environment) = &self.config.environment {
+            contexts.push(environment.clone());
+        }
+
+        if let Some(gdpr_context) = &self.config.gdpr_context {
+            contexts.push(gdpr_context.to_self_describing_json());
+        }
+
+        if let Some(desktop_context) = &self.config.desktop_context {
+            contexts.push(desktop_context.to_self_describing_json());
+        }
+
+        if let Some(mobile_context) = &self.config.mobile_context {
+            contexts.push(mobile_context.to_self_describing_json());
+        }
+
+        if let Some(geolocation_context) = &self.config.geolocation_context {
+            contexts.push(geolocation_context.to_self_describing_json());
+        }
+
+        if let Some(application_context) = &self.config.application_context {
+            contexts.push(application_context.to_self_describing_json());
+        }
+
+        if let Some(schema) = &self.config.diagnostic_context {
+            contexts.push(SelfDescribingJson::new(
+                schema,
+                json!({
+                    "namespace": self.namespace,
+                    "trackerVersion": self.config.version,
+                    "bufferedEvents": self.emitter.stats().buffered,
+                }),
+            ));
+        }
+
+        contexts
+    }
+
+    // Prepares the payload and passes it to the emitter using `add`
+    fn emit(
+        &mut self,
+        payload_builder: PayloadBuilder,
+        add: fn(&mut dyn Emitter, PayloadBuilder) -> Result<(), Error>,
+    ) -> Result<Uuid, Error> {
+        let payload_builder = self.prepare(payload_builder)?;
+        self.dispatch(payload_builder, add)
+    }
+
+    // Applies the session and validates the payload, right before it is emitted
+    fn prepare(&mut self, mut payload_builder: PayloadBuilder) -> Result<PayloadBuilder, Error> {
+        // The session is set here rather than when building the payload, as each event counts towards it
+        if let Some(session_tracker) = self.session_tracker.as_mut() {
+            if !self.config.client_anonymisation {
+                let (session_id, session_index) = session_tracker.next_event();
+                let subject = payload_builder.subject.take().flatten().unwrap_or_default();
+                payload_builder = payload_builder.subject(Subject {
+                    session_user_id: Some(session_id),
+                    domain_session_index: Some(session_index),
+                    ..subject
+                });
+            }
+        }
+
+        #[cfg(feature = "schema-validation")]
+        self.validate_payload(&payload_builder)?;
+
+        Ok(payload_builder)
+    }
+
+    // Passes the payload to the emitter using `add`, via the coalescer if enabled
+    fn dispatch(
+        &mut self,
+        payload_builder: PayloadBuilder,
+        add: fn(&mut dyn Emitter, PayloadBuilder) -> Result<(), Error>,
+    ) -> Result<Uuid, Error> {
+        let now = self.clock.now();
+        if let Some(coalescer) = self.coalescer.as_mut() {
+            let (event_id, ready) = coalescer.push(payload_builder, now)?;
+            if let Some(ready) = ready {
+                let ready = self.finish_coalesced(ready)?;
+                add(self.emitter.as_mut(), ready)?;
+            }
+            return Ok(event_id);
+        }
+
+        let event_id = match payload_builder.eid {
+            Some(eid) => eid,
+            None => return Err(Error::BuilderError("Event ID not set".to_string())),
+        };
+
+        add(self.emitter.as_mut(), payload_builder)?;
+        Ok(event_id)
+    }
+}
+
+// The event held by the coalescer would otherwise be lost
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        if let Err(e) = self.emit_coalesced() {
+            log::warn!("Failed to send the coalesced event: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::mpsc::{self, Sender};
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+
+    use crate::event_batch::EventBatch;
+    use crate::http_client::mock_collector::mock_collector;
+    use crate::payload::EventType;
+    use chrono::TimeZone;
+
+    use crate::{
+        BatchEmitter, FixedClock, HttpClient, InMemoryEventStore, MockEmitter, SelfDescribingEvent,
+        StructuredEvent,
+    };
+
+    use super::*;
+
+    // An emitter that keeps every added payload, so tests can inspect what would be sent
+    struct RecordingEmitter {
+        payloads: Arc<Mutex<Vec<Payload>>>,
+    }
+
+    impl Emitter for RecordingEmitter {
+        fn add(&mut self, payload: PayloadBuilder) -> Result<(), Error> {
+            self.payloads
+                .lock()
+                .unwrap()
+                .push(payload.finalise_payload()?);
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn close(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn collector_url(&self) -> &str {
+            "http://example.com"
+        }
+    }
+
+    // An HTTP client that reports the headers of every request it is asked to send
+    struct HeaderCapturingClient {
+        headers: Sender<HashMap<String, String>>,
+    }
+
+    #[async_trait]
+    impl HttpClient for HeaderCapturingClient {
+        async fn post(&self, payload: SelfDescribingJson) -> Result<u16, Error> {
+            self.post_with_headers(payload, &HashMap::new()).await
+        }
+
+        async fn post_with_headers(
+            &self,
+            _payload: SelfDescribingJson,
+            headers: &HashMap<String, String>,
+        ) -> Result<u16, Error> {
+            self.headers.send(headers.clone()).ok();
+            Ok(200)
+        }
+
+        fn clone(&self) -> Box<dyn HttpClient + Send + Sync> {
+            Box::new(HeaderCapturingClient {
+                headers: self.headers.clone(),
+            })
+        }
+    }
+
+    fn header_capturing_tracker(
+        send_namespace_header: bool,
+    ) -> (Tracker, mpsc::Receiver<HashMap<String, String>>) {
+        let (tx, rx) = mpsc::channel();
+        let emitter = BatchEmitter::builder()
+            .collector_url("http://example.com")
+            .event_store(InMemoryEventStore::new(10, 1))
+            .http_client(HeaderCapturingClient { headers: tx })
+            .send_namespace_header(send_namespace_header)
+            .build()
+            .unwrap();
+
+        (Tracker::new("ns", "app_id", emitter, None), rx)
+    }
+
+    fn recording_tracker() -> (Tracker, Arc<Mutex<Vec<Payload>>>) {
+        let payloads = Arc::new(Mutex::new(Vec::new()));
+        let emitter = RecordingEmitter {
+            payloads: payloads.clone(),
+        };
+        (Tracker::new("ns", "app_id", emitter, None), payloads)
+    }
+
+    fn structured_event(action: &str) -> StructuredEvent {
+        StructuredEvent::builder()
+            .category("shop")
+            .action(action)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn lifecycle_events_are_tracked_across_background_and_foreground() {
+        let (mut tracker, payloads) = recording_tracker();
+        let clock = FixedClock::new(Utc.timestamp_millis_opt(1_700_000_000_000).unwrap());
+        tracker.set_clock(clock.clone());
+        tracker.set_session_tracker(
+            SessionTracker::new().background_timeout(Duration::from_secs(30 * 60)),
+        );
+
+        // A short time in the background continues the session
+        tracker.on_background().unwrap();
+        assert!(tracker.session_tracker_mut().unwrap().is_in_background());
+        clock.advance(chrono::Duration::minutes(10));
+        tracker.on_foreground().unwrap();
+        assert!(!tracker.session_tracker_mut().unwrap().is_in_background());
+
+        // A long time in the background starts a new session
+        tracker.on_background().unwrap();
+        clock.advance(chrono::Duration::hours(1));
+        tracker.on_foreground().unwrap();
+
+        let payloads = payloads.lock().unwrap();
+        let events: Vec<_> = payloads
+            .iter()
+            .map(|payload| {
+                let event = &payload.ue_pr.as_ref().unwrap().data;
+                let index = match event.schema.as_str() {
+                    APPLICATION_BACKGROUND_SCHEMA => event.data["backgroundIndex"].clone(),
+                    _ => event.data["foregroundIndex"].clone(),
+                };
+                let session_index = serde_json::to_value(payload).unwrap()["vid"].clone();
+                (event.schema.as_str(), index, session_index)
+            })
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                (APPLICATION_BACKGROUND_SCHEMA, json!(1), json!("1")),
+                (APPLICATION_FOREGROUND_SCHEMA, json!(1), json!("1")),
+                (APPLICATION_BACKGROUND_SCHEMA, json!(2), json!("1")),
+                (APPLICATION_FOREGROUND_SCHEMA, json!(2), json!("2")),
+            ]
+        );
+    }
+
+    #[test]
+    fn builder_applies_all_options() {
+        let (_, payloads) = recording_tracker();
+        let emitter = RecordingEmitter {
+            payloads: payloads.clone(),
+        };
+        let build_context = SelfDescribingJson::new(
+            "iglu:com.acme/build/jsonschema/1-0-0",
+            json!({ "commit": "4f2c1a9" }),
+        );
+        let mut tracker = Tracker::builder()
+            .namespace("ns")
+            .app_id("app_id")
+            .emitter(emitter)
+            .subject(Subject::builder().user_id("user").build().unwrap())
+            .config(TrackerConfig {
+                platform: "srv".to_string(),
+                environment: Some(SelfDescribingJson::new(
+                    ENVIRONMENT_SCHEMA,
+                    json!({ "name": "staging" }),
+                )),
+                ..TrackerConfig::default()
+            })
+            .global_context(build_context.clone())
+            .enable_session(SessionTracker::new())
+            .clock(FixedClock::new(
+                Utc.timestamp_millis_opt(1_700_000_000_123).unwrap(),
+            ))
+            .build()
+            .unwrap();
+
+        assert_eq!(tracker.namespace(), "ns");
+        assert_eq!(tracker.app_id(), "app_id");
+        assert_eq!(tracker.platform(), "srv");
+        assert_eq!(tracker.subject().user_id.as_deref(), Some("user"));
+        tracker.track(structured_event("add"), None).unwrap();
+
+        let payloads = payloads.lock().unwrap();
+        let event = serde_json::to_value(&payloads[0]).unwrap();
+        assert_eq!(event["aid"], "app_id");
+        assert_eq!(event["p"], "srv");
+        assert_eq!(event["dtm"], "1700000000123");
+        assert_eq!(event["vid"], "1");
+        let schemas: Vec<_> = payloads[0]
+            .co
+            .as_ref()
+            .unwrap()
+            .data
+            .iter()
+            .map(|context| context.schema.as_str())
+            .collect();
+        assert_eq!(
+            schemas,
+            vec![build_context.schema.as_str(), ENVIRONMENT_SCHEMA]
+        );
+    }
+
+    #[test]
+    fn builder_requires_namespace_app_id_and_emitter() {
+        let result = Tracker::builder()
+            .app_id("app_id")
+            .emitter(MockEmitter::new())
+            .build();
+        assert!(matches!(result, Err(Error::BuilderError(_))));
+
+        let result = Tracker::builder()
+            .namespace("ns")
+            .emitter(MockEmitter::new())
+            .build();
+        assert!(matches!(result, Err(Error::BuilderError(_))));
+
+        let result = Tracker::builder().namespace("ns").app_id("app_id").build();
+        assert!(matches!(result, Err(Error::BuilderError(_))));
+    }
 
     #[test]
     fn create_new_tracker() {
@@ -190,7 +1490,7 @@ mod tests {
 
         assert_eq!(tracker.namespace, "test namespace");
         assert_eq!(tracker.app_id, "test app id");
-        assert_eq!(tracker.emitter.collector_url(), "http://example.com/");
+        assert_eq!(tracker.emitter.collector_url(), "http://example.com");
         assert_eq!(tracker.subject.user_id, Some("user_1".to_string()));
         assert_eq!(tracker.config.platform, "pc".to_string());
         assert_eq!(
@@ -234,27 +1534,1371 @@ mod tests {
             Some(
                 Subject::builder()
                     .user_id("user_1")
-                    .ip_address("999.999.999.999")
+                    .ip_address("203.0.113.7")
                     .build()
                     .unwrap(),
             ),
         );
         assert_eq!(tracker.subject.user_id, Some("user_1".to_string()));
-        assert_eq!(
-            tracker.subject.ip_address,
-            Some("999.999.999.999".to_string())
-        );
+        assert_eq!(tracker.subject.ip_address, Some("203.0.113.7".to_string()));
 
         let updated_subject = Subject::builder().user_id("user_2").build().unwrap();
 
         *tracker.subject_mut() = updated_subject.merge(tracker.subject.clone());
 
         assert_eq!(tracker.subject.user_id, Some("user_2".to_string()));
-        assert_eq!(
-            tracker.subject.ip_address,
-            Some("999.999.999.999".to_string())
+        assert_eq!(tracker.subject.ip_address, Some("203.0.113.7".to_string()));
+
+        tracker.close_emitter().unwrap();
+    }
+
+    #[test]
+    fn update_subject_merges_into_tracker_subject() {
+        let mut tracker = Tracker::new(
+            "test namespace",
+            "test app id",
+            BatchEmitter::builder()
+                .collector_url("http://example.com/")
+                .build()
+                .unwrap(),
+            Some(
+                Subject::builder()
+                    .user_id("user_1")
+                    .language("en-gb")
+                    .timezone("Europe/London")
+                    .build()
+                    .unwrap(),
+            ),
         );
 
+        tracker.update_subject(Subject::builder().user_id("user_2").build().unwrap());
+
+        assert_eq!(tracker.subject.user_id, Some("user_2".to_string()));
+        assert_eq!(tracker.subject.language, Some("en-GB".to_string()));
+        assert_eq!(tracker.subject.timezone, Some("Europe/London".to_string()));
+
+        tracker.close_emitter().unwrap();
+    }
+
+    // The schema of the context entity carrying the count of coalesced events in tests
+    const COALESCED_EVENTS_SCHEMA: &str = "iglu:com.acme/coalesced_events/jsonschema/1-0-0";
+
+    // The schema of the environment context entity in tests
+    const ENVIRONMENT_SCHEMA: &str = "iglu:com.acme/environment/jsonschema/1-0-0";
+
+    // The schema of the diagnostic context entity in tests
+    const TRACKER_DIAGNOSTIC_SCHEMA: &str = "iglu:com.acme/tracker_diagnostic/jsonschema/1-0-0";
+
+    #[test]
+    fn coalesces_identical_consecutive_events() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker
+            .set_coalescing_window(Some(Duration::from_secs(60)), COALESCED_EVENTS_SCHEMA)
+            .unwrap();
+
+        let event_ids: Vec<Uuid> = (0..5)
+            .map(|_| tracker.track(structured_event("click"), None).unwrap())
+            .collect();
+        assert!(payloads.lock().unwrap().is_empty());
+        assert!(event_ids.iter().all(|id| *id == event_ids[0]));
+
+        tracker.flush().unwrap();
+
+        let payloads = payloads.lock().unwrap();
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(payloads[0].eid, event_ids[0]);
+
+        let contexts = serde_json::to_value(&payloads[0]).unwrap()["co"].clone();
+        let contexts: serde_json::Value = serde_json::from_str(contexts.as_str().unwrap()).unwrap();
+        assert_eq!(contexts["data"][0]["schema"], COALESCED_EVENTS_SCHEMA);
+        assert_eq!(contexts["data"][0]["data"]["count"], 5);
+    }
+
+    #[test]
+    fn held_coalesced_event_is_sent_when_tracker_is_dropped() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker
+            .set_coalescing_window(Some(Duration::from_secs(60)), COALESCED_EVENTS_SCHEMA)
+            .unwrap();
+
+        tracker.track(structured_event("click"), None).unwrap();
+        assert!(payloads.lock().unwrap().is_empty());
+        drop(tracker);
+
+        assert_eq!(payloads.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn different_event_releases_coalesced_event() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker
+            .set_coalescing_window(Some(Duration::from_secs(60)), COALESCED_EVENTS_SCHEMA)
+            .unwrap();
+
+        tracker.track(structured_event("click"), None).unwrap();
+        tracker.track(structured_event("click"), None).unwrap();
+        tracker.track(structured_event("scroll"), None).unwrap();
+        assert_eq!(payloads.lock().unwrap().len(), 1);
+
+        tracker.close_emitter().unwrap();
+
+        let payloads = payloads.lock().unwrap();
+        assert_eq!(payloads.len(), 2);
+        // A single event is sent as-is, without a count context
+        assert!(serde_json::to_value(&payloads[1])
+            .unwrap()
+            .get("co")
+            .is_none());
+    }
+
+    #[test]
+    fn identical_events_outside_window_are_not_coalesced() {
+        let (mut tracker, payloads) = recording_tracker();
+        let clock = FixedClock::new(Utc.timestamp_millis_opt(1_700_000_000_000).unwrap());
+        tracker.set_clock(clock.clone());
+        tracker
+            .set_coalescing_window(Some(Duration::from_secs(10)), COALESCED_EVENTS_SCHEMA)
+            .unwrap();
+
+        let first_id = tracker.track(structured_event("click"), None).unwrap();
+        clock.advance(chrono::Duration::seconds(11));
+        let second_id = tracker.track(structured_event("click"), None).unwrap();
+
+        assert_ne!(first_id, second_id);
+        assert_eq!(payloads.lock().unwrap().len(), 1);
+
+        tracker
+            .set_coalescing_window(None, COALESCED_EVENTS_SCHEMA)
+            .unwrap();
+        assert_eq!(payloads.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn namespace_header_sent_when_enabled() {
+        let (mut tracker, headers) = header_capturing_tracker(true);
+        tracker.track(structured_event("add"), None).unwrap();
+
+        let headers = headers.recv_timeout(Duration::from_secs(5)).unwrap();
         tracker.close_emitter().unwrap();
+
+        assert_eq!(headers.get("X-SP-Namespace"), Some(&"ns".to_string()));
+    }
+
+    #[test]
+    fn namespace_header_not_sent_by_default() {
+        let (mut tracker, headers) = header_capturing_tracker(false);
+        tracker.track(structured_event("add"), None).unwrap();
+
+        let headers = headers.recv_timeout(Duration::from_secs(5)).unwrap();
+        tracker.close_emitter().unwrap();
+
+        assert!(!headers.contains_key("X-SP-Namespace"));
+    }
+
+    #[test]
+    fn track_with_id_uses_provided_event_id() {
+        let (mut tracker, payloads) = recording_tracker();
+        let event_id = Uuid::parse_str("c0ffee00-1234-4abc-8def-0123456789ab").unwrap();
+
+        let returned_id = tracker
+            .track_with_id(event_id, structured_event("replayed"), None)
+            .unwrap();
+
+        assert_eq!(returned_id, event_id);
+        assert_eq!(payloads.lock().unwrap()[0].eid, event_id);
+
+        let result = tracker.track_with_id(Uuid::nil(), structured_event("nil"), None);
+        assert!(matches!(result, Err(Error::BuilderError(_))));
+        assert_eq!(payloads.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn tracker_version_defaults_to_package_version() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.track(structured_event("default"), None).unwrap();
+        tracker.set_tracker_version("my-wrapper-1.2.3");
+        tracker.track(structured_event("override"), None).unwrap();
+
+        let payloads = payloads.lock().unwrap();
+        let default_tv = serde_json::to_value(&payloads[0]).unwrap()["tv"].clone();
+        assert_eq!(default_tv, format!("rust-{}", env!("CARGO_PKG_VERSION")));
+        assert!(default_tv
+            .as_str()
+            .unwrap()
+            .contains(env!("CARGO_PKG_VERSION")));
+
+        let override_tv = serde_json::to_value(&payloads[1]).unwrap()["tv"].clone();
+        assert_eq!(override_tv, "my-wrapper-1.2.3");
+    }
+
+    #[test]
+    fn numeric_encoding_applied_to_tracked_events() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.set_numeric_encoding(NumericEncoding::Number);
+
+        let event = StructuredEvent::builder()
+            .category("shop")
+            .action("add")
+            .value(3.0)
+            .build()
+            .unwrap();
+        tracker.track(event, None).unwrap();
+
+        let payload = serde_json::to_value(&payloads.lock().unwrap()[0]).unwrap();
+        assert_eq!(payload["se_va"], serde_json::json!(3.0));
+    }
+
+    #[test]
+    fn context_encoding_applied_to_tracked_events() {
+        let context = SelfDescribingJson::new(
+            "iglu:com.acme/page/jsonschema/1-0-0",
+            serde_json::json!({ "name": "basket" }),
+        );
+        let expected = serde_json::json!({
+            "schema": "iglu:com.snowplowanalytics.snowplow/contexts/jsonschema/1-0-1",
+            "data": [{
+                "schema": "iglu:com.acme/page/jsonschema/1-0-0",
+                "data": { "name": "basket" }
+            }]
+        });
+
+        for (encoding, expected) in [
+            (
+                ContextEncoding::String,
+                serde_json::json!(expected.to_string()),
+            ),
+            (ContextEncoding::Object, expected.clone()),
+        ] {
+            let (mut tracker, payloads) = recording_tracker();
+            tracker.set_context_encoding(encoding);
+            tracker
+                .track(structured_event("add"), Some(vec![context.clone()]))
+                .unwrap();
+
+            let payload = payloads.lock().unwrap()[0].clone();
+            assert_eq!(serde_json::to_value(&payload).unwrap()["co"], expected);
+
+            // The `payload_data` body of a POST request keeps the same form
+            let batch = EventBatch::new(Uuid::new_v4(), vec![payload]);
+            let body = serde_json::to_value(batch.as_payload()).unwrap();
+            assert_eq!(body["data"][0]["co"], expected);
+        }
+    }
+
+    #[test]
+    fn context_encoding_applied_to_coalesced_event_count() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.set_context_encoding(ContextEncoding::Object);
+        tracker
+            .set_coalescing_window(Some(Duration::from_secs(60)), COALESCED_EVENTS_SCHEMA)
+            .unwrap();
+
+        tracker.track(structured_event("click"), None).unwrap();
+        tracker.track(structured_event("click"), None).unwrap();
+        tracker.flush().unwrap();
+
+        let payload = serde_json::to_value(&payloads.lock().unwrap()[0]).unwrap();
+        assert_eq!(payload["co"]["data"][0]["schema"], COALESCED_EVENTS_SCHEMA);
+        assert_eq!(payload["co"]["data"][0]["data"]["count"], 2);
+    }
+
+    #[test]
+    fn track_deduped_skips_duplicate_keys_within_window() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.set_dedup_window(Some(Duration::from_secs(60)));
+
+        let first = tracker
+            .track_deduped(structured_event("click"), None, Some("checkout-button"))
+            .unwrap();
+        let second = tracker
+            .track_deduped(structured_event("click"), None, Some("checkout-button"))
+            .unwrap();
+        tracker
+            .track_deduped(structured_event("click"), None, Some("cancel-button"))
+            .unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(payloads.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn track_deduped_uses_event_content_without_key() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.set_dedup_window(Some(Duration::from_secs(60)));
+
+        tracker
+            .track_deduped(structured_event("click"), None, None)
+            .unwrap();
+        tracker
+            .track_deduped(structured_event("click"), None, None)
+            .unwrap();
+        tracker
+            .track_deduped(structured_event("scroll"), None, None)
+            .unwrap();
+
+        assert_eq!(payloads.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn track_deduped_tracks_duplicates_outside_window() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.set_dedup_window(Some(Duration::from_millis(10)));
+
+        let first = tracker
+            .track_deduped(structured_event("click"), None, Some("key"))
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        let second = tracker
+            .track_deduped(structured_event("click"), None, Some("key"))
+            .unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(payloads.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn client_anonymisation_removes_user_identifiers_from_subject() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.update_subject(
+            Subject::builder()
+                .user_id("user_1")
+                .ip_address("0.0.0.0")
+                .build()
+                .unwrap(),
+        );
+        tracker.set_client_anonymisation(true);
+
+        let event = StructuredEvent::builder()
+            .category("shop")
+            .action("add")
+            .subject(Subject::builder().language("en").build().unwrap())
+            .build()
+            .unwrap();
+        tracker.track(event, None).unwrap();
+
+        let payload = serde_json::to_value(&payloads.lock().unwrap()[0]).unwrap();
+        assert!(payload.get("uid").is_none());
+        assert!(payload.get("ip").is_none());
+        assert_eq!(payload["lang"], "en");
+    }
+
+    #[cfg(feature = "schema-validation")]
+    #[test]
+    fn invalid_events_are_not_tracked_with_schema_resolver() {
+        use crate::{SchemaResolver, SelfDescribingEvent};
+
+        let (url, _requests) = mock_collector(|_| {
+            let schema = serde_json::json!({
+                "type": "object",
+                "properties": { "id": { "type": "string" } },
+                "required": ["id"]
+            });
+            (200, schema.to_string())
+        });
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.set_schema_resolver(Some(SchemaResolver::remote(&url)));
+
+        let event = |data| {
+            SelfDescribingEvent::builder()
+                .schema("iglu:com.acme/button_click/jsonschema/1-0-0")
+                .data(data)
+                .build()
+                .unwrap()
+        };
+
+        let valid = tracker.track(event(serde_json::json!({ "id": "buy" })), None);
+        let invalid = tracker.track(event(serde_json::json!({ "id": 1 })), None);
+
+        assert!(valid.is_ok());
+        assert!(matches!(invalid, Err(Error::ValidationError(_))));
+        assert_eq!(payloads.lock().unwrap().len(), 1);
+    }
+
+    #[cfg(feature = "schema-validation")]
+    #[test]
+    fn coalesced_event_count_is_validated_with_schema_resolver() {
+        use crate::SchemaResolver;
+
+        let (url, _requests) = mock_collector(|_| {
+            let schema = serde_json::json!({
+                "type": "object",
+                "properties": { "count": { "type": "string" } },
+                "required": ["count"]
+            });
+            (200, schema.to_string())
+        });
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.set_schema_resolver(Some(SchemaResolver::remote(&url)));
+        tracker
+            .set_coalescing_window(Some(Duration::from_secs(60)), COALESCED_EVENTS_SCHEMA)
+            .unwrap();
+
+        tracker.track(structured_event("click"), None).unwrap();
+        tracker.track(structured_event("click"), None).unwrap();
+
+        assert!(matches!(tracker.flush(), Err(Error::ValidationError(_))));
+        assert!(payloads.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn track_nonblocking_delivers_events_after_flush() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(1000, 50))
+            .build()
+            .unwrap();
+        let mut tracker = Tracker::new("ns", "app_id", emitter, None);
+
+        let mut event_ids = Vec::new();
+        for i in 0..105 {
+            let event_id = tracker
+                .track_nonblocking(structured_event(&format!("action-{i}")), None)
+                .unwrap();
+            event_ids.push(event_id);
+        }
+        tracker.flush().unwrap();
+        tracker.close_emitter().unwrap();
+        drop(tracker);
+
+        let bodies: String = requests.try_iter().map(|request| request.body).collect();
+        for event_id in event_ids {
+            assert!(bodies.contains(&event_id.to_string()));
+        }
+    }
+
+    #[test]
+    fn track_all_sends_events_in_order_in_one_batch() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 10))
+            .build()
+            .unwrap();
+        let mut tracker = Tracker::new("ns", "app_id", emitter, None);
+
+        let events: Vec<EventWithContext> = vec![
+            (Box::new(structured_event("first")), None),
+            (Box::new(structured_event("second")), None),
+            (Box::new(structured_event("third")), None),
+        ];
+        let event_ids = tracker.track_all(events).unwrap();
+        tracker.flush_now().unwrap();
+        tracker.close_emitter().unwrap();
+
+        let requests: Vec<_> = requests.try_iter().collect();
+        assert_eq!(requests.len(), 1);
+
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        let sent = body["data"].as_array().unwrap();
+        let actions: Vec<&str> = sent.iter().map(|e| e["se_ac"].as_str().unwrap()).collect();
+        let sent_ids: Vec<&str> = sent.iter().map(|e| e["eid"].as_str().unwrap()).collect();
+        assert_eq!(actions, vec!["first", "second", "third"]);
+        let event_ids: Vec<String> = event_ids.iter().map(Uuid::to_string).collect();
+        assert_eq!(sent_ids, event_ids);
+    }
+
+    #[test]
+    fn enricher_attaches_computed_context() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.set_subject_provider(|| Subject::builder().user_id("user_1").build().unwrap());
+        tracker.set_enricher(Box::new(|payload| {
+            let user_id = payload
+                .subject
+                .clone()
+                .flatten()
+                .and_then(|subject| subject.user_id)
+                .unwrap_or_default();
+            payload.add_context(SelfDescribingJson::new(
+                "iglu:com.acme/user/jsonschema/1-0-0",
+                serde_json::json!({ "idLength": user_id.len() }),
+            ));
+        }));
+
+        let context = SelfDescribingJson::new(
+            "iglu:com.acme/page/jsonschema/1-0-0",
+            serde_json::json!({ "name": "basket" }),
+        );
+        tracker.track(structured_event("first"), None).unwrap();
+        tracker
+            .track(structured_event("second"), Some(vec![context]))
+            .unwrap();
+
+        let payloads = payloads.lock().unwrap();
+        let contexts: Vec<&Vec<SelfDescribingJson>> = payloads
+            .iter()
+            .map(|payload| &payload.co.as_ref().unwrap().data)
+            .collect();
+
+        assert_eq!(contexts[0].len(), 1);
+        assert_eq!(contexts[0][0].schema, "iglu:com.acme/user/jsonschema/1-0-0");
+        assert_eq!(contexts[0][0].data["idLength"], 6);
+
+        // Appended after the event's own context entities
+        assert_eq!(contexts[1].len(), 2);
+        assert_eq!(contexts[1][1].schema, "iglu:com.acme/user/jsonschema/1-0-0");
+    }
+
+    #[test]
+    fn hash_pii_replaces_subject_fields_with_salted_hash() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.set_subject_provider(|| {
+            Subject::builder()
+                .user_id("user_1")
+                .ip_address("203.0.113.7")
+                .language("en")
+                .build()
+                .unwrap()
+        });
+        tracker.set_hash_pii(
+            HashSet::from([PiiField::UserId, PiiField::IpAddress]),
+            "pepper",
+        );
+
+        tracker.track(structured_event("click"), None).unwrap();
+
+        let payload = serde_json::to_value(&payloads.lock().unwrap()[0]).unwrap();
+        // sha256("pepperuser_1") and sha256("pepper203.0.113.7")
+        assert_eq!(
+            payload["uid"],
+            "c86cf08a7f47f5e09dd929a59156807e557b2e66965f145a65bf520633302343"
+        );
+        assert_eq!(
+            payload["ip"],
+            "8fc212f188c11cc380ea9112da8e6dba4197bb31854882f5e4d07602091e019f"
+        );
+        assert_eq!(payload["lang"], "en");
+    }
+
+    #[test]
+    fn environment_attached_to_every_event() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.set_environment("staging", ENVIRONMENT_SCHEMA);
+
+        let user_context =
+            SelfDescribingJson::new("iglu:com.acme/user/jsonschema/1-0-0", json!({}));
+        tracker.track(structured_event("add"), None).unwrap();
+        tracker
+            .track(structured_event("remove"), Some(vec![user_context]))
+            .unwrap();
+
+        let payloads = payloads.lock().unwrap();
+        assert_eq!(payloads.len(), 2);
+        for payload in payloads.iter() {
+            let environment = payload
+                .co
+                .as_ref()
+                .unwrap()
+                .data
+                .iter()
+                .find(|context| context.schema == ENVIRONMENT_SCHEMA)
+                .unwrap();
+            assert_eq!(environment.data, json!({ "name": "staging" }));
+        }
+    }
+
+    #[test]
+    fn duplicate_context_schemas_are_all_sent_by_default() {
+        let (mut tracker, payloads) = recording_tracker();
+        let product = |sku: &str| {
+            SelfDescribingJson::new(
+                "iglu:com.acme/product/jsonschema/1-0-0",
+                json!({ "sku": sku }),
+            )
+        };
+
+        tracker
+            .track(
+                structured_event("add"),
+                Some(vec![product("sku-1"), product("sku-2")]),
+            )
+            .unwrap();
+
+        let payloads = payloads.lock().unwrap();
+        let skus: Vec<_> = payloads[0]
+            .co
+            .as_ref()
+            .unwrap()
+            .data
+            .iter()
+            .map(|context| context.data["sku"].clone())
+            .collect();
+        assert_eq!(skus, vec!["sku-1", "sku-2"]);
+    }
+
+    #[test]
+    fn duplicate_context_schemas_are_sent_once() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.set_environment("staging", ENVIRONMENT_SCHEMA);
+        let event_environment =
+            || SelfDescribingJson::new(ENVIRONMENT_SCHEMA, json!({ "name": "production" }));
+
+        tracker.set_context_deduplication(ContextDeduplication::FirstWins);
+        tracker
+            .track(structured_event("first"), Some(vec![event_environment()]))
+            .unwrap();
+        tracker.set_context_deduplication(ContextDeduplication::LastWins);
+        tracker
+            .track(structured_event("last"), Some(vec![event_environment()]))
+            .unwrap();
+
+        let payloads = payloads.lock().unwrap();
+        let environments: Vec<_> = payloads
+            .iter()
+            .map(|payload| {
+                let contexts = &payload.co.as_ref().unwrap().data;
+                assert_eq!(contexts.len(), 1);
+                contexts[0].data["name"].clone()
+            })
+            .collect();
+        assert_eq!(environments, vec!["production", "staging"]);
+    }
+
+    #[test]
+    fn events_can_opt_out_of_default_contexts() {
+        // A diagnostic event, which shouldn't carry the tracker's contexts
+        struct DiagnosticEvent(StructuredEvent);
+
+        impl PayloadAddable for DiagnosticEvent {
+            fn add_to_payload(self, payload_builder: PayloadBuilder) -> PayloadBuilder {
+                self.0.add_to_payload(payload_builder)
+            }
+
+            fn subject(&self) -> &Option<Subject> {
+                self.0.subject()
+            }
+
+            fn attach_default_contexts(&self) -> bool {
+                false
+            }
+        }
+
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.set_environment("staging", ENVIRONMENT_SCHEMA);
+        let diagnostic_context = SelfDescribingJson::new(
+            "iglu:com.acme/diagnostic/jsonschema/1-0-0",
+            json!({ "check": "startup" }),
+        );
+
+        tracker
+            .track(DiagnosticEvent(structured_event("diagnostic")), None)
+            .unwrap();
+        tracker
+            .track(
+                Box::new(DiagnosticEvent(structured_event("boxed")))
+                    as Box<dyn crate::EventBuildable>,
+                Some(vec![diagnostic_context.clone()]),
+            )
+            .unwrap();
+        tracker.track(structured_event("add"), None).unwrap();
+
+        let payloads = payloads.lock().unwrap();
+        assert!(payloads[0].co.is_none());
+        let contexts = &payloads[1].co.as_ref().unwrap().data;
+        assert_eq!(contexts.len(), 1);
+        assert_eq!(contexts[0].schema, diagnostic_context.schema);
+        assert_eq!(
+            payloads[2].co.as_ref().unwrap().data[0].schema,
+            ENVIRONMENT_SCHEMA
+        );
+    }
+
+    #[test]
+    fn field_limits_truncate_tracked_events() {
+        let (mut tracker, payloads) = recording_tracker();
+        let truncations = Arc::new(Mutex::new(0));
+        let truncation_count = truncations.clone();
+        tracker.set_field_limits(
+            FieldLimits::new()
+                .limit("se_la", 10)
+                .on_truncate(move |_, _, _| *truncation_count.lock().unwrap() += 1),
+        );
+
+        let event = StructuredEvent::builder()
+            .category("shop")
+            .action("add")
+            .label("x".repeat(1000))
+            .build()
+            .unwrap();
+        tracker.track(event, None).unwrap();
+
+        let payload = serde_json::to_value(&payloads.lock().unwrap()[0]).unwrap();
+        assert_eq!(payload["se_la"], "x".repeat(10));
+        assert_eq!(*truncations.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn subject_provider_is_called_for_each_event() {
+        let (mut tracker, payloads) = recording_tracker();
+        let current_user = Arc::new(Mutex::new("user_1".to_string()));
+        let provider_user = current_user.clone();
+        tracker.set_subject_provider(move || {
+            Subject::builder()
+                .user_id(provider_user.lock().unwrap().clone())
+                .build()
+                .unwrap()
+        });
+
+        tracker.track(structured_event("first"), None).unwrap();
+        *current_user.lock().unwrap() = "user_2".to_string();
+        tracker.track(structured_event("second"), None).unwrap();
+
+        let user_ids: Vec<_> = payloads
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|payload| serde_json::to_value(payload).unwrap()["uid"].clone())
+            .collect();
+        assert_eq!(user_ids, vec!["user_1", "user_2"]);
+    }
+
+    #[test]
+    fn event_subject_takes_priority_over_subject_provider() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.set_subject_provider(|| {
+            Subject::builder()
+                .user_id("provided")
+                .language("en")
+                .build()
+                .unwrap()
+        });
+
+        let event = StructuredEvent::builder()
+            .category("shop")
+            .action("add")
+            .subject(Subject::builder().user_id("event").build().unwrap())
+            .build()
+            .unwrap();
+        tracker.track(event, None).unwrap();
+
+        let payload = serde_json::to_value(&payloads.lock().unwrap()[0]).unwrap();
+        assert_eq!(payload["uid"], "event");
+        assert_eq!(payload["lang"], "en");
+    }
+
+    #[test]
+    fn session_tracker_starts_new_session_after_max_events() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.set_session_tracker(SessionTracker::new().max_events_per_session(2));
+
+        for action in ["first", "second", "third"] {
+            tracker.track(structured_event(action), None).unwrap();
+        }
+
+        let sessions: Vec<_> = payloads
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|payload| {
+                let payload = serde_json::to_value(payload).unwrap();
+                (payload["sid"].clone(), payload["vid"].clone())
+            })
+            .collect();
+        assert_eq!(sessions[0], sessions[1]);
+        assert_eq!(sessions[1].1, "1");
+        assert_ne!(sessions[2].0, sessions[0].0);
+        assert_eq!(sessions[2].1, "2");
+    }
+
+    #[test]
+    fn event_true_timestamp_takes_priority_over_source() {
+        let (mut tracker, payloads) = recording_tracker();
+        let source_timestamp = DateTime::from_timestamp_millis(1_600_000_000_000).unwrap();
+        let event_timestamp = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+        tracker.set_true_timestamp_source(move || source_timestamp);
+
+        let event = StructuredEvent::builder()
+            .category("shop")
+            .action("add")
+            .true_tstamp(event_timestamp)
+            .build()
+            .unwrap();
+        tracker.track(event, None).unwrap();
+        tracker
+            .track(structured_event("no-timestamp"), None)
+            .unwrap();
+
+        let payloads = payloads.lock().unwrap();
+        assert_eq!(payloads[0].ttm, Some(event_timestamp));
+        assert_eq!(payloads[1].ttm, Some(source_timestamp));
+    }
+
+    #[test]
+    fn true_timestamp_unset_without_source() {
+        let (mut tracker, payloads) = recording_tracker();
+
+        tracker.track(structured_event("add"), None).unwrap();
+
+        assert!(payloads.lock().unwrap()[0].ttm.is_none());
+    }
+
+    #[test]
+    fn structured_fast_path_matches_generic_path() {
+        let (mut tracker, payloads) = recording_tracker();
+        *tracker.subject_mut() = Subject::builder().language("en").build().unwrap();
+        tracker.set_environment("test", ENVIRONMENT_SCHEMA);
+        tracker.set_true_timestamp_source(Utc::now);
+
+        let event = StructuredEvent::builder()
+            .category("shop")
+            .action("add-to-basket")
+            .label("shoes")
+            .property("size")
+            .value(42.5)
+            .subject(Subject::builder().user_id("user_1").build().unwrap())
+            .build()
+            .unwrap();
+        let context = vec![SelfDescribingJson::new(
+            "iglu:com.acme/page/jsonschema/1-0-0",
+            json!({ "name": "basket" }),
+        )];
+        tracker.track(event.clone(), Some(context.clone())).unwrap();
+        tracker.track_structured(event, Some(context)).unwrap();
+
+        // Everything but the event ID and timestamps should match
+        let payloads: Vec<_> = payloads
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|payload| {
+                let mut payload = serde_json::to_value(payload).unwrap();
+                let fields = payload.as_object_mut().unwrap();
+                for field in ["eid", "dtm", "stm", "ttm"] {
+                    assert!(fields.remove(field).is_some());
+                }
+                payload
+            })
+            .collect();
+        assert_eq!(payloads[0], payloads[1]);
+        assert_eq!(payloads[0]["uid"], "user_1");
+        assert_eq!(payloads[0]["lang"], "en");
+        assert_eq!(payloads[0]["se_va"], "42.5");
+    }
+
+    #[test]
+    fn gdpr_context_is_attached_to_every_event() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.enable_gdpr_context(
+            GdprContext::builder()
+                .basis_for_processing("consent")
+                .document_id("privacy-policy")
+                .build()
+                .unwrap(),
+        );
+
+        tracker.track(structured_event("first"), None).unwrap();
+        tracker.disable_gdpr_context();
+        tracker.track(structured_event("second"), None).unwrap();
+
+        let payloads = payloads.lock().unwrap();
+        let context = payloads[0].co.as_ref().unwrap();
+        assert_eq!(context.data.len(), 1);
+        assert_eq!(context.data[0].schema, crate::gdpr_context::GDPR_SCHEMA);
+        assert_eq!(context.data[0].data["basisForProcessing"], "consent");
+        assert!(payloads[1].co.is_none());
+    }
+
+    #[test]
+    fn empty_subject_is_treated_as_absent() {
+        let (mut tracker, payloads) = recording_tracker();
+
+        let event = StructuredEvent::builder()
+            .category("shop")
+            .action("add")
+            .subject(Subject::default())
+            .build()
+            .unwrap();
+        tracker.track(event, None).unwrap();
+
+        let payloads = payloads.lock().unwrap();
+        assert!(payloads[0].subject.is_none());
+        let payload = serde_json::to_value(&payloads[0]).unwrap();
+        let mut keys: Vec<_> = payload.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec!["aid", "dtm", "e", "eid", "p", "se_ac", "se_ca", "stm", "tv"]
+        );
+    }
+
+    #[test]
+    fn event_subject_overrides_tracker_subject_for_that_event_only() {
+        let (mut tracker, payloads) = recording_tracker();
+        *tracker.subject_mut() = Subject::builder().user_id("tracker_user").build().unwrap();
+
+        let event = SelfDescribingEvent::builder()
+            .schema("iglu:com.acme/button_click/jsonschema/1-0-0")
+            .data(json!({ "id": "buy" }))
+            .subject(Subject::builder().user_id("event_user").build().unwrap())
+            .build()
+            .unwrap();
+        tracker.track(event, None).unwrap();
+
+        let event = StructuredEvent::builder()
+            .category("shop")
+            .action("add")
+            .subject(Subject::builder().language("en").build().unwrap())
+            .build()
+            .unwrap();
+        tracker.track(event, None).unwrap();
+        tracker.track(structured_event("remove"), None).unwrap();
+
+        let payloads: Vec<_> = payloads
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|payload| serde_json::to_value(payload).unwrap())
+            .collect();
+        assert_eq!(payloads[0]["uid"], "event_user");
+        assert_eq!(payloads[1]["uid"], "tracker_user");
+        assert_eq!(payloads[1]["lang"], "en");
+        assert_eq!(payloads[2]["uid"], "tracker_user");
+        assert!(payloads[2].get("lang").is_none());
+        assert_eq!(tracker.subject().user_id.as_deref(), Some("tracker_user"));
+    }
+
+    #[test]
+    fn call_subject_overrides_event_and_tracker_subjects() {
+        let (mut tracker, payloads) = recording_tracker();
+        *tracker.subject_mut() = Subject::builder()
+            .user_id("tracker_user")
+            .language("en")
+            .timezone("Europe/London")
+            .build()
+            .unwrap();
+
+        let event = StructuredEvent::builder()
+            .category("shop")
+            .action("add")
+            .subject(
+                Subject::builder()
+                    .user_id("event_user")
+                    .language("sv")
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+        let call_subject = Subject::builder().user_id("call_user").build().unwrap();
+        tracker
+            .track_with_subject(event, None, call_subject.clone())
+            .unwrap();
+        tracker
+            .track_with_subject(structured_event("no_event_subject"), None, call_subject)
+            .unwrap();
+
+        let payloads: Vec<_> = payloads
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|payload| serde_json::to_value(payload).unwrap())
+            .collect();
+        assert_eq!(payloads[0]["uid"], "call_user");
+        assert_eq!(payloads[0]["lang"], "sv");
+        assert_eq!(payloads[0]["tz"], "Europe/London");
+        assert_eq!(payloads[1]["uid"], "call_user");
+        assert_eq!(payloads[1]["lang"], "en");
+        assert_eq!(tracker.subject().user_id.as_deref(), Some("tracker_user"));
+    }
+
+    #[test]
+    fn desktop_context_is_attached_to_every_event() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.set_desktop_context(
+            DesktopContext::builder()
+                .os_type("macOS")
+                .os_version("14.0")
+                .build()
+                .unwrap(),
+        );
+
+        tracker.track(structured_event("first"), None).unwrap();
+        tracker.track(structured_event("second"), None).unwrap();
+
+        for payload in payloads.lock().unwrap().iter() {
+            let context = payload.co.as_ref().unwrap();
+            assert_eq!(
+                context.data[0].schema,
+                crate::desktop_context::DESKTOP_CONTEXT_SCHEMA
+            );
+            assert_eq!(context.data[0].data["osType"], "macOS");
+        }
+    }
+
+    #[test]
+    fn application_context_is_attached_to_every_event() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.set_application_context(
+            ApplicationContext::builder()
+                .version("2.1.0")
+                .build_name("2104")
+                .build()
+                .unwrap(),
+        );
+
+        tracker.track(structured_event("first"), None).unwrap();
+        tracker.track(structured_event("second"), None).unwrap();
+
+        for payload in payloads.lock().unwrap().iter() {
+            let context = payload.co.as_ref().unwrap();
+            assert_eq!(
+                context.data[0].schema,
+                crate::application_context::APPLICATION_CONTEXT_SCHEMA
+            );
+            assert_eq!(context.data[0].data["version"], "2.1.0");
+            assert_eq!(context.data[0].data["build"], "2104");
+        }
+    }
+
+    #[test]
+    fn track_event_returns_the_sent_payload() {
+        let tracker_subject = Subject::builder().user_id("user_1").build().unwrap();
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.update_subject(tracker_subject);
+        tracker.set_environment("staging", ENVIRONMENT_SCHEMA);
+
+        let event = StructuredEvent::builder()
+            .category("shop")
+            .action("add")
+            .subject(Subject::builder().language("en-gb").build().unwrap())
+            .build()
+            .unwrap();
+        let context = SelfDescribingJson::new("iglu:com.acme/ctx/jsonschema/1-0-0", json!({}));
+
+        let tracked = tracker.track_event(event, Some(vec![context])).unwrap();
+
+        assert_eq!(tracked.event_id, tracked.payload.eid);
+
+        let subject = tracked.payload.subject.as_ref().unwrap();
+        assert_eq!(subject.user_id, Some("user_1".to_string()));
+        assert_eq!(subject.language, Some("en-GB".to_string()));
+
+        let schemas: Vec<&str> = tracked
+            .payload
+            .co
+            .as_ref()
+            .unwrap()
+            .data
+            .iter()
+            .map(|context| context.schema.as_str())
+            .collect();
+        assert_eq!(
+            schemas,
+            vec!["iglu:com.acme/ctx/jsonschema/1-0-0", ENVIRONMENT_SCHEMA]
+        );
+
+        let sent = &payloads.lock().unwrap()[0];
+        assert_eq!(sent.eid, tracked.event_id);
+        assert_eq!(
+            serde_json::to_value(&sent.co).unwrap(),
+            serde_json::to_value(&tracked.payload.co).unwrap()
+        );
+    }
+
+    #[test]
+    fn fixed_clock_sets_event_timestamps() {
+        let clock = FixedClock::new(Utc.timestamp_millis_opt(1_700_000_000_123).unwrap());
+        let emitter = MockEmitter::with_clock(clock.clone());
+        let mut tracker = Tracker::new("ns", "app_id", emitter.clone(), None);
+        tracker.set_clock(clock.clone());
+
+        tracker.track(structured_event("first"), None).unwrap();
+        clock.advance(chrono::Duration::milliseconds(1_000));
+        tracker.track(structured_event("second"), None).unwrap();
+
+        let payloads: Vec<serde_json::Value> = emitter
+            .payloads()
+            .iter()
+            .map(|payload| serde_json::to_value(payload).unwrap())
+            .collect();
+        assert_eq!(payloads[0]["dtm"], "1700000000123");
+        assert_eq!(payloads[0]["stm"], "1700000000123");
+        assert_eq!(payloads[1]["dtm"], "1700000001123");
+        assert_eq!(payloads[1]["stm"], "1700000001123");
+    }
+
+    #[test]
+    fn sent_timestamp_is_set_when_flushed() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 5))
+            .build()
+            .unwrap();
+        let mut tracker = Tracker::new("ns", "app_id", emitter, None);
+
+        tracker.track(structured_event("queued"), None).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        tracker.flush_now().unwrap();
+        tracker.close_emitter().unwrap();
+        drop(tracker);
+
+        let body: serde_json::Value =
+            serde_json::from_str(&requests.try_recv().unwrap().body).unwrap();
+        let timestamp =
+            |key: &str| -> i64 { body["data"][0][key].as_str().unwrap().parse().unwrap() };
+        assert!(timestamp("stm") >= timestamp("dtm") + 50);
+    }
+
+    fn raw_payload() -> PayloadBuilder {
+        Payload::builder()
+            .p("srv".to_string())
+            .tv("other-tracker-1.0".to_string())
+            .eid(Uuid::new_v4())
+            .dtm(Utc.timestamp_millis_opt(1_600_000_000_000).unwrap())
+            .stm(Utc.timestamp_millis_opt(1_600_000_000_000).unwrap())
+            .aid("migrated_app".to_string())
+    }
+
+    #[test]
+    fn track_payload_sends_a_prebuilt_payload() {
+        let (url, requests) = mock_collector(|_| (200, String::new()));
+        let emitter = BatchEmitter::builder()
+            .collector_url(&url)
+            .event_store(InMemoryEventStore::new(10, 5))
+            .build()
+            .unwrap();
+        let mut tracker = Tracker::new("ns", "app_id", emitter, None);
+
+        let payload = raw_payload()
+            .e(EventType::StructuredEvent)
+            .structured_event(structured_event("replayed"))
+            .build()
+            .unwrap();
+        let event_id = tracker.track_payload(payload).unwrap();
+        tracker.flush_now().unwrap();
+        tracker.close_emitter().unwrap();
+        drop(tracker);
+
+        let body: serde_json::Value =
+            serde_json::from_str(&requests.try_recv().unwrap().body).unwrap();
+        let event = &body["data"][0];
+        assert_eq!(event["eid"], event_id.to_string());
+        assert_eq!(event["e"], "se");
+        assert_eq!(event["se_ac"], "replayed");
+        assert_eq!(event["aid"], "migrated_app");
+        assert_eq!(event["tv"], "other-tracker-1.0");
+        assert_eq!(event["dtm"], "1600000000000");
+        assert_ne!(event["stm"], "1600000000000");
+    }
+
+    #[test]
+    fn track_payload_requires_event_type_and_data() {
+        let (mut tracker, payloads) = recording_tracker();
+
+        let without_type = raw_payload().build().unwrap();
+        let without_data = raw_payload().e(EventType::StructuredEvent).build().unwrap();
+        let without_platform = raw_payload()
+            .p(String::new())
+            .e(EventType::StructuredEvent)
+            .structured_event(structured_event("replayed"))
+            .build()
+            .unwrap();
+
+        for payload in [without_type, without_data, without_platform] {
+            assert!(matches!(
+                tracker.track_payload(payload),
+                Err(Error::BuilderError(_))
+            ));
+        }
+        assert!(payloads.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn track_payload_sends_the_held_coalesced_event_first() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker
+            .set_coalescing_window(Some(Duration::from_secs(60)), COALESCED_EVENTS_SCHEMA)
+            .unwrap();
+
+        let held_id = tracker.track(structured_event("click"), None).unwrap();
+        let payload = raw_payload()
+            .e(EventType::StructuredEvent)
+            .structured_event(structured_event("click"))
+            .build()
+            .unwrap();
+        let replayed_id = tracker.track_payload(payload).unwrap();
+
+        let payloads = payloads.lock().unwrap();
+        let event_ids: Vec<Uuid> = payloads.iter().map(|payload| payload.eid).collect();
+        assert_eq!(event_ids, vec![held_id, replayed_id]);
+    }
+
+    #[test]
+    fn platform_contexts_attach_to_tracked_events() {
+        use crate::desktop_context::DESKTOP_CONTEXT_SCHEMA;
+        use crate::geolocation_context::GEOLOCATION_CONTEXT_SCHEMA;
+        use crate::mobile_context::MOBILE_CONTEXT_SCHEMA;
+
+        let emitter = MockEmitter::new();
+        let platform_contexts = PlatformContexts::new()
+            .session(SessionTracker::new())
+            .mobile(
+                MobileContext::builder()
+                    .os_type("android")
+                    .os_version("14")
+                    .device_manufacturer("Acme")
+                    .device_model("Phone 3")
+                    .build()
+                    .unwrap(),
+            )
+            .desktop(
+                DesktopContext::builder()
+                    .os_type("Linux")
+                    .os_version("6.1")
+                    .build()
+                    .unwrap(),
+            )
+            .geolocation(
+                GeolocationContext::builder()
+                    .latitude(59.33)
+                    .longitude(18.07)
+                    .build()
+                    .unwrap(),
+            );
+        let mut tracker = Tracker::new("ns", "app_id", emitter.clone(), None)
+            .with_platform_contexts(platform_contexts);
+
+        tracker.track(structured_event("click"), None).unwrap();
+
+        let payload = &emitter.payloads()[0];
+        let schemas: Vec<&str> = payload
+            .co
+            .as_ref()
+            .unwrap()
+            .data
+            .iter()
+            .map(|context| context.schema.as_str())
+            .collect();
+        assert_eq!(
+            schemas,
+            vec![
+                DESKTOP_CONTEXT_SCHEMA,
+                MOBILE_CONTEXT_SCHEMA,
+                GEOLOCATION_CONTEXT_SCHEMA
+            ]
+        );
+
+        let subject = payload.subject.as_ref().unwrap();
+        assert!(subject.session_user_id.is_some());
+        assert_eq!(subject.domain_session_index, Some(1));
+    }
+
+    #[test]
+    fn diagnostic_context_is_attached_once_enabled() {
+        let (mut tracker, payloads) = recording_tracker();
+        tracker.set_tracker_version("rust-test");
+
+        tracker.track(structured_event("before"), None).unwrap();
+        tracker.enable_diagnostic_context(TRACKER_DIAGNOSTIC_SCHEMA);
+        tracker.track(structured_event("enabled"), None).unwrap();
+        tracker.disable_diagnostic_context();
+        tracker.track(structured_event("after"), None).unwrap();
+
+        let payloads = payloads.lock().unwrap();
+        assert!(payloads[0].co.is_none());
+        assert!(payloads[2].co.is_none());
+        let contexts = &payloads[1].co.as_ref().unwrap().data;
+        assert_eq!(contexts.len(), 1);
+        assert_eq!(contexts[0].schema, TRACKER_DIAGNOSTIC_SCHEMA);
+        assert_eq!(contexts[0].data["namespace"], "ns");
+        assert_eq!(contexts[0].data["trackerVersion"], "rust-test");
+        assert_eq!(contexts[0].data["bufferedEvents"], 0);
+    }
+
+    #[test]
+    fn preview_returns_payload_without_tracking_it() {
+        let (tracker, payloads) = recording_tracker();
+        let context = SelfDescribingJson::new(
+            "iglu:com.acme/user/jsonschema/1-0-0",
+            json!({ "id": "user_1" }),
+        );
+
+        let structured = tracker
+            .preview(structured_event("add"), Some(vec![context]))
+            .unwrap();
+        let self_describing = tracker
+            .preview(
+                SelfDescribingEvent::builder()
+                    .schema("iglu:com.acme/button_click/jsonschema/1-0-0")
+                    .data(json!({ "id": "buy" }))
+                    .build()
+                    .unwrap(),
+                None,
+            )
+            .unwrap();
+
+        assert!(payloads.lock().unwrap().is_empty());
+        assert!(structured.contains('\n'));
+        let structured: serde_json::Value = serde_json::from_str(&structured).unwrap();
+        assert_eq!(structured["e"], "se");
+        assert_eq!(structured["se_ac"], "add");
+        assert!(structured["co"].as_str().unwrap().contains("user_1"));
+        let self_describing: serde_json::Value = serde_json::from_str(&self_describing).unwrap();
+        assert_eq!(self_describing["e"], "ue");
+        assert_eq!(self_describing["aid"], tracker.app_id());
+        assert!(self_describing["ue_pr"]
+            .as_str()
+            .unwrap()
+            .contains("iglu:com.acme/button_click/jsonschema/1-0-0"));
+    }
+
+    #[test]
+    fn platform_can_be_overridden_per_event() {
+        let (mut tracker, payloads) = recording_tracker();
+
+        tracker
+            .track_with_platform(structured_event("relayed"), None, "mob")
+            .unwrap();
+        tracker.track(structured_event("own"), None).unwrap();
+
+        let payloads: Vec<serde_json::Value> = payloads
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|payload| serde_json::to_value(payload).unwrap())
+            .collect();
+        assert_eq!(payloads[0]["p"], "mob");
+        assert_eq!(payloads[1]["p"], tracker.platform());
+    }
+
+    #[test]
+    fn track_with_contexts_accepts_any_iterator() {
+        let (mut tracker, payloads) = recording_tracker();
+        let contexts = [
+            SelfDescribingJson::new("iglu:com.acme/user/jsonschema/1-0-0", json!({ "id": 1 })),
+            SelfDescribingJson::new(
+                "iglu:com.acme/page/jsonschema/1-0-0",
+                json!({ "path": "/" }),
+            ),
+        ];
+
+        tracker
+            .track_with_contexts(structured_event("slice"), contexts[..].iter().cloned())
+            .unwrap();
+        tracker
+            .track_with_contexts(structured_event("single"), Some(contexts[0].clone()))
+            .unwrap();
+        tracker
+            .track_with_contexts(structured_event("none"), [])
+            .unwrap();
+
+        let payloads = payloads.lock().unwrap();
+        let schemas = |payload: &Payload| -> Vec<String> {
+            payload
+                .co
+                .as_ref()
+                .unwrap()
+                .data
+                .iter()
+                .map(|context| context.schema.clone())
+                .collect()
+        };
+        assert_eq!(
+            schemas(&payloads[0]),
+            vec![contexts[0].schema.clone(), contexts[1].schema.clone()]
+        );
+        assert_eq!(schemas(&payloads[1]), vec![contexts[0].schema.clone()]);
+        assert!(payloads[2].co.is_none());
     }
 }
@@ -90,7 +90,7 @@ async fn track_event_with_subject() {
 
     assert_eq!("user_1", event["user_id"]);
     assert_eq!("Europe/London", event["os_timezone"]);
-    assert_eq!("en-gb", event["br_lang"]);
+    assert_eq!("en-GB", event["br_lang"]);
     assert_eq!("0.0.0.0", event["user_ipaddress"]);
     assert_eq!("Mozilla/Firefox", event["useragent"]);
     assert_eq!(domain_user_id.to_string(), event["domain_userid"]);
@@ -130,7 +130,7 @@ async fn track_event_with_partial_subject() {
     // Fields sent in Subject
     assert_eq!("user_1".to_string(), event["user_id"]);
     assert_eq!("Europe/London".to_string(), event["os_timezone"]);
-    assert_eq!("en-gb".to_string(), event["br_lang"]);
+    assert_eq!("en-GB".to_string(), event["br_lang"]);
 
     // Fields not sent in Subject, not set by Enrich
     assert_eq!(serde_json::Value::Null, event["useragent"]);